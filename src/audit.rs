@@ -0,0 +1,157 @@
+//! Append-only audit log of tool calls, written as JSON lines and optionally sent to syslog.
+//!
+//! Every tool call produces one record with the client's identity, the tool, its arguments, the debugger commands
+//! it ran with the policy decision taken for each, and the outcome. Debugger output is not recorded.
+use std::cell::RefCell;
+use std::future::Future;
+use std::io::{Error, Write};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Syslog socket of the local host.
+const SYSLOG_SOCKET: &str = "/dev/log";
+/// Syslog priority of audit records: facility authpriv, severity info.
+const SYSLOG_PRIORITY: u8 = 10 * 8 + 6;
+
+/// Audit log settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Write the audit log. Defaults to on for the http transport and off for stdio.
+    pub enabled: Option<bool>,
+    /// File the records are appended to, see [`default_path`] for the default.
+    pub file: Option<PathBuf>,
+    /// Also send every record to the local syslog.
+    pub syslog: bool,
+}
+
+/// Default audit log location: `$XDG_STATE_HOME/dbgmcp/audit.jsonl`, falling back to `~/.local/state`.
+pub fn default_path() -> PathBuf {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+        .unwrap_or_default();
+    state.join("dbgmcp").join("audit.jsonl")
+}
+
+tokio::task_local! {
+    static DECISIONS: RefCell<Vec<Decision>>;
+}
+
+/// Policy decision taken during a tool call.
+#[derive(Clone, Debug)]
+pub struct Decision {
+    /// Debugger command the decision is about, as sent to the debugger. `None` for decisions about paths.
+    pub command: Option<String>,
+    pub decision: String,
+}
+
+/// Note a policy decision taken for the tool call running in the current task, see [`track_decisions`].
+pub fn add_decision<S: Into<String>>(command: Option<&str>, decision: S) {
+    let _ = DECISIONS.try_with(|decisions| {
+        decisions.borrow_mut().push(Decision {
+            command: command.map(str::to_owned),
+            decision: decision.into(),
+        })
+    });
+}
+
+/// Run a tool call and return its output together with the policy decisions it noted, in order.
+pub async fn track_decisions<F: Future>(call: F) -> (F::Output, Vec<Decision>) {
+    DECISIONS
+        .scope(RefCell::new(Vec::new()), async {
+            let output = call.await;
+            (output, DECISIONS.with(|decisions| decisions.take()))
+        })
+        .await
+}
+
+/// Destination of audit records. The default log is disabled and drops every record.
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    sink: Option<Arc<AuditSink>>,
+}
+
+struct AuditSink {
+    file: Mutex<std::fs::File>,
+    syslog: Option<UnixDatagram>,
+    host: String,
+}
+
+impl AuditLog {
+    /// Open an audit log appending to `path`, creating the file and its directory if needed.
+    pub fn open<P: AsRef<Path>>(path: P, syslog: bool) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let syslog = if syslog {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(SYSLOG_SOCKET)?;
+            Some(socket)
+        } else {
+            None
+        };
+        Ok(Self {
+            sink: Some(Arc::new(AuditSink {
+                file: Mutex::new(file),
+                syslog,
+                host: hostname(),
+            })),
+        })
+    }
+
+    /// Whether records are written anywhere.
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Append a record, adding the time and host name to its fields.
+    pub fn record(&self, fields: Map<String, Value>) -> Result<(), Error> {
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+        let mut record = Map::new();
+        record.insert("time".to_owned(), chrono::Utc::now().to_rfc3339().into());
+        record.insert("host".to_owned(), sink.host.clone().into());
+        record.extend(fields);
+        let line = Value::Object(record).to_string();
+
+        {
+            let mut file = sink.file.lock().unwrap();
+            file.write_all(format!("{}\n", line).as_bytes())?;
+            file.flush()?;
+        }
+        if let Some(syslog) = &sink.syslog {
+            let message = format!(
+                "<{}>dbgmcp[{}]: {}",
+                SYSLOG_PRIORITY,
+                std::process::id(),
+                line
+            );
+            syslog.send(message.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return "unknown".to_owned();
+    }
+    let length = buffer.iter().position(|byte| *byte == 0).unwrap_or(0);
+    String::from_utf8_lossy(&buffer[..length]).into_owned()
+}
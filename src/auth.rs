@@ -0,0 +1,142 @@
+//! Bearer token authentication for the networked transports.
+use std::path::Path;
+
+use crate::server::DEFAULT_NAMESPACE;
+
+/// Environment variable holding a single token for the default namespace.
+pub const TOKEN_ENV: &str = "DBGMCP_AUTH_TOKEN";
+
+/// Accepted bearer tokens. Each token is tied to a session namespace, so clients using
+/// different tokens cannot see or touch each other's debugger sessions.
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    tokens: Vec<(String, String)>,
+}
+
+impl Auth {
+    /// Accept `token` for clients of `namespace`.
+    pub fn add<N: Into<String>, T: Into<String>>(&mut self, namespace: N, token: T) {
+        self.tokens.push((namespace.into(), token.into()));
+    }
+
+    /// Load tokens from a file with one `namespace:token` entry per line.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}. [Error]: {}", path.display(), err))?;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(':') {
+                Some((namespace, token)) if !namespace.is_empty() && !token.is_empty() => {
+                    self.add(namespace, token)
+                }
+                _ => {
+                    return Err(format!(
+                        "{}:{}: expected 'namespace:token'",
+                        path.display(),
+                        number + 1
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Accept the token from [`TOKEN_ENV`], if set, for the default namespace.
+    pub fn load_env(&mut self) {
+        if let Ok(token) = std::env::var(TOKEN_ENV)
+            && !token.is_empty()
+        {
+            self.add(DEFAULT_NAMESPACE, token);
+        }
+    }
+
+    /// Whether any token is configured. Without tokens, every client is let in.
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Resolve the namespace of a client from its `Authorization` header value.
+    /// Returns `None` if authentication is enabled and the credentials are missing or wrong.
+    pub fn authenticate(&self, authorization: Option<&str>) -> Option<&str> {
+        if !self.is_enabled() {
+            return Some(DEFAULT_NAMESPACE);
+        }
+        let presented = authorization?
+            .strip_prefix("Bearer ")
+            .or_else(|| authorization?.strip_prefix("bearer "))?
+            .trim();
+        self.tokens
+            .iter()
+            .find(|(_, token)| constant_time_eq(token.as_bytes(), presented.as_bytes()))
+            .map(|(namespace, _)| namespace.as_str())
+    }
+}
+
+/// Compare secrets without leaking the position of the first mismatch through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> Auth {
+        let mut auth = Auth::default();
+        auth.add("alice", "3f9c0a");
+        auth.add("ci", "a71be2");
+        auth
+    }
+
+    #[test]
+    fn tokens_resolve_their_namespace() {
+        let auth = auth();
+        assert_eq!(auth.authenticate(Some("Bearer 3f9c0a")), Some("alice"));
+        assert_eq!(auth.authenticate(Some("bearer a71be2 ")), Some("ci"));
+        assert_eq!(auth.authenticate(Some("Bearer 3f9c0b")), None);
+        assert_eq!(auth.authenticate(Some("Bearer 3f9c0")), None);
+        assert_eq!(auth.authenticate(Some("Bearer ")), None);
+        assert_eq!(auth.authenticate(Some("Basic 3f9c0a")), None);
+        assert_eq!(auth.authenticate(Some("3f9c0a")), None);
+        assert_eq!(auth.authenticate(None), None);
+    }
+
+    #[test]
+    fn without_tokens_everyone_is_default() {
+        let auth = Auth::default();
+        assert!(!auth.is_enabled());
+        assert_eq!(auth.authenticate(None), Some(DEFAULT_NAMESPACE));
+        assert_eq!(auth.authenticate(Some("Bearer x")), Some(DEFAULT_NAMESPACE));
+    }
+
+    #[test]
+    fn token_files_are_loaded() {
+        let path = std::env::temp_dir().join(format!("dbgmcp-{}-tokens", std::process::id()));
+        std::fs::write(&path, "# namespace:token\n\nalice:3f9c0a\n  ci:a7:1b  \n").unwrap();
+        let mut auth = Auth::default();
+        auth.load(&path).unwrap();
+        assert_eq!(auth.authenticate(Some("Bearer 3f9c0a")), Some("alice"));
+        assert_eq!(auth.authenticate(Some("Bearer a7:1b")), Some("ci"));
+
+        std::fs::write(&path, "alice:3f9c0a\n:nonamespace\n").unwrap();
+        let err = Auth::default().load(&path).unwrap_err();
+        assert!(err.ends_with(":2: expected 'namespace:token'"), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn secrets_are_compared_whole() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
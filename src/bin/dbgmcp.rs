@@ -0,0 +1,6 @@
+use dbgmcp::server::Backend;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dbgmcp::cli::run(&Backend::ALL).await
+}
@@ -0,0 +1,260 @@
+//! Command line entry point shared by the `dbgmcp` binary and the per-debugger aliases.
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{
+    audit::AuditLog,
+    auth::Auth,
+    config::Config,
+    server::{Backend, DbgServer, Logger, ServerContext, SessionLimits},
+    tls::TlsConfig,
+};
+
+const USAGE: &str = "\
+Usage: dbgmcp [OPTIONS]
+
+Options:
+  --backends <LIST>       Comma separated debuggers to serve (gdb, lldb, pdb)
+  --max-sessions <N>      Maximum number of debugger sessions alive at the same time
+  --config <PATH>         JSON configuration file, see README for the available settings
+  --read-only             Only allow inspection commands in every session
+  --sandbox               Run debuggers in a sandbox without network access, see README
+  --log-file <PATH>       Append diagnostic logs to a file instead of stderr
+  --audit-log <PATH>      Append an audit record of every tool call to a file.
+                          On by default with the http transport, see README
+  --transport <NAME>      Transport to serve MCP over: stdio (default) or http
+  --bind <ADDR>           Address to listen on with the http transport (default 127.0.0.1:8765)
+  --auth-tokens <PATH>    File of 'namespace:token' lines accepted as bearer tokens by the http transport.
+                          A single token can also be set with the DBGMCP_AUTH_TOKEN environment variable
+  --tls-cert <PATH>       PEM certificate chain to serve HTTPS with. Required to bind a non-loopback address
+  --tls-key <PATH>        PEM private key of the --tls-cert certificate
+  --tls-client-ca <PATH>  PEM CA certificates clients must present a certificate signed by. The certificate
+                          authenticates clients without bearer tokens, its name is their namespace
+  -h, --help              Print this help";
+
+const DEFAULT_BIND: &str = "127.0.0.1:8765";
+
+/// Transport used to talk to MCP clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    /// A single client connected to stdin/stdout.
+    Stdio,
+    /// Streamable HTTP and legacy SSE, see [`crate::http`].
+    Http,
+}
+
+/// Options accepted on the command line.
+#[derive(Debug)]
+pub struct Options {
+    pub backends: Vec<Backend>,
+    pub max_sessions: Option<usize>,
+    pub config: Option<String>,
+    pub read_only: bool,
+    pub sandbox: bool,
+    pub log_file: Option<String>,
+    pub audit_log: Option<String>,
+    pub transport: Transport,
+    pub bind: SocketAddr,
+    pub auth_tokens: Option<String>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parse command line arguments, excluding the program name.
+    /// `default_backends` is used unless `--backends` is given.
+    pub fn parse<I>(args: I, default_backends: &[Backend]) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options {
+            backends: default_backends.to_vec(),
+            max_sessions: None,
+            config: None,
+            read_only: false,
+            sandbox: false,
+            log_file: None,
+            audit_log: None,
+            transport: Transport::Stdio,
+            bind: DEFAULT_BIND.parse().unwrap(),
+            auth_tokens: None,
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("Missing value for {}", flag))
+            };
+            match flag.as_str() {
+                "--backends" => {
+                    options.backends = value()?
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?;
+                }
+                "--max-sessions" => {
+                    let max = value()?;
+                    options.max_sessions = Some(
+                        max.parse()
+                            .map_err(|_| format!("Invalid --max-sessions value '{}'", max))?,
+                    );
+                }
+                "--config" => options.config = Some(value()?),
+                "--read-only" => options.read_only = true,
+                "--sandbox" => options.sandbox = true,
+                "--log-file" => options.log_file = Some(value()?),
+                "--audit-log" => options.audit_log = Some(value()?),
+                "--transport" => {
+                    options.transport = match value()?.as_str() {
+                        "stdio" => Transport::Stdio,
+                        "http" => Transport::Http,
+                        other => return Err(format!("Unknown transport '{}'", other)),
+                    };
+                }
+                "--bind" => {
+                    let bind = value()?;
+                    options.bind = bind
+                        .parse()
+                        .map_err(|_| format!("Invalid --bind address '{}'", bind))?;
+                }
+                "--auth-tokens" => options.auth_tokens = Some(value()?),
+                "--tls-cert" => options.tls_cert = Some(value()?.into()),
+                "--tls-key" => options.tls_key = Some(value()?.into()),
+                "--tls-client-ca" => options.tls_client_ca = Some(value()?.into()),
+                "-h" | "--help" => return Err(USAGE.to_owned()),
+                _ => return Err(format!("Unknown option '{}'\n\n{}", flag, USAGE)),
+            }
+        }
+        if options.backends.is_empty() {
+            return Err("At least one backend must be enabled".to_owned());
+        }
+        if options.tls_cert.is_some() != options.tls_key.is_some() {
+            return Err("--tls-cert and --tls-key must be given together".to_owned());
+        }
+        if options.tls_client_ca.is_some() && options.tls_cert.is_none() {
+            return Err("--tls-client-ca requires --tls-cert and --tls-key".to_owned());
+        }
+        Ok(options)
+    }
+}
+
+/// Parse the process arguments and serve the selected backends until the transport shuts down.
+pub async fn run(default_backends: &[Backend]) -> Result<(), Box<dyn std::error::Error>> {
+    let options = match Options::parse(std::env::args().skip(1), default_backends) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(if message == USAGE { 0 } else { 2 });
+        }
+    };
+    let mut config = match &options.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    config.policy.read_only |= options.read_only;
+    config.sandbox.enabled |= options.sandbox;
+    let logger = match &options.log_file {
+        Some(path) => Logger::file(path)?,
+        None => Logger::stderr(),
+    };
+    let audit = if options.audit_log.is_some()
+        || config
+            .audit
+            .enabled
+            .unwrap_or(options.transport == Transport::Http)
+    {
+        let path = options
+            .audit_log
+            .map(PathBuf::from)
+            .or(config.audit.file)
+            .unwrap_or_else(crate::audit::default_path);
+        logger.log(format!("Writing the audit log to {}", path.display()));
+        AuditLog::open(&path, config.audit.syslog).map_err(|err| {
+            format!(
+                "Failed to open the audit log {}. [Error]: {}",
+                path.display(),
+                err
+            )
+        })?
+    } else {
+        AuditLog::default()
+    };
+    let mut http = config.http;
+    if let (Some(certificate), Some(key)) = (options.tls_cert, options.tls_key) {
+        http.tls = Some(TlsConfig {
+            certificate,
+            key,
+            client_ca: options.tls_client_ca,
+        });
+    }
+    let context = ServerContext {
+        limits: SessionLimits::new(
+            options.max_sessions.or(config.quotas.max_sessions),
+            config.quotas.max_sessions_per_client,
+        ),
+        logger,
+        policy: Arc::new(config.policy),
+        filesystem: Arc::new(config.filesystem),
+        sandbox: Arc::new(config.sandbox),
+        quotas: Arc::new(config.quotas),
+        prompts: Arc::new(config.prompts),
+        audit,
+    };
+
+    let mut auth = Auth::default();
+    if let Some(path) = &options.auth_tokens {
+        auth.load(path)?;
+    }
+    auth.load_env();
+    let client_certificates = http.tls.as_ref().is_some_and(|tls| tls.client_ca.is_some());
+    if options.transport == Transport::Http
+        && !options.bind.ip().is_loopback()
+        && !auth.is_enabled()
+        && !client_certificates
+    {
+        return Err(format!(
+            "Refusing to listen on non-loopback address {} without authentication. \
+             Configure --auth-tokens, {} or --tls-client-ca",
+            options.bind,
+            crate::auth::TOKEN_ENV
+        )
+        .into());
+    }
+    if options.transport == Transport::Http
+        && !options.bind.ip().is_loopback()
+        && http.tls.is_none()
+    {
+        return Err(format!(
+            "Refusing to listen on non-loopback address {} without TLS, bearer tokens would be sent in cleartext. \
+             Configure --tls-cert and --tls-key",
+            options.bind
+        )
+        .into());
+    }
+
+    let server = DbgServer::new(&options.backends, context.clone());
+
+    match options.transport {
+        Transport::Stdio => {
+            let make_service = |client| server.with_client(client);
+            crate::stdio::serve(make_service, context.logger).await?
+        }
+        Transport::Http => {
+            let make_service =
+                move |namespace: &str, client| server.in_namespace(namespace).with_client(client);
+            crate::http::serve(make_service, options.bind, auth, http, context.logger).await?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,353 @@
+//! Requests from the server to the MCP client that rmcp does not model, such as elicitation and roots.
+//!
+//! Transports exchange raw JSON-RPC messages with [`serve`]. Responses to requests issued through a [`Client`]
+//! are kept back, everything else is handed to the rmcp service as typed messages.
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::PathBuf;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use futures::{
+    SinkExt, Stream, StreamExt,
+    channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded},
+};
+use rmcp::{
+    ServerHandler, ServiceExt,
+    model::{ClientJsonRpcMessage, LoggingLevel, RequestId, ServerJsonRpcMessage},
+};
+use serde_json::{Value, json};
+use tokio::{
+    sync::oneshot,
+    time::{self, Duration},
+};
+
+/// How long to wait for the user to answer an elicitation request.
+const ELICITATION_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long to wait for the client to list its roots.
+const ROOTS_TIMEOUT: Duration = Duration::from_secs(10);
+/// Least severe level of log notifications sent until the client sets a level.
+pub const DEFAULT_LOG_LEVEL: LoggingLevel = LoggingLevel::Info;
+
+/// Handle to the MCP client of one connection.
+/// A default handle is not connected to any client and fails every request.
+#[derive(Clone, Default)]
+pub struct Client {
+    outgoing: Option<UnboundedSender<Value>>,
+    connection: String,
+    info: Arc<Mutex<Option<Value>>>,
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>,
+    capabilities: Arc<Mutex<Option<Value>>>,
+    roots: Arc<Mutex<Option<Vec<PathBuf>>>>,
+    /// Progress tokens of tool calls in flight, keyed by the JSON text of their request ID.
+    progress_tokens: Arc<Mutex<HashMap<String, Value>>>,
+    /// Least severe level of log notifications sent to the client, `None` for [`DEFAULT_LOG_LEVEL`].
+    log_level: Arc<Mutex<Option<LoggingLevel>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Client {
+    /// Creates a client handle together with the messages the transport must deliver to the client.
+    /// `connection` describes how the client is connected, for example its address.
+    pub fn new<S: Into<String>>(connection: S) -> (Self, UnboundedReceiver<Value>) {
+        let (outgoing, messages) = unbounded();
+        let client = Self {
+            outgoing: Some(outgoing),
+            connection: connection.into(),
+            ..Default::default()
+        };
+        (client, messages)
+    }
+
+    /// How the client is connected, empty if it is not.
+    pub fn connection(&self) -> &str {
+        &self.connection
+    }
+
+    /// Whether the client is still connected: its transport did not close and its session did not end.
+    pub fn is_connected(&self) -> bool {
+        self.outgoing
+            .as_ref()
+            .is_some_and(|outgoing| !outgoing.is_closed())
+    }
+
+    /// Drop the messages still to be sent to the client, and any sent later. Every handle stops being connected.
+    fn disconnect(&self) {
+        if let Some(outgoing) = &self.outgoing {
+            outgoing.close_channel();
+        }
+    }
+
+    /// Whether both handles talk to the same client.
+    pub fn is(&self, other: &Client) -> bool {
+        Arc::ptr_eq(&self.pending, &other.pending)
+    }
+
+    /// Name and version the client announced when initializing.
+    pub fn info(&self) -> Option<Value> {
+        self.info.lock().unwrap().clone()
+    }
+
+    /// Whether the client announced the given capability when initializing.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.get(capability).is_some())
+    }
+
+    /// Take the progress token the client attached to a tool call, if any.
+    pub fn take_progress_token(&self, id: &RequestId) -> Option<Value> {
+        let key = serde_json::to_string(id).ok()?;
+        self.progress_tokens.lock().unwrap().remove(&key)
+    }
+
+    /// Send a notification to the client. Notifications to a client that is not connected are dropped.
+    pub fn notify(&self, method: &str, params: Value) {
+        if let Some(outgoing) = &self.outgoing {
+            let _ = outgoing.unbounded_send(json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": params,
+            }));
+        }
+    }
+
+    /// Only send log notifications of `level` or more severe, see the MCP `logging/setLevel` request.
+    pub fn set_log_level(&self, level: LoggingLevel) {
+        *self.log_level.lock().unwrap() = Some(level);
+    }
+
+    /// Send a log notification from `logger` to the client, unless it is less severe than the client's level.
+    pub fn log(&self, level: LoggingLevel, logger: &str, data: Value) {
+        let threshold = severity(
+            self.log_level
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap_or(&DEFAULT_LOG_LEVEL),
+        );
+        if severity(&level) < threshold {
+            return;
+        }
+        self.notify(
+            "notifications/message",
+            json!({ "level": level, "logger": logger, "data": data }),
+        );
+    }
+
+    /// Send a request to the client and wait for the result.
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let outgoing = self
+            .outgoing
+            .as_ref()
+            .ok_or("The client is not connected".to_owned())?;
+        let id = format!("dbgmcp-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        let (responder, response) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), responder);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        if outgoing.unbounded_send(request).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err("The client is not connected".to_owned());
+        }
+
+        let response = match time::timeout(timeout, response).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err("The client disconnected".to_owned()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(format!(
+                    "No answer from the client within {} seconds",
+                    timeout.as_secs()
+                ));
+            }
+        };
+        if let Some(error) = response.get("error") {
+            return Err(format!(
+                "Client failed the {} request. [Error]: {}",
+                method,
+                error.get("message").unwrap_or(error)
+            ));
+        }
+        Ok(response.get("result").cloned().unwrap_or_default())
+    }
+
+    /// Ask the user for input through the client, see the MCP elicitation specification.
+    /// Returns the result of the request, holding the `action` taken by the user and the `content` they entered.
+    pub async fn elicit(&self, message: &str, schema: Value) -> Result<Value, String> {
+        if !self.supports("elicitation") {
+            return Err("The client does not support elicitation".to_owned());
+        }
+        let params = json!({ "message": message, "requestedSchema": schema });
+        self.request("elicitation/create", params, ELICITATION_TIMEOUT)
+            .await
+    }
+
+    /// Ask the user to approve an action. Only an explicit approval counts, declining, dismissing or
+    /// failing to answer does not.
+    pub async fn confirm(&self, message: &str) -> Result<bool, String> {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "approve": {
+                    "type": "boolean",
+                    "title": "Approve",
+                    "description": "Allow the action to proceed",
+                },
+            },
+            "required": ["approve"],
+        });
+        let result = self.elicit(message, schema).await?;
+        Ok(
+            result.get("action").and_then(Value::as_str) == Some("accept")
+                && result
+                    .pointer("/content/approve")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+        )
+    }
+
+    /// Local directories the client exposes as roots, usually its workspace folders. The first one is the main root.
+    /// Empty if the client does not support roots. The list is cached until the client reports a change.
+    pub async fn roots(&self) -> Result<Vec<PathBuf>, String> {
+        if !self.supports("roots") {
+            return Ok(Vec::new());
+        }
+        if let Some(roots) = self.roots.lock().unwrap().clone() {
+            return Ok(roots);
+        }
+        let result = self.request("roots/list", json!({}), ROOTS_TIMEOUT).await?;
+        let roots: Vec<PathBuf> = result
+            .get("roots")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|root| root.get("uri")?.as_str())
+            .filter_map(file_uri_path)
+            .collect();
+        *self.roots.lock().unwrap() = Some(roots.clone());
+        Ok(roots)
+    }
+
+    /// Take a message received from the client. Returns the message if it is meant for the rmcp service.
+    fn accept(&self, message: Value) -> Option<ClientJsonRpcMessage> {
+        match message.get("method").and_then(Value::as_str) {
+            Some("initialize") => {
+                *self.capabilities.lock().unwrap() =
+                    message.pointer("/params/capabilities").cloned();
+                *self.info.lock().unwrap() = message.pointer("/params/clientInfo").cloned();
+            }
+            Some("notifications/roots/list_changed") => *self.roots.lock().unwrap() = None,
+            Some("tools/call") => {
+                if let Some(id) = message.get("id")
+                    && let Some(token) = message.pointer("/params/_meta/progressToken")
+                {
+                    self.progress_tokens
+                        .lock()
+                        .unwrap()
+                        .insert(id.to_string(), token.clone());
+                }
+            }
+            _ => {}
+        }
+        if message.get("method").is_none()
+            && let Some(id) = message.get("id").and_then(Value::as_str)
+            && let Some(responder) = self.pending.lock().unwrap().remove(id)
+        {
+            let _ = responder.send(message);
+            return None;
+        }
+        serde_json::from_value(message).ok()
+    }
+}
+
+/// Rank of a log level, higher is more severe.
+fn severity(level: &LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+/// Local path of a `file://` URI. Other schemes have no local path.
+fn file_uri_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // Skip the authority, which is empty or `localhost` for local files.
+    let path = &path[path.find('/')?..];
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| std::str::from_utf8(tail.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Serve an rmcp service to the client behind `client`, reading the client's messages from `incoming`.
+/// Messages for the client are delivered through the receiver returned by [`Client::new`].
+/// Returns when `incoming` ends or the service shuts down.
+pub async fn serve<S, I>(service: S, client: Client, incoming: I) -> Result<(), Error>
+where
+    S: ServerHandler,
+    I: Stream<Item = Value> + Send + Unpin + 'static,
+{
+    let outgoing = client
+        .outgoing
+        .clone()
+        .ok_or(Error::other("The client is not connected"))?;
+    let (to_service, from_client) = unbounded::<ClientJsonRpcMessage>();
+    let session = client.clone();
+    tokio::spawn(async move {
+        let mut incoming = incoming;
+        while let Some(message) = incoming.next().await {
+            if let Some(message) = client.accept(message)
+                && to_service.unbounded_send(message).is_err()
+            {
+                break;
+            }
+        }
+    });
+    let to_client = outgoing
+        .sink_map_err(Error::other)
+        .with(|message: ServerJsonRpcMessage| {
+            futures::future::ready(serde_json::to_value(message).map_err(Error::other))
+        });
+    let result = match service.serve((Box::pin(to_client), from_client)).await {
+        Ok(running) => running.waiting().await.map(drop).map_err(Error::other),
+        Err(err) => Err(err),
+    };
+    // Handles of the client kept elsewhere, such as resource subscriptions, must see it is gone.
+    session.disconnect();
+    result
+}
@@ -0,0 +1,40 @@
+//! Configuration file for settings that do not fit on the command line.
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    audit::AuditConfig, http::HttpConfig, policy::Policy, prompts::Prompts, quota::Quotas,
+    sandbox::Sandbox, scope::FilesystemScope,
+};
+
+/// Contents of the JSON configuration file given with `--config`. Every section is optional.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Allow/deny rules for debugger commands.
+    pub policy: Policy,
+    /// Directories sessions may load programs, core files and scripts from.
+    pub filesystem: FilesystemScope,
+    /// Isolation and resource limits of debugger processes.
+    pub sandbox: Sandbox,
+    /// Limits on sessions, command rate, CPU time and output.
+    pub quotas: Quotas,
+    /// Audit log of tool calls.
+    pub audit: AuditConfig,
+    /// Prompt templates for debugging workflows.
+    pub prompts: Prompts,
+    /// Settings of the http transport.
+    pub http: HttpConfig,
+}
+
+impl Config {
+    /// Read the configuration from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}. [Error]: {}", path.display(), err))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid config {}. [Error]: {}", path.display(), err))
+    }
+}
@@ -0,0 +1,553 @@
+//! ELF core dumps: finding the executable that dumped a core, and telling whether an executable is the same build.
+//!
+//! The executable is found through the auxiliary vector saved in the core: its file name (`AT_EXECFN`) if the
+//! stack holding it was dumped, otherwise the file mapped at its entry point (`AT_ENTRY`) in the `NT_FILE` note.
+//! Build IDs come from the `NT_GNU_BUILD_ID` note of the executable's first page, which cores normally include.
+//! Only little endian cores are read. Cores are read piecewise, as they can be large.
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::{client::Client, server::ServerContext};
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const ET_CORE: u16 = 4;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x4649_4c45;
+const NT_GNU_BUILD_ID: u32 = 3;
+const AT_ENTRY: u64 = 9;
+const AT_EXECFN: u64 = 31;
+/// Bytes of an executable read to find its build ID, which is in a note near its start.
+const BUILD_ID_SEARCH_BYTES: u64 = 64 * 1024;
+
+/// What a core dump tells about the program that dumped it.
+#[derive(Debug, Default)]
+pub struct CoreInfo {
+    /// Path of the executable, as it was on the machine that dumped the core.
+    pub executable: Option<String>,
+    /// Build ID of the executable, in hex.
+    pub build_id: Option<String>,
+}
+
+/// Reads little endian words of the ELF class of a file.
+#[derive(Clone, Copy)]
+struct Elf {
+    is_64: bool,
+}
+
+impl Elf {
+    /// Check the ELF identification of a file.
+    fn new(header: &[u8]) -> Option<Elf> {
+        if header.len() < 64 || &header[..4] != b"\x7fELF" || header[5] != 1 {
+            return None;
+        }
+        match header[4] {
+            1 => Some(Elf { is_64: false }),
+            2 => Some(Elf { is_64: true }),
+            _ => None,
+        }
+    }
+
+    fn word_size(&self) -> usize {
+        if self.is_64 { 8 } else { 4 }
+    }
+
+    fn u16(bytes: &[u8], offset: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            bytes.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    }
+
+    fn u32(bytes: &[u8], offset: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    }
+
+    fn word(&self, bytes: &[u8], offset: usize) -> Option<u64> {
+        if self.is_64 {
+            Some(u64::from_le_bytes(
+                bytes.get(offset..offset + 8)?.try_into().ok()?,
+            ))
+        } else {
+            Elf::u32(bytes, offset).map(u64::from)
+        }
+    }
+
+    /// Size of a program header, as far as its fields are read.
+    fn program_header_size(&self) -> usize {
+        if self.is_64 { 56 } else { 32 }
+    }
+
+    /// Offset, size and number of the program headers. `None` if their size is too small to hold a program header.
+    fn program_headers(&self, header: &[u8]) -> Option<(u64, usize, usize)> {
+        let (offset, size, count) = if self.is_64 {
+            (
+                self.word(header, 32)?,
+                usize::from(Elf::u16(header, 54)?),
+                usize::from(Elf::u16(header, 56)?),
+            )
+        } else {
+            (
+                self.word(header, 28)?,
+                usize::from(Elf::u16(header, 42)?),
+                usize::from(Elf::u16(header, 44)?),
+            )
+        };
+        (size >= self.program_header_size()).then_some((offset, size, count))
+    }
+
+    /// Type, file offset, virtual address and size in the file of a program header.
+    fn segment(&self, header: &[u8]) -> Option<Segment> {
+        let kind = Elf::u32(header, 0)?;
+        let (offset, address, size) = if self.is_64 {
+            (
+                self.word(header, 8)?,
+                self.word(header, 16)?,
+                self.word(header, 32)?,
+            )
+        } else {
+            (
+                self.word(header, 4)?,
+                self.word(header, 8)?,
+                self.word(header, 16)?,
+            )
+        };
+        Some(Segment {
+            kind,
+            offset,
+            address,
+            size,
+        })
+    }
+
+    /// Notes as name, type and description.
+    fn notes<'a>(&self, bytes: &'a [u8]) -> Vec<(&'a [u8], u32, &'a [u8])> {
+        let align = |size: usize| size.div_ceil(4) * 4;
+        let mut notes = Vec::new();
+        let mut offset = 0;
+        while let (Some(name_size), Some(desc_size), Some(kind)) = (
+            Elf::u32(bytes, offset),
+            Elf::u32(bytes, offset + 4),
+            Elf::u32(bytes, offset + 8),
+        ) {
+            let name_start = offset + 12;
+            let desc_start = name_start + align(name_size as usize);
+            let end = desc_start + align(desc_size as usize);
+            let (Some(name), Some(desc)) = (
+                bytes.get(name_start..name_start + name_size as usize),
+                bytes.get(desc_start..desc_start + desc_size as usize),
+            ) else {
+                break;
+            };
+            notes.push((name.strip_suffix(b"\0").unwrap_or(name), kind, desc));
+            offset = end;
+        }
+        notes
+    }
+}
+
+struct Segment {
+    kind: u32,
+    offset: u64,
+    address: u64,
+    size: u64,
+}
+
+/// A core file, read on demand.
+struct Core {
+    file: File,
+    /// Size of the file, which sizes read from its headers must not exceed.
+    length: u64,
+    elf: Elf,
+    segments: Vec<Segment>,
+}
+
+impl Core {
+    fn open(path: &Path) -> Result<Core, String> {
+        let mut file = File::open(path).map_err(|err| {
+            format!(
+                "Failed to open core file {}. [Error]: {}",
+                path.display(),
+                err
+            )
+        })?;
+        let mut header = [0; 64];
+        file.read_exact(&mut header)
+            .map_err(|_| format!("{} is not an ELF core file", path.display()))?;
+        let elf = Elf::new(&header)
+            .filter(|_| Elf::u16(&header, 16) == Some(ET_CORE))
+            .ok_or(format!(
+                "{} is not a little endian ELF core file",
+                path.display()
+            ))?;
+        let (offset, size, count) = elf
+            .program_headers(&header)
+            .ok_or(format!("{} has no valid program headers", path.display()))?;
+        let length = file
+            .metadata()
+            .map_err(|err| {
+                format!(
+                    "Failed to read core file {}. [Error]: {}",
+                    path.display(),
+                    err
+                )
+            })?
+            .len();
+        let mut core = Core {
+            file,
+            length,
+            elf,
+            segments: Vec::new(),
+        };
+        let headers = core
+            .read(offset, size * count)
+            .ok_or(format!("{} is truncated", path.display()))?;
+        core.segments = headers
+            .chunks(size)
+            .filter_map(|header| elf.segment(header))
+            .collect();
+        Ok(core)
+    }
+
+    /// Read bytes of the file. `None` if they are not all in the file.
+    fn read(&mut self, offset: u64, length: usize) -> Option<Vec<u8>> {
+        if offset.checked_add(u64::try_from(length).ok()?)? > self.length {
+            return None;
+        }
+        let mut bytes = vec![0; length];
+        self.file.seek(SeekFrom::Start(offset)).ok()?;
+        self.file.read_exact(&mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    /// Read memory of the dumped process, if it was dumped. Reads may be cut short at the end of a segment.
+    fn read_memory(&mut self, address: u64, length: usize) -> Option<Vec<u8>> {
+        let (offset, available) = self.segments.iter().find_map(|segment| {
+            let start = address.checked_sub(segment.address)?;
+            (segment.kind == PT_LOAD && start < segment.size)
+                .then(|| Some((segment.offset.checked_add(start)?, segment.size - start)))?
+        })?;
+        self.read(offset, length.min(usize::try_from(available).ok()?))
+    }
+
+    /// Descriptions of the notes of the given type.
+    fn notes(&mut self, kind: u32) -> Vec<Vec<u8>> {
+        let note_segments: Vec<(u64, u64)> = self
+            .segments
+            .iter()
+            .filter(|segment| segment.kind == PT_NOTE)
+            .map(|segment| (segment.offset, segment.size))
+            .collect();
+        let mut descriptions = Vec::new();
+        for (offset, size) in note_segments {
+            let Some(bytes) = usize::try_from(size)
+                .ok()
+                .and_then(|size| self.read(offset, size))
+            else {
+                continue;
+            };
+            descriptions.extend(
+                self.elf
+                    .notes(&bytes)
+                    .into_iter()
+                    .filter(|(name, note_kind, _)| *name == b"CORE" && *note_kind == kind)
+                    .map(|(_, _, desc)| desc.to_vec()),
+            );
+        }
+        descriptions
+    }
+
+    /// Value of an entry of the auxiliary vector.
+    fn auxv(&mut self, key: u64) -> Option<u64> {
+        let auxv = self.notes(NT_AUXV).into_iter().next()?;
+        let size = self.elf.word_size();
+        auxv.chunks(size * 2).find_map(|entry| {
+            (self.elf.word(entry, 0)? == key).then(|| self.elf.word(entry, size))?
+        })
+    }
+
+    /// Mapped files of the `NT_FILE` note, as start address, end address, page offset and path.
+    fn mapped_files(&mut self) -> Vec<(u64, u64, u64, String)> {
+        let Some(note) = self.notes(NT_FILE).into_iter().next() else {
+            return Vec::new();
+        };
+        let size = self.elf.word_size();
+        // Every file takes three words, which bounds the count.
+        let Some(count) = self
+            .elf
+            .word(&note, 0)
+            .and_then(|count| usize::try_from(count).ok())
+            .filter(|count| *count <= note.len() / (3 * size))
+        else {
+            return Vec::new();
+        };
+        let names_start = size * (2 + 3 * count);
+        let names = note
+            .get(names_start..)
+            .unwrap_or_default()
+            .split(|byte| *byte == 0)
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        (0..count)
+            .zip(names)
+            .filter_map(|(index, name)| {
+                let entry = size * (2 + 3 * index);
+                Some((
+                    self.elf.word(&note, entry)?,
+                    self.elf.word(&note, entry + size)?,
+                    self.elf.word(&note, entry + 2 * size)?,
+                    name,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Build ID of an ELF image, from its `NT_GNU_BUILD_ID` note. `image` holds the start of the file.
+fn image_build_id(image: &[u8]) -> Option<String> {
+    let elf = Elf::new(image)?;
+    let (offset, size, count) = elf.program_headers(image)?;
+    let offset = usize::try_from(offset).ok()?;
+    (0..count).find_map(|index| {
+        let segment = elf.segment(image.get(offset + index * size..)?)?;
+        if segment.kind != PT_NOTE {
+            return None;
+        }
+        let start = usize::try_from(segment.offset).ok()?;
+        let notes = image.get(start..start.checked_add(usize::try_from(segment.size).ok()?)?)?;
+        elf.notes(notes)
+            .into_iter()
+            .find(|(name, kind, _)| *name == b"GNU" && *kind == NT_GNU_BUILD_ID)
+            .map(|(_, _, desc)| desc.iter().map(|byte| format!("{:02x}", byte)).collect())
+    })
+}
+
+/// Build ID of an executable or library file, if it has one.
+pub fn build_id(path: &Path) -> Option<String> {
+    let mut image = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(BUILD_ID_SEARCH_BYTES)
+        .read_to_end(&mut image)
+        .ok()?;
+    image_build_id(&image)
+}
+
+/// Find the executable that dumped a core, and its build ID.
+pub fn inspect(path: &Path) -> Result<CoreInfo, String> {
+    let mut core = Core::open(path)?;
+    let files = core.mapped_files();
+    let entry = core.auxv(AT_ENTRY);
+    let execfn = core
+        .auxv(AT_EXECFN)
+        .and_then(|address| core.read_memory(address, 4096))
+        .and_then(|name| {
+            let name = name.split(|byte| *byte == 0).next()?;
+            (!name.is_empty()).then(|| String::from_utf8_lossy(name).into_owned())
+        });
+    let mapped = entry.and_then(|entry| {
+        files
+            .iter()
+            .find(|(start, end, _, _)| (*start..*end).contains(&entry))
+            .map(|(_, _, _, name)| name.clone())
+    });
+    // `AT_EXECFN` is the path as given to exec, which may be relative. The mapped file's path is absolute.
+    let executable = match (execfn, mapped) {
+        (Some(execfn), _) if execfn.starts_with('/') => Some(execfn),
+        (execfn, mapped) => mapped.or(execfn),
+    };
+    // The first page of the executable holds its headers and, usually, the build ID note.
+    let build_id = executable.as_ref().and_then(|executable| {
+        let (start, _, _, _) = files
+            .iter()
+            .find(|(_, _, page, name)| name == executable && *page == 0)?;
+        image_build_id(&core.read_memory(*start, 4096)?)
+    });
+    Ok(CoreInfo {
+        executable,
+        build_id,
+    })
+}
+
+/// Shared libraries mapped by the process that dumped a core, as paths on the machine that dumped it.
+pub fn mapped_libraries(path: &Path) -> Result<Vec<String>, String> {
+    let mut libraries: Vec<String> = Vec::new();
+    for (_, _, _, name) in Core::open(path)?.mapped_files() {
+        let file_name = name.rsplit('/').next().unwrap_or_default();
+        if (file_name.ends_with(".so") || file_name.contains(".so.")) && !libraries.contains(&name)
+        {
+            libraries.push(name);
+        }
+    }
+    Ok(libraries)
+}
+
+/// The executable to load with a core.
+pub struct Executable {
+    pub path: PathBuf,
+    /// The path was found in the core rather than given by the client.
+    pub inferred: bool,
+    /// The executable is not the build that dumped the core.
+    pub mismatch: Option<String>,
+}
+
+/// Resolve the executable given with a core, or find it in the core if none is given, and check it is the build
+/// that dumped the core.
+pub async fn executable(
+    context: &ServerContext,
+    client: &Client,
+    core: &Path,
+    executable: Option<&str>,
+) -> Result<Executable, String> {
+    let info = match (inspect(core), executable) {
+        (Ok(info), _) => info,
+        // The debugger may still make sense of a core this module cannot read.
+        (Err(_), Some(_)) => CoreInfo::default(),
+        (Err(err), None) => return Err(format!("{}. Give the path of the executable", err)),
+    };
+    let inferred = executable.is_none();
+    let executable = match executable.or(info.executable.as_deref()) {
+        Some(executable) => executable,
+        None => {
+            return Err(format!(
+                "Could not find the executable in core file {}. Give the path of the executable",
+                core.display()
+            ));
+        }
+    };
+    let path = context.resolve_path(client, executable).await?;
+    let dumped_by = info
+        .build_id
+        .as_ref()
+        .map(|build_id| format!(" (build ID {})", build_id))
+        .unwrap_or_default();
+    if !path.is_file() {
+        return Err(format!(
+            "The core was dumped by {}{}, which is not on this machine. Give the path of a copy of the same build",
+            path.display(),
+            dumped_by
+        ));
+    }
+    let mismatch = info
+        .build_id
+        .zip(build_id(&path))
+        .filter(|(dumped, found)| dumped != found)
+        .map(|(dumped, found)| {
+            format!(
+                "{} has build ID {}, but the core was dumped by build ID {}. Symbols and backtraces may be wrong",
+                path.display(),
+                found,
+                dumped
+            )
+        });
+    Ok(Executable {
+        path,
+        inferred,
+        mismatch,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &[u8], kind: u32, desc: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((name.len() as u32).to_le_bytes());
+        bytes.extend((desc.len() as u32).to_le_bytes());
+        bytes.extend(kind.to_le_bytes());
+        for part in [name, desc] {
+            bytes.extend(part);
+            bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+        }
+        bytes
+    }
+
+    fn words(words: &[u64]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// A 64-bit core with a single note segment, and program headers of the given size.
+    fn core(header_size: u16, notes: &[u8], notes_size: u64) -> Vec<u8> {
+        let mut bytes = vec![0; 64];
+        bytes[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        bytes[16..18].copy_from_slice(&ET_CORE.to_le_bytes());
+        bytes[32..40].copy_from_slice(&64u64.to_le_bytes());
+        bytes[54..56].copy_from_slice(&header_size.to_le_bytes());
+        bytes[56..58].copy_from_slice(&1u16.to_le_bytes());
+        let mut header = vec![0; 56];
+        header[..4].copy_from_slice(&PT_NOTE.to_le_bytes());
+        header[8..16].copy_from_slice(&120u64.to_le_bytes());
+        header[32..40].copy_from_slice(&notes_size.to_le_bytes());
+        bytes.extend(header);
+        bytes.extend(notes);
+        bytes
+    }
+
+    fn write(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dbgmcp-{}-{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn crash_notes() -> Vec<u8> {
+        let mut file = words(&[1, 4096, 0x40_0000, 0x40_2000, 0]);
+        file.extend(b"/usr/bin/crashy\0");
+        let mut notes = note(
+            b"CORE\0",
+            NT_AUXV,
+            &words(&[AT_ENTRY, 0x40_1000, AT_EXECFN, 0x7ffd_0000, 0, 0]),
+        );
+        notes.extend(note(b"CORE\0", NT_FILE, &file));
+        notes
+    }
+
+    #[test]
+    fn notes_are_split_and_truncated_notes_dropped() {
+        let elf = Elf { is_64: true };
+        let mut bytes = note(b"GNU\0", NT_GNU_BUILD_ID, &[0xab, 0xcd, 0xef]);
+        bytes.extend(note(b"CORE\0", NT_AUXV, &[1; 8]));
+        let notes = elf.notes(&bytes);
+        assert_eq!(notes.len(), 2);
+        assert_eq!(
+            notes[0],
+            (&b"GNU"[..], NT_GNU_BUILD_ID, &[0xab, 0xcd, 0xef][..])
+        );
+        assert_eq!(notes[1], (&b"CORE"[..], NT_AUXV, &[1; 8][..]));
+        assert_eq!(elf.notes(&bytes[..bytes.len() - 1]).len(), 1);
+    }
+
+    #[test]
+    fn auxv_and_mapped_files_find_the_executable() {
+        let notes = crash_notes();
+        let path = write("auxv", &core(56, &notes, notes.len() as u64));
+        let mut core = Core::open(&path).unwrap();
+        assert_eq!(core.auxv(AT_ENTRY), Some(0x40_1000));
+        assert_eq!(core.auxv(AT_EXECFN), Some(0x7ffd_0000));
+        assert_eq!(core.auxv(7), None);
+        assert_eq!(
+            core.mapped_files(),
+            vec![(0x40_0000, 0x40_2000, 0, "/usr/bin/crashy".to_owned())]
+        );
+        // The stack holding `AT_EXECFN` was not dumped, the file mapped at the entry point is found instead.
+        let info = inspect(&path).unwrap();
+        assert_eq!(info.executable.as_deref(), Some("/usr/bin/crashy"));
+        assert_eq!(info.build_id, None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sizes_from_headers_are_checked() {
+        let notes = crash_notes();
+        let path = write("phentsize", &core(0, &notes, notes.len() as u64));
+        assert!(Core::open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+
+        let path = write("filesz", &core(56, &notes, 1 << 40));
+        let mut core = Core::open(&path).unwrap();
+        assert!(core.notes(NT_AUXV).is_empty());
+        assert!(core.mapped_files().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+//! Disassembly returned by the debuggers, with the source line of every instruction and the current one marked.
+use serde_json::{Value, json};
+
+use crate::mi;
+
+/// Instructions shown before and after the current one when disassembling around the program counter.
+pub const DEFAULT_WINDOW: usize = 10;
+
+/// A disassembled instruction.
+#[derive(Debug, Default)]
+pub struct Instruction {
+    pub address: u64,
+    pub function: Option<String>,
+    /// Offset of the instruction from the start of its function, in bytes.
+    pub offset: Option<u64>,
+    /// Bytes of the instruction, as printed by the debugger.
+    pub opcodes: Option<String>,
+    pub text: String,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    /// Text of the source line, if the debugger printed it.
+    pub source: Option<String>,
+}
+
+impl Instruction {
+    fn to_json(&self, current: bool) -> Value {
+        mi::compact(json!({
+            "address": format!("{:#x}", self.address),
+            "function": self.function,
+            "offset": self.offset,
+            "opcodes": self.opcodes,
+            "instruction": self.text,
+            "file": self.file,
+            "line": self.line,
+            "source": self.source,
+            "current": current.then_some(true),
+        }))
+    }
+}
+
+/// What to disassemble, as asked by the client.
+pub enum Range {
+    /// The function with the given name, or the function containing the given address.
+    Function(String),
+    /// Instructions from the start address up to the end address.
+    Addresses(String, String),
+    /// Instructions around the program counter, in its function.
+    AroundPc(usize),
+}
+
+impl Range {
+    /// Pick the range from the tool arguments.
+    pub fn new(
+        function: Option<String>,
+        start: Option<String>,
+        end: Option<String>,
+        window: Option<usize>,
+    ) -> Result<Self, String> {
+        match (function, start, end) {
+            (Some(function), None, None) => Ok(Range::Function(function)),
+            (None, Some(start), Some(end)) => Ok(Range::Addresses(start, end)),
+            (None, None, None) => Ok(Range::AroundPc(window.unwrap_or(DEFAULT_WINDOW))),
+            (Some(_), _, _) => {
+                Err("Give either a function or a start and end address, not both".to_owned())
+            }
+            _ => Err("Give both a start and an end address".to_owned()),
+        }
+    }
+}
+
+/// The disassembly as JSON. Around the program counter, only `window` instructions before and after it are kept.
+pub fn to_json(instructions: &[Instruction], pc: Option<u64>, range: &Range) -> Value {
+    let current = pc.and_then(|pc| {
+        instructions
+            .iter()
+            .position(|instruction| instruction.address == pc)
+    });
+    let (first, last) = match (range, current) {
+        (Range::AroundPc(window), Some(current)) => (
+            current.saturating_sub(*window),
+            (current + window + 1).min(instructions.len()),
+        ),
+        _ => (0, instructions.len()),
+    };
+    let listed: Vec<Value> = instructions[first..last]
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| instruction.to_json(current == Some(first + index)))
+        .collect();
+    mi::compact(json!({
+        "pc": pc.map(|pc| format!("{:#x}", pc)),
+        "instructions": listed,
+        "truncated": (first > 0 || last < instructions.len()).then_some(true),
+    }))
+}
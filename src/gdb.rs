@@ -0,0 +1,2111 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    CLIDebugger,
+    client::Client,
+    coredump,
+    disassembly::{self, Instruction},
+    generate_session_id,
+    memory::{self, Decoding, Memory},
+    mi, process,
+    registers::{self, RegisterGroup},
+    remote::{self, Connection, Stub},
+    resources::Activity,
+    server::{
+        Backend, DEFAULT_NAMESPACE, ServerContext, Session, SessionMap, SessionNamespaces,
+        observe_session,
+    },
+    watchpoints::{self, Hit, Target, WatchKind, Watchpoints},
+};
+
+use rmcp::{
+    ServerHandler,
+    model::{LoggingLevel, ServerCapabilities, ServerInfo},
+    tool,
+};
+use serde_json::{Value, json};
+
+/// Frames of the crashing thread listed when loading a core.
+const CORE_BACKTRACE_DEPTH: u64 = 64;
+
+/// MCP server exposing GDB sessions as tools.
+#[derive(Clone)]
+pub struct GdbServer {
+    sessions: SessionMap,
+    namespaces: SessionNamespaces,
+    context: ServerContext,
+    namespace: String,
+    client: Client,
+}
+
+#[tool(tool_box)]
+impl ServerHandler for GdbServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some("GNU Debugger".into()),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tool(tool_box)]
+impl GdbServer {
+    pub fn new(context: ServerContext) -> Self {
+        let namespaces = SessionNamespaces::default();
+        Self {
+            sessions: namespaces.get(DEFAULT_NAMESPACE),
+            namespaces,
+            context,
+            namespace: DEFAULT_NAMESPACE.to_owned(),
+            client: Client::default(),
+        }
+    }
+
+    /// Returns a handle to this server that only sees the sessions of the given client namespace.
+    pub fn in_namespace(&self, namespace: &str) -> Self {
+        Self {
+            sessions: self.namespaces.get(namespace),
+            namespace: namespace.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Sessions of the client namespace of this handle.
+    pub fn sessions(&self) -> &SessionMap {
+        &self.sessions
+    }
+
+    /// Returns a handle to this server that talks to the given client.
+    pub fn with_client(&self, client: Client) -> Self {
+        Self {
+            client,
+            ..self.clone()
+        }
+    }
+
+    #[tool(
+        description = "Start a new GDB debugging session. When done using it, terminate the session"
+    )]
+    async fn gdb_start(
+        &self,
+        #[tool(param)]
+        #[schemars(
+            description = "Only allow inspection commands (backtraces, printing, memory reads, disassembly, source listing) in this session"
+        )]
+        read_only: Option<bool>,
+        #[tool(param)]
+        #[schemars(
+            description = "Run in non-stop mode: when a thread stops, the other threads keep running, and threads can be continued or interrupted one by one"
+        )]
+        non_stop: Option<bool>,
+    ) -> Result<String, String> {
+        let session_id = format!("gdb-{}", generate_session_id());
+        let non_stop = non_stop.unwrap_or(false);
+        let slot = self.context.limits.acquire(&self.namespace)?;
+
+        let activity = Activity::shared(&session_id);
+        let mut debugger = CLIDebugger::new("gdb")
+            .args(["--interpreter=mi"])
+            .prompt("(gdb)")
+            .observe_lines(observe_session(
+                self.client.clone(),
+                Backend::Gdb,
+                &session_id,
+                activity.clone(),
+                mi_event,
+            ));
+        if let Some(workspace) = self.context.workspace(&self.client).await {
+            debugger = debugger.current_dir(workspace);
+        }
+        if self.context.sandbox.enabled {
+            debugger = debugger.sandbox(self.context.sandbox.as_ref().clone());
+        }
+        let mut session = debugger
+            .spawn()
+            .map_err(|err| format!("Failed to start GDB session. [Error]: {}", err))?;
+        let response = session
+            .read_response()
+            .await
+            .map_err(|err| format!("Failed to read from GDB session. [Error]: {}", err))?;
+        if non_stop {
+            // Non-stop mode needs GDB to accept commands while threads run.
+            for command in ["-gdb-set mi-async on", "-gdb-set non-stop on"] {
+                let output = session
+                    .execute_command(command)
+                    .await
+                    .map_err(|err| format!("Failed to enable non-stop mode. [Error]: {}", err))?;
+                mi::result_record(&output)
+                    .map_err(|err| format!("Failed to enable non-stop mode. [Error]: {}", err))?;
+            }
+        }
+
+        let sandbox = session
+            .sandbox()
+            .map(|sandbox| format!(" [Sandbox]: {}.", sandbox))
+            .unwrap_or_default();
+        let mut session = Session::new(session, read_only.unwrap_or(false), activity);
+        session.non_stop = non_stop;
+        self.sessions
+            .lock()
+            .await
+            .insert(session_id.clone(), session);
+        slot.commit();
+        self.context
+            .logger
+            .log(format!("GDB session {} started", session_id));
+        let mode = if non_stop { " Non-stop mode." } else { "" };
+        Ok(format!(
+            "GDB session started with ID {}.{}{} [GDB output]: {}",
+            session_id, mode, sandbox, response
+        ))
+    }
+
+    #[tool(description = "Load a program into existing GDB session")]
+    async fn gdb_load(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the program to debug")]
+        program: String,
+        #[tool(param)]
+        #[schemars(description = "Arguments to pass to the program")]
+        arguments: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        let program = self.context.resolve_path(&self.client, &program).await?;
+        let file_command = format!("file {}", mi::quote(&program.display().to_string()));
+        let args_command = arguments.map(|args| format!("set args {}", args.join(" ")));
+        let commands = match &args_command {
+            Some(args_command) => format!("{}\n{}", file_command, args_command),
+            None => file_command.clone(),
+        };
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Gdb,
+                &self.sessions,
+                &session_id,
+                &commands,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        let run_commands = async || -> Result<String, std::io::Error> {
+            let mut response = session.execute_command(&file_command).await?;
+            if let Some(args_command) = args_command {
+                let args_response = session.execute_command(&args_command).await?;
+                response.push_str(&args_response);
+            }
+            Ok(response)
+        };
+        let response = run_commands()
+            .await
+            .map_err(|err| format!("Failed to load program. [Error]: {}", err))?;
+
+        Ok(format!(
+            "Program loaded into GDB.\n [GDB output]: {}",
+            response
+        ))
+    }
+
+    #[tool(description = "Execute a GDB command")]
+    async fn gdb_command(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "GDB command to execute")]
+        command: String,
+    ) -> Result<String, String> {
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Gdb,
+                &self.sessions,
+                &session_id,
+                &command,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        let response = session
+            .execute_command(&command)
+            .await
+            .map_err(|err| format!("Failed to execute GDB command. [Error]: {}", err))?;
+
+        Ok(format!("Command executed.\n[GDB output]: {}", response))
+    }
+
+    #[tool(description = "Wait for GDB debugee to hit a breakpoint or stop running")]
+    async fn gdb_wait(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Timeout in seconds")]
+        timeout: Option<u64>,
+    ) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let timeout = Duration::from_secs(timeout.unwrap_or(10));
+
+        let response = session
+            .read_response_until(Some("*stopped"), timeout)
+            .await
+            .map_err(|err| format!("Failed to read from GDB session. [Error]: {}", err))?;
+
+        Ok(format!("GDB debugee stopped.\n[GDB output]: {}", response))
+    }
+
+    #[tool(
+        description = "Insert a breakpoint. Locations that do not resolve yet, such as functions of libraries not loaded yet, become pending breakpoints. Returns the breakpoint as JSON"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn gdb_break_insert(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Location: function, file:line, line in the current file, or *address"
+        )]
+        location: String,
+        #[tool(param)]
+        #[schemars(description = "Only stop when this expression is true")]
+        condition: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Number of hits to ignore before stopping")]
+        ignore_count: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Delete the breakpoint after its first stop")]
+        temporary: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Use a hardware breakpoint")]
+        hardware: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Only stop in this thread (GDB thread number)")]
+        thread: Option<u64>,
+    ) -> Result<String, String> {
+        let mut command = "-break-insert -f".to_owned();
+        if temporary.unwrap_or(false) {
+            command.push_str(" -t");
+        }
+        if hardware.unwrap_or(false) {
+            command.push_str(" -h");
+        }
+        if let Some(condition) = condition {
+            command.push_str(&format!(" -c {}", mi::quote(&condition)));
+        }
+        if let Some(ignore_count) = ignore_count {
+            command.push_str(&format!(" -i {}", ignore_count));
+        }
+        if let Some(thread) = thread {
+            command.push_str(&format!(" -p {}", thread));
+        }
+        command.push_str(&format!(" {}", mi::quote(&location)));
+
+        let record = self.mi_command(&session_id, &command).await?;
+        let breakpoints = breakpoints_json(
+            record
+                .results
+                .iter()
+                .filter(|(name, _)| name == "bkpt" || name.is_empty())
+                .map(|(_, value)| value),
+        );
+        let breakpoint = breakpoints
+            .into_iter()
+            .next()
+            .ok_or("GDB did not report the inserted breakpoint")?;
+        Ok(breakpoint.to_string())
+    }
+
+    #[tool(
+        description = "List breakpoints and watchpoints as JSON, with their locations, hit counts, conditions and pending status"
+    )]
+    async fn gdb_break_list(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let breakpoints = self.list_breakpoints(&session_id, "-break-list").await?;
+        Ok(Value::Array(breakpoints).to_string())
+    }
+
+    #[tool(description = "Delete breakpoints")]
+    async fn gdb_break_delete(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Numbers of the breakpoints to delete")]
+        breakpoints: Vec<String>,
+    ) -> Result<String, String> {
+        let numbers = breakpoint_numbers(&breakpoints, false)?;
+        self.mi_command(&session_id, &format!("-break-delete {}", numbers))
+            .await?;
+        Ok(json!({ "deleted": breakpoints }).to_string())
+    }
+
+    #[tool(
+        description = "Enable or disable breakpoints, or single locations of a breakpoint (as in 2.1). Returns the breakpoints as JSON"
+    )]
+    async fn gdb_break_toggle(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Numbers of the breakpoints or breakpoint locations")]
+        breakpoints: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = "Enable (true) or disable (false) them")]
+        enabled: bool,
+    ) -> Result<String, String> {
+        let numbers = breakpoint_numbers(&breakpoints, true)?;
+        let verb = if enabled { "enable" } else { "disable" };
+        self.mi_command(&session_id, &format!("-break-{} {}", verb, numbers))
+            .await?;
+        let toggled: Vec<Value> = self
+            .list_breakpoints(&session_id, "-break-list")
+            .await?
+            .into_iter()
+            .filter(|breakpoint| {
+                let number = mi::field(breakpoint, "number").unwrap_or_default();
+                breakpoints
+                    .iter()
+                    .any(|toggled| toggled.split('.').next() == Some(number))
+            })
+            .collect();
+        Ok(Value::Array(toggled).to_string())
+    }
+
+    #[tool(
+        description = "Set or remove the condition and the ignore count of a breakpoint. Returns the breakpoint as JSON"
+    )]
+    async fn gdb_break_condition(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Number of the breakpoint")]
+        breakpoint: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Only stop when this expression is true. Omit or leave empty to remove the condition"
+        )]
+        condition: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Number of hits to ignore before stopping")]
+        ignore_count: Option<u64>,
+    ) -> Result<String, String> {
+        let number = breakpoint_numbers(std::slice::from_ref(&breakpoint), false)?;
+        let command = match condition.filter(|condition| !condition.trim().is_empty()) {
+            Some(condition) => format!(
+                "-break-condition {} {}",
+                number,
+                mi::quote(condition.trim())
+            ),
+            None => format!("-break-condition {}", number),
+        };
+        self.mi_command(&session_id, &command).await?;
+        if let Some(ignore_count) = ignore_count {
+            self.mi_command(
+                &session_id,
+                &format!("-break-after {} {}", number, ignore_count),
+            )
+            .await?;
+        }
+        let breakpoint = self
+            .list_breakpoints(&session_id, &format!("-break-info {}", number))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(format!("Breakpoint {} not found", number))?;
+        Ok(breakpoint.to_string())
+    }
+
+    #[tool(
+        description = "Set a hardware watchpoint stopping when an expression or address range is written, read or accessed, with an optional condition. With collect, the program is continued on every trigger and the old value, new value, thread and backtrace of each are recorded. Returns the watchpoint and the collected hits as JSON"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn gdb_watch(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Expression to watch, such as a variable or `node->next`")]
+        expression: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Start address of a range to watch, as an expression, instead of an expression"
+        )]
+        address: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Length of the range to watch in bytes (default 1)")]
+        length: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Accesses to stop on: write (default), read or access")]
+        kind: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Only stop when this expression is true")]
+        condition: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Continue the program on every trigger and record the hits, until another stop, max_hits or the timeout"
+        )]
+        collect: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Hits to collect at most (default 100)")]
+        max_hits: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Time to collect for at most, in seconds (default 30)")]
+        timeout: Option<u64>,
+    ) -> Result<String, String> {
+        let kind: WatchKind = kind.as_deref().unwrap_or("write").parse()?;
+        let target = Target::new(expression, address, length)?;
+
+        // Find the watched bytes, to count the debug registers they take.
+        let (watched, start, length) = match &target {
+            Target::Expression(expression) => {
+                let start = self
+                    .evaluate_number(&session_id, &format!("&({})", expression))
+                    .await
+                    .map_err(|err| format!("Cannot watch {}. [Error]: {}", expression, err))?;
+                let length = self
+                    .evaluate_number(&session_id, &format!("sizeof({})", expression))
+                    .await?;
+                (expression.clone(), start, length)
+            }
+            Target::Range(address, length) => {
+                let start = self.evaluate_number(&session_id, address).await?;
+                let watched = format!("*(unsigned char (*)[{}]) {:#x}", length, start);
+                (watched, start, *length)
+            }
+        };
+        let needed = watchpoints::slots(start, length);
+        let existing: Vec<u64> = self
+            .list_breakpoints(&session_id, "-break-list")
+            .await?
+            .iter()
+            .filter_map(|breakpoint| mi::number(breakpoint, "number"))
+            .collect();
+        self.session_watchpoints(&session_id, |watchpoints| {
+            watchpoints.check_slots(&existing, needed)
+        })
+        .await?;
+
+        let flag = match kind {
+            WatchKind::Write => "",
+            WatchKind::Read => "-r ",
+            WatchKind::Access => "-a ",
+        };
+        let record = self
+            .mi_command(
+                &session_id,
+                &format!("-break-watch {}{}", flag, mi::quote(&watched)),
+            )
+            .await?;
+        let number = ["wpt", "hw-rwpt", "hw-awpt"]
+            .iter()
+            .find_map(|name| record.get(name))
+            .and_then(|watchpoint| mi::number(watchpoint, "number"))
+            .ok_or("GDB did not report the number of the watchpoint".to_owned())?;
+        self.session_watchpoints(&session_id, |watchpoints| {
+            watchpoints.add(number, needed);
+            Ok(())
+        })
+        .await?;
+        if let Some(condition) = condition.filter(|condition| !condition.trim().is_empty()) {
+            let command = format!(
+                "-break-condition {} {}",
+                number,
+                mi::quote(condition.trim())
+            );
+            self.mi_command(&session_id, &command).await?;
+        }
+
+        let mut watchpoint = self
+            .list_breakpoints(&session_id, &format!("-break-info {}", number))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(format!("Watchpoint {} not found", number))?;
+        watchpoint["kind"] = kind.name().into();
+        watchpoint["address"] = format!("{:#x}", start).into();
+        watchpoint["length"] = length.into();
+        watchpoint["hardware_slots"] = needed.into();
+        if !collect.unwrap_or(false) {
+            return Ok(watchpoint.to_string());
+        }
+        let collected = self
+            .collect_hits(
+                &session_id,
+                number,
+                max_hits.unwrap_or(watchpoints::DEFAULT_COLLECT_HITS),
+                Duration::from_secs(timeout.unwrap_or(30)),
+            )
+            .await?;
+        Ok(json!({ "watchpoint": watchpoint, "collected": collected }).to_string())
+    }
+
+    #[tool(
+        description = "Get the watchpoint hits collected in a session as JSON: old value, new value, thread and backtrace of each, oldest first"
+    )]
+    async fn gdb_watch_history(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Only return the hits of this watchpoint")]
+        watchpoint: Option<u64>,
+    ) -> Result<String, String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        Ok(session.watchpoints.history_json(watchpoint, 0).to_string())
+    }
+
+    #[tool(
+        description = "Get the backtrace of a thread as JSON frames with level, function, address, file, line, library and arguments"
+    )]
+    async fn gdb_backtrace(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "GDB thread number. Defaults to the current thread")]
+        thread: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Only list this many innermost frames")]
+        depth: Option<u64>,
+        #[tool(param)]
+        #[schemars(
+            description = "Collapse consecutive frames of the same function, as in deep recursion, into one frame with a repeat count"
+        )]
+        collapse_recursion: Option<bool>,
+    ) -> Result<String, String> {
+        Ok(self
+            .backtrace(
+                &session_id,
+                thread,
+                depth,
+                collapse_recursion.unwrap_or(false),
+            )
+            .await?
+            .to_string())
+    }
+
+    #[tool(
+        description = "List the threads of the program as JSON: ID, name, state (stopped or running) and top frame of every thread, and the current thread"
+    )]
+    async fn gdb_threads(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let non_stop = self.non_stop(&session_id).await?;
+        let info = self
+            .mi_command(&session_id, "-thread-info")
+            .await?
+            .into_value();
+        let threads: Vec<Value> = info
+            .get("threads")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(thread_json)
+            .collect();
+        Ok(mi::compact(json!({
+            "current_thread": mi::number(&info, "current-thread-id"),
+            "non_stop": non_stop,
+            "threads": threads,
+        }))
+        .to_string())
+    }
+
+    #[tool(
+        description = "Select the current thread, which commands without a thread apply to. Returns its top frame as JSON if it is stopped"
+    )]
+    async fn gdb_thread_select(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "ID of the thread, as listed by gdb_threads")]
+        thread: u64,
+    ) -> Result<String, String> {
+        let selected = self
+            .mi_command(&session_id, &format!("-thread-select {}", thread))
+            .await?
+            .into_value();
+        Ok(mi::compact(json!({
+            "thread": mi::number(&selected, "new-thread-id"),
+            "frame": selected.get("frame").map(|frame| {
+                frame_json(frame, frame.get("args").cloned().unwrap_or_default())
+            }),
+        }))
+        .to_string())
+    }
+
+    #[tool(
+        description = "Continue the program. In non-stop mode, continue a single thread while the others stay stopped, or all threads. Wait for the next stop with gdb_wait"
+    )]
+    async fn gdb_continue(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "ID of the thread to continue, in non-stop mode only. All threads if not given"
+        )]
+        thread: Option<u64>,
+    ) -> Result<String, String> {
+        let non_stop = self.non_stop(&session_id).await?;
+        let command = match (thread, non_stop) {
+            (Some(thread), true) => format!("-exec-continue --thread {}", thread),
+            (None, true) => "-exec-continue --all".to_owned(),
+            (None, false) => "-exec-continue".to_owned(),
+            (Some(_), false) => return Err(single_thread_error("continued")),
+        };
+        self.mi_command(&session_id, &command).await?;
+        Ok(match thread {
+            Some(thread) => format!(
+                "Thread {} continued, the other threads keep their state.",
+                thread
+            ),
+            None => "Program continued.".to_owned(),
+        })
+    }
+
+    #[tool(
+        description = "Interrupt the running program. In non-stop mode, interrupt a single thread while the others keep running, or all threads. Wait for the stop with gdb_wait"
+    )]
+    async fn gdb_interrupt(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "ID of the thread to interrupt, in non-stop mode only. All threads if not given"
+        )]
+        thread: Option<u64>,
+    ) -> Result<String, String> {
+        let non_stop = self.non_stop(&session_id).await?;
+        match (thread, non_stop) {
+            (Some(thread), true) => {
+                let command = format!("-exec-interrupt --thread {}", thread);
+                self.mi_command(&session_id, &command).await?;
+            }
+            (None, true) => {
+                self.mi_command(&session_id, "-exec-interrupt --all")
+                    .await?;
+            }
+            (None, false) => {
+                // In all-stop mode, GDB does not read commands while the program runs. The program is sent SIGINT
+                // instead, checked against the policy as the command it stands for.
+                self.context
+                    .authorize(
+                        &self.client,
+                        Backend::Gdb,
+                        &self.sessions,
+                        &session_id,
+                        "-exec-interrupt",
+                    )
+                    .await?;
+                let sessions = self.sessions.lock().await;
+                let session = sessions.get(&session_id).ok_or(format!(
+                    "Session with ID {} not found. Start a new session",
+                    session_id
+                ))?;
+                session
+                    .interrupt()
+                    .map_err(|err| format!("Failed to interrupt the program. [Error]: {}", err))?;
+            }
+            (Some(_), false) => return Err(single_thread_error("interrupted")),
+        }
+        Ok(match thread {
+            Some(thread) => format!(
+                "Thread {} interrupted, the other threads keep their state.",
+                thread
+            ),
+            None => "Program interrupted.".to_owned(),
+        })
+    }
+
+    #[tool(
+        description = "Create a variable object for an expression, to explore large structures, arrays and lists lazily with gdb_var_children. Returns its name, type, value and number of children as JSON"
+    )]
+    async fn gdb_var_create(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Expression to watch, such as a variable name")]
+        expression: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Value format: natural (default), hexadecimal, decimal, octal, binary or zero-hexadecimal"
+        )]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Evaluate the expression again in whichever frame is current at each update, instead of the frame it was created in"
+        )]
+        floating: Option<bool>,
+    ) -> Result<String, String> {
+        let format = format.as_deref().map(var_format).transpose()?;
+        let frame = if floating.unwrap_or(false) { "@" } else { "*" };
+        let created = self
+            .mi_command(
+                &session_id,
+                &format!("-var-create - {} {}", frame, mi::quote(&expression)),
+            )
+            .await?
+            .into_value();
+        let mut variable = var_json(&created);
+        variable["expression"] = expression.into();
+        if let Some(format) = format {
+            let name = mi::field(&created, "name")
+                .ok_or("GDB did not report the name of the variable object")?;
+            let formatted = self
+                .mi_command(
+                    &session_id,
+                    &format!("-var-set-format {} {}", mi::quote(name), format),
+                )
+                .await?;
+            variable["value"] = formatted.get("value").cloned().unwrap_or_default();
+            variable["format"] = format.into();
+        }
+        Ok(mi::compact(variable).to_string())
+    }
+
+    #[tool(
+        description = "List the children of a variable object (struct members, array elements, pointed-to values) as JSON. Children are variable objects too and can be explored further"
+    )]
+    async fn gdb_var_children(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Name of the variable object")]
+        name: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Value format of the children: natural (default), hexadecimal, decimal, octal, binary or zero-hexadecimal"
+        )]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Index of the first child to list, for large arrays")]
+        from: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Index after the last child to list, for large arrays")]
+        to: Option<u64>,
+    ) -> Result<String, String> {
+        let format = format.as_deref().map(var_format).transpose()?;
+        let range = match (from, to) {
+            (None, None) => String::new(),
+            (from, to) => format!(" {} {}", from.unwrap_or(0), to.unwrap_or(i32::MAX as u64)),
+        };
+        let listed = self
+            .mi_command(
+                &session_id,
+                &format!(
+                    "-var-list-children --simple-values {}{}",
+                    mi::quote(&name),
+                    range
+                ),
+            )
+            .await?;
+        let mut children: Vec<Value> = listed
+            .get("children")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(var_json)
+            .collect();
+
+        if let Some(format) = format
+            && !children.is_empty()
+        {
+            let commands: Vec<String> = children
+                .iter()
+                .filter_map(|child| mi::field(child, "name"))
+                .map(|child| format!("-var-set-format {} {}", mi::quote(child), format))
+                .collect();
+            let formatted = self.mi_commands(&session_id, &commands).await?;
+            for (child, record) in children.iter_mut().zip(formatted) {
+                if let Some(value) = record.get("value") {
+                    child["value"] = value.clone();
+                }
+            }
+        }
+        Ok(mi::compact(json!({
+            "children": children,
+            "has_more": listed.get("has_more").and_then(Value::as_str).map(|more| more != "0"),
+        }))
+        .to_string())
+    }
+
+    #[tool(
+        description = "Re-evaluate variable objects and list the ones whose value changed since the last update, as JSON"
+    )]
+    async fn gdb_var_update(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Name of the variable object. Defaults to all of them")]
+        name: Option<String>,
+    ) -> Result<String, String> {
+        let name = name.map(|name| mi::quote(&name)).unwrap_or("*".to_owned());
+        let updated = self
+            .mi_command(&session_id, &format!("-var-update --all-values {}", name))
+            .await?;
+        let changed: Vec<Value> = updated
+            .get("changelist")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|change| {
+                mi::compact(json!({
+                    "name": mi::field(change, "name"),
+                    "value": mi::field(change, "value"),
+                    "in_scope": mi::field(change, "in_scope"),
+                    "new_type": mi::field(change, "new_type"),
+                    "num_children": mi::number(change, "new_num_children"),
+                }))
+            })
+            .collect();
+        Ok(json!({ "changed": changed }).to_string())
+    }
+
+    #[tool(description = "Delete a variable object and its children")]
+    async fn gdb_var_delete(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Name of the variable object")]
+        name: String,
+    ) -> Result<String, String> {
+        let deleted = self
+            .mi_command(&session_id, &format!("-var-delete {}", mi::quote(&name)))
+            .await?
+            .into_value();
+        Ok(json!({ "deleted": mi::number(&deleted, "ndeleted") }).to_string())
+    }
+
+    #[tool(
+        description = "Read memory as a hexdump plus optional decodings, as JSON. Unreadable parts of the range are reported instead of failing the read"
+    )]
+    async fn gdb_read_memory(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Expression giving the start address, such as &buf, ptr + 16 or 0x601040"
+        )]
+        address: String,
+        #[tool(param)]
+        #[schemars(description = "Number of bytes to read, at most 65536")]
+        length: u64,
+        #[tool(param)]
+        #[schemars(description = "Decodings to add: u8, u16, u32, u64, ascii, utf16")]
+        decode: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(
+            description = "Decode integers and UTF-16 as big endian instead of little endian"
+        )]
+        big_endian: Option<bool>,
+    ) -> Result<String, String> {
+        memory::check_length(length)?;
+        let decodings = decode
+            .unwrap_or_default()
+            .iter()
+            .map(|decoding| decoding.parse())
+            .collect::<Result<Vec<Decoding>, String>>()?;
+
+        let command = format!("-data-read-memory-bytes {} {}", mi::quote(&address), length);
+        let memory = match self.mi_command(&session_id, &command).await {
+            Ok(record) => {
+                let blocks = record
+                    .get("memory")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                // Offsets are relative to the requested address, which GDB does not report otherwise.
+                let start = blocks
+                    .first()
+                    .and_then(|block| {
+                        let begin = memory::parse_address(mi::field(block, "begin")?)?;
+                        let offset = memory::parse_address(mi::field(block, "offset")?)?;
+                        begin.checked_sub(offset)
+                    })
+                    .ok_or("GDB did not report the address of the memory read")?;
+                let mut memory = Memory::new(start, length);
+                for block in blocks {
+                    if let Some(begin) = mi::field(block, "begin").and_then(memory::parse_address)
+                        && let Some(contents) = mi::field(block, "contents")
+                    {
+                        memory.add(begin, decode_hex(contents));
+                    }
+                }
+                memory
+            }
+            // Nothing could be read. Report the whole range as unreadable if its address can be found.
+            Err(err) => {
+                let value = self
+                    .mi_command(
+                        &session_id,
+                        &format!("-data-evaluate-expression {}", mi::quote(&address)),
+                    )
+                    .await
+                    .map_err(|_| err.clone())?;
+                let start = value
+                    .get("value")
+                    .and_then(Value::as_str)
+                    .and_then(|value| value.split_whitespace().find_map(memory::parse_address))
+                    .ok_or(err)?;
+                Memory::new(start, length)
+            }
+        };
+        Ok(memory
+            .to_json(&decodings, big_endian.unwrap_or(false))
+            .to_string())
+    }
+
+    #[tool(
+        description = "Read registers of the selected frame as JSON, filtered by group and optionally only those changed since the last stop"
+    )]
+    async fn gdb_registers(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Register group: general (default), float, vector or all")]
+        group: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Only return registers that changed since the program last stopped"
+        )]
+        changed_since_last_stop: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Value format: hex (default) or natural")]
+        format: Option<String>,
+    ) -> Result<String, String> {
+        let group: RegisterGroup = group.as_deref().unwrap_or("general").parse()?;
+        let changed_only = changed_since_last_stop.unwrap_or(false);
+        let format = match format.as_deref().map(str::trim).unwrap_or("hex") {
+            "hex" | "hexadecimal" => "x",
+            "natural" => "N",
+            format => {
+                return Err(format!(
+                    "Unknown register format '{}'. Expected hex or natural",
+                    format
+                ));
+            }
+        };
+
+        let mut commands = vec!["-data-list-register-names".to_owned()];
+        if changed_only {
+            commands.push("-data-list-changed-registers".to_owned());
+        }
+        let records = self.mi_commands(&session_id, &commands).await?;
+        let names: Vec<&str> = records[0]
+            .get("register-names")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|name| name.as_str().unwrap_or_default())
+            .collect();
+        let changed: Option<Vec<&str>> = records.get(1).map(|record| {
+            record
+                .get("changed-registers")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_str)
+                .collect()
+        });
+        // Registers are identified by their position in the list of names.
+        let numbers: Vec<String> = registers::select(&names, group)
+            .into_iter()
+            .filter_map(|name| names.iter().position(|known| *known == name))
+            .map(|number| number.to_string())
+            .filter(|number| {
+                changed
+                    .as_ref()
+                    .is_none_or(|changed| changed.contains(&number.as_str()))
+            })
+            .collect();
+        if numbers.is_empty() {
+            return Ok(registers::to_json(group, changed_only, &[]).to_string());
+        }
+
+        let command = format!(
+            "-data-list-register-values --skip-unavailable {} {}",
+            format,
+            numbers.join(" ")
+        );
+        let record = self.mi_command(&session_id, &command).await?;
+        let values: Vec<(String, String)> = record
+            .get("register-values")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|register| {
+                let name = names.get(usize::try_from(mi::number(register, "number")?).ok()?)?;
+                Some(((*name).to_owned(), mi::field(register, "value")?.to_owned()))
+            })
+            .collect();
+        Ok(registers::to_json(group, changed_only, &values).to_string())
+    }
+
+    #[tool(
+        description = "Disassemble a function, an address range, or the function around the current instruction, as JSON with the source line of every instruction and the current instruction marked"
+    )]
+    async fn gdb_disassemble(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Function to disassemble, by name or by an address inside it")]
+        function: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Start address expression of the range to disassemble")]
+        start: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "End address expression of the range to disassemble, exclusive")]
+        end: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Without function or range, number of instructions to show before and after the current one (default 10)"
+        )]
+        window: Option<usize>,
+        #[tool(param)]
+        #[schemars(description = "Add the source file and line of instructions (default true)")]
+        source: Option<bool>,
+    ) -> Result<String, String> {
+        let range = disassembly::Range::new(function, start, end, window)?;
+        // Raw opcodes, with or without source lines.
+        let mode = if source.unwrap_or(true) { 5 } else { 4 };
+        let command = match &range {
+            disassembly::Range::Function(function) => {
+                format!("-data-disassemble -a {} -- {}", mi::quote(function), mode)
+            }
+            disassembly::Range::Addresses(start, end) => format!(
+                "-data-disassemble -s {} -e {} -- {}",
+                mi::quote(start),
+                mi::quote(end),
+                mode
+            ),
+            disassembly::Range::AroundPc(_) => format!("-data-disassemble -a $pc -- {}", mode),
+        };
+        // There is no program counter before the program runs, which only matters when disassembling around it.
+        let pc = match self.mi_command(&session_id, "-stack-info-frame").await {
+            Ok(record) => record
+                .get("frame")
+                .and_then(|frame| mi::field(frame, "addr"))
+                .and_then(memory::parse_address),
+            Err(err) if matches!(range, disassembly::Range::AroundPc(_)) => {
+                return Err(format!(
+                    "{}. Without a running program, give a function or an address range",
+                    err
+                ));
+            }
+            Err(_) => None,
+        };
+        let record = self.mi_command(&session_id, &command).await?;
+
+        let mut instructions = Vec::new();
+        for item in record
+            .get("asm_insns")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            match item.get("line_asm_insn").and_then(Value::as_array) {
+                Some(lines) => {
+                    let file = mi::field(item, "fullname").or(mi::field(item, "file"));
+                    let line = mi::number(item, "line");
+                    instructions.extend(lines.iter().filter_map(|instruction| {
+                        let mut instruction = instruction_json(instruction)?;
+                        instruction.file = file.map(str::to_owned);
+                        instruction.line = line;
+                        Some(instruction)
+                    }));
+                }
+                None => instructions.extend(instruction_json(item)),
+            }
+        }
+        Ok(disassembly::to_json(&instructions, pc, &range).to_string())
+    }
+
+    #[tool(
+        description = "List the running processes of the user running the server with PID, name, user and command line, as JSON, to find a process to attach to"
+    )]
+    async fn gdb_list_processes(
+        &self,
+        #[tool(param)]
+        #[schemars(
+            description = "Only list processes whose name or command line contains this text"
+        )]
+        filter: Option<String>,
+    ) -> Result<String, String> {
+        Ok(process::list_json(filter.as_deref()).to_string())
+    }
+
+    #[tool(
+        description = "Attach a GDB session to a running process, by PID or by name. The process stops until continued"
+    )]
+    async fn gdb_attach(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "PID of the process")]
+        pid: Option<u32>,
+        #[tool(param)]
+        #[schemars(
+            description = "Name of the process, if not given by PID. It must match exactly one process"
+        )]
+        name: Option<String>,
+    ) -> Result<String, String> {
+        let target = process::find(pid, name.as_deref())?;
+        self.check_attachable(&session_id).await?;
+
+        let command = format!("-target-attach {}", target.pid);
+        self.mi_command(&session_id, &command)
+            .await
+            .map_err(|err| {
+                let explanation = process::explain_attach_failure("gdb", &target, &err)
+                    .map(|explanation| format!(" {}", explanation))
+                    .unwrap_or_default();
+                format!(
+                    "Failed to attach to process {}.{} [Error]: {}",
+                    target.pid, explanation, err
+                )
+            })?;
+        Ok(format!(
+            "Attached to process {} ({}). It is stopped until continued. Detach with gdb_detach to leave it running.",
+            target.pid, target.command
+        ))
+    }
+
+    #[tool(
+        description = "Detach a GDB session from the process it is attached to, leaving the process running"
+    )]
+    async fn gdb_detach(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        self.mi_command(&session_id, "-target-detach").await?;
+        Ok("Detached from the process, which keeps running.".to_owned())
+    }
+
+    #[tool(
+        description = "Connect a GDB session to a remote target, such as gdbserver in another container or QEMU user mode started with -g"
+    )]
+    async fn gdb_connect(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Address of the remote stub: host:port, or a serial device such as /dev/ttyUSB0"
+        )]
+        address: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Use target extended-remote, which stays connected when the program exits and can run or attach to programs through the stub (gdbserver --multi)"
+        )]
+        extended: Option<bool>,
+    ) -> Result<String, String> {
+        remote::check_address(&address)?;
+        self.check_connectable(&session_id).await?;
+        let protocol = if extended.unwrap_or(false) {
+            "extended-remote"
+        } else {
+            "remote"
+        };
+        let command = format!("-target-select {} {}", protocol, mi::quote(&address));
+        self.mi_command(&session_id, &command)
+            .await
+            .map_err(|err| format!("Failed to connect to {}. [Error]: {}", address, err))?;
+        if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+            session.remote = Some(Connection::new(&address, protocol));
+        }
+        Ok(format!(
+            "Connected to the remote target at {} (target {}). Disconnect with gdb_disconnect.",
+            address, protocol
+        ))
+    }
+
+    #[tool(
+        description = "Disconnect a GDB session from its remote target. A gdbserver started with gdb_spawn_server is stopped"
+    )]
+    async fn gdb_disconnect(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        self.mi_command(&session_id, "-target-disconnect").await?;
+        let remote = match self.sessions.lock().await.get_mut(&session_id) {
+            Some(session) => session.remote.take(),
+            None => None,
+        };
+        Ok(match remote {
+            Some(remote) => format!("Disconnected from the remote target at {}.", remote.address),
+            None => "Disconnected from the remote target.".to_owned(),
+        })
+    }
+
+    #[tool(
+        description = "Start a program under a local gdbserver on a free port and connect a GDB session to it. The program is stopped at its first instruction until continued, and gdbserver is stopped with the session"
+    )]
+    async fn gdb_spawn_server(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the program to debug")]
+        program: String,
+        #[tool(param)]
+        #[schemars(description = "Arguments to pass to the program")]
+        arguments: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        let activity = self.check_connectable(&session_id).await?;
+        let program = self.context.resolve_path(&self.client, &program).await?;
+        let sandbox = self
+            .context
+            .sandbox
+            .enabled
+            .then_some(self.context.sandbox.as_ref());
+        let (stub, address) = remote::spawn(
+            Stub::Gdbserver,
+            &program,
+            &arguments.unwrap_or_default(),
+            sandbox,
+            activity,
+        )
+        .await?;
+        let pid = stub.id();
+
+        let commands = [
+            format!(
+                "-file-exec-and-symbols {}",
+                mi::quote(&program.display().to_string())
+            ),
+            format!("-target-select remote {}", address),
+        ];
+        self.mi_commands(&session_id, &commands).await?;
+        if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+            session.remote = Some(Connection::spawned(
+                &address,
+                "remote",
+                Stub::Gdbserver,
+                stub,
+            ));
+        }
+        Ok(format!(
+            "Started {} under gdbserver (PID {}) on {} and connected to it. The program is stopped at its first instruction until continued.",
+            program.display(),
+            pid.map(|pid| pid.to_string())
+                .unwrap_or("unknown".to_owned()),
+            address
+        ))
+    }
+
+    #[tool(
+        description = "Load a core dump for post-mortem analysis and summarize the crash as JSON: signal, faulting address, backtrace of the crashing thread and libraries whose symbols could not be loaded"
+    )]
+    async fn gdb_load_core(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the core file")]
+        core: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Path to the executable that dumped the core. Found from the core if not given"
+        )]
+        executable: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Directory holding a copy of the root filesystem of the machine that dumped the core, to find its libraries"
+        )]
+        sysroot: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Directories to search for shared libraries")]
+        solib_search_paths: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        let core = self.context.resolve_path(&self.client, &core).await?;
+        let executable =
+            coredump::executable(&self.context, &self.client, &core, executable.as_deref()).await?;
+        let mut commands = Vec::new();
+        if let Some(sysroot) = sysroot {
+            let sysroot = self.context.resolve_path(&self.client, &sysroot).await?;
+            commands.push(format!(
+                "-gdb-set sysroot {}",
+                mi::quote(&sysroot.display().to_string())
+            ));
+        }
+        if let Some(paths) = solib_search_paths {
+            let mut resolved = Vec::new();
+            for path in paths {
+                resolved.push(self.context.resolve_path(&self.client, &path).await?);
+            }
+            let resolved: Vec<String> = resolved
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            commands.push(format!(
+                "-gdb-set solib-search-path {}",
+                mi::quote(&resolved.join(":"))
+            ));
+        }
+        commands.push(format!(
+            "-file-exec-and-symbols {}",
+            mi::quote(&executable.path.display().to_string())
+        ));
+        commands.push(format!(
+            "core-file {}",
+            mi::quote(&core.display().to_string())
+        ));
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Gdb,
+                &self.sessions,
+                &session_id,
+                &commands.join("\n"),
+            )
+            .await?;
+
+        // Loading prints the signal and the warnings as console and log stream records.
+        let mut output = String::new();
+        {
+            let mut sessions = self.sessions.lock().await;
+            let session = sessions.get_mut(&session_id).ok_or(format!(
+                "Session with ID {} not found. Start a new session",
+                session_id
+            ))?;
+            for command in &commands {
+                let response = session
+                    .execute_command(command)
+                    .await
+                    .map_err(|err| format!("Failed to load core. [Error]: {}", err))?;
+                mi::result_record(&response).map_err(|err| {
+                    format!(
+                        "Failed to load core. [Command]: {} [Error]: {}",
+                        command, err
+                    )
+                })?;
+                output.push_str(&response);
+            }
+        }
+        let streams: Vec<String> = output
+            .lines()
+            .filter(|line| line.starts_with('~') || line.starts_with('&'))
+            .filter_map(|line| mi::c_string(&line[1..]).ok())
+            .collect();
+        let (signal, description) = streams
+            .iter()
+            .find_map(|text| {
+                let signal = text.split_once("Program terminated with signal ")?.1;
+                let signal = signal.trim_end().trim_end_matches('.');
+                Some(match signal.split_once(", ") {
+                    Some((signal, description)) => {
+                        (signal.to_owned(), Some(description.to_owned()))
+                    }
+                    None => (signal.to_owned(), None),
+                })
+            })
+            .unzip();
+        let mut warnings: Vec<String> = executable.mismatch.into_iter().collect();
+        for text in &streams {
+            let text = text.trim();
+            if text.starts_with("warning:") && !warnings.iter().any(|warning| warning == text) {
+                warnings.push(text.to_owned());
+            }
+        }
+
+        // The address is only meaningful for signals raised by a faulting instruction.
+        let fault_address = match signal.as_deref() {
+            Some("SIGSEGV" | "SIGBUS" | "SIGILL" | "SIGFPE") => self
+                .mi_command(
+                    &session_id,
+                    "-data-evaluate-expression $_siginfo._sifields._sigfault.si_addr",
+                )
+                .await
+                .ok()
+                .and_then(|record| {
+                    let value = record.get("value")?.as_str()?.to_owned();
+                    value.split_whitespace().find_map(memory::parse_address)
+                })
+                .map(|address| format!("{:#x}", address)),
+            _ => None,
+        };
+        let thread = self
+            .mi_command(&session_id, "-thread-info")
+            .await
+            .ok()
+            .and_then(|record| mi::number(&record.into_value(), "current-thread-id"));
+        let missing_libraries: Vec<String> = self
+            .mi_command(&session_id, "-file-list-shared-libraries")
+            .await
+            .ok()
+            .and_then(|record| record.get("shared-libraries").cloned())
+            .and_then(|libraries| libraries.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter(|library| mi::field(library, "symbols-loaded") == Some("0"))
+            .filter_map(|library| mi::field(library, "target-name").map(str::to_owned))
+            .collect();
+        let backtrace = self
+            .backtrace(&session_id, None, Some(CORE_BACKTRACE_DEPTH), true)
+            .await
+            .unwrap_or_default();
+
+        Ok(mi::compact(json!({
+            "executable": executable.path.display().to_string(),
+            "executable_inferred": executable.inferred,
+            "core": core.display().to_string(),
+            "signal": signal,
+            "signal_description": description.flatten(),
+            "fault_address": fault_address,
+            "thread": thread,
+            "frames": backtrace.get("frames"),
+            "frames_truncated": backtrace.get("truncated"),
+            "missing_libraries": missing_libraries,
+            "warnings": warnings,
+        }))
+        .to_string())
+    }
+
+    #[tool(description = "Terminate a GDB session")]
+    async fn gdb_terminate(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "GDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        session
+            .terminate()
+            .await
+            .map_err(|err| format!("Failed to terminate GDB session. [Error]: {}", err))?;
+        sessions.remove(&session_id);
+        self.context.limits.release(&self.namespace);
+        self.context
+            .logger
+            .log(format!("GDB session {} terminated", session_id));
+        Ok("GDB session terminated".to_string())
+    }
+}
+
+impl GdbServer {
+    /// Run a GDB/MI command in a session, checked like any other command, and parse its result record.
+    async fn mi_command(
+        &self,
+        session_id: &str,
+        command: &str,
+    ) -> Result<mi::ResultRecord, String> {
+        let mut records = self.mi_commands(session_id, &[command.to_owned()]).await?;
+        Ok(records.remove(0))
+    }
+
+    /// Run GDB/MI commands in a session one after the other, checked together, and parse their result records.
+    /// Stops at the first command that fails.
+    async fn mi_commands(
+        &self,
+        session_id: &str,
+        commands: &[String],
+    ) -> Result<Vec<mi::ResultRecord>, String> {
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Gdb,
+                &self.sessions,
+                session_id,
+                &commands.join("\n"),
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let mut records = Vec::new();
+        for command in commands {
+            let output = session
+                .execute_command(command)
+                .await
+                .map_err(|err| format!("Failed to execute GDB command. [Error]: {}", err))?;
+            let record = mi::result_record(&output).map_err(|err| {
+                format!(
+                    "GDB command failed. [Command]: {} [Error]: {}",
+                    command, err
+                )
+            })?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Frames of a thread as JSON, see `gdb_backtrace`.
+    async fn backtrace(
+        &self,
+        session_id: &str,
+        thread: Option<u64>,
+        depth: Option<u64>,
+        collapse_recursion: bool,
+    ) -> Result<Value, String> {
+        let mut options = String::new();
+        if let Some(thread) = thread {
+            options.push_str(&format!(" --thread {}", thread));
+        }
+        let range = match depth {
+            Some(0) => return Err("The depth must be at least 1".to_owned()),
+            // One more frame than asked for tells whether the stack is deeper.
+            Some(depth) => format!(" 0 {}", depth),
+            None => String::new(),
+        };
+        let frames = self
+            .mi_command(
+                session_id,
+                &format!("-stack-list-frames{}{}", options, range),
+            )
+            .await?;
+        let arguments = self
+            .mi_command(
+                session_id,
+                &format!("-stack-list-arguments{} --simple-values{}", options, range),
+            )
+            .await?;
+
+        let arguments = arguments
+            .get("stack-args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut frames: Vec<Value> = frames
+            .get("stack")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|frame| {
+                let level = mi::field(frame, "level");
+                let args = arguments
+                    .iter()
+                    .find(|arguments| mi::field(arguments, "level") == level)
+                    .and_then(|arguments| arguments.get("args"))
+                    .cloned()
+                    .unwrap_or(Value::Array(Vec::new()));
+                frame_json(frame, args)
+            })
+            .collect();
+        let truncated = depth.is_some_and(|depth| frames.len() as u64 > depth);
+        if let Some(depth) = depth {
+            frames.truncate(depth as usize);
+        }
+        if collapse_recursion {
+            frames = collapse_recursive_frames(frames);
+        }
+        Ok(mi::compact(json!({
+            "thread": thread,
+            "frames": frames,
+            "truncated": truncated,
+        })))
+    }
+
+    /// Evaluate an expression to a number, such as an address or a size.
+    async fn evaluate_number(&self, session_id: &str, expression: &str) -> Result<u64, String> {
+        let record = self
+            .mi_command(
+                session_id,
+                &format!("-data-evaluate-expression {}", mi::quote(expression)),
+            )
+            .await?;
+        // Pointers are printed as `(int *) 0x601040 <counter>`, sizes and integers in decimal.
+        record
+            .get("value")
+            .and_then(Value::as_str)
+            .and_then(|value| {
+                value
+                    .split_whitespace()
+                    .find_map(|word| memory::parse_address(word).or(word.parse().ok()))
+            })
+            .ok_or(format!("{} is not a number or an address", expression))
+    }
+
+    /// Run `update` on the watchpoints of a session.
+    async fn session_watchpoints<T>(
+        &self,
+        session_id: &str,
+        update: impl FnOnce(&mut Watchpoints) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        update(&mut session.watchpoints)
+    }
+
+    /// Continue the program on every trigger of a watchpoint and record the hits, until it stops for another reason,
+    /// `max_hits` were collected or `timeout` elapsed. Returns why collecting ended and the hits as JSON.
+    async fn collect_hits(
+        &self,
+        session_id: &str,
+        watchpoint: u64,
+        max_hits: u64,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        // The commands are checked once, not on every trigger.
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Gdb,
+                &self.sessions,
+                session_id,
+                "-exec-continue\n-stack-list-frames",
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let since = session.watchpoints.recorded();
+        let deadline = Instant::now() + timeout;
+        let mut collected = 0;
+        let (ended_by, last_stop) = loop {
+            if collected >= max_hits {
+                break ("max_hits".to_owned(), None);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break ("timeout".to_owned(), None);
+            }
+            let mut output = session
+                .execute_command("-exec-continue")
+                .await
+                .map_err(|err| format!("Failed to execute GDB command. [Error]: {}", err))?;
+            mi::result_record(&output)
+                .map_err(|err| format!("Failed to continue the program. [Error]: {}", err))?;
+            if !output.contains("*stopped") {
+                match session
+                    .read_response_until(Some("*stopped"), remaining)
+                    .await
+                {
+                    Ok(more) => output.push_str(&more),
+                    Err(err) if err.kind() == std::io::ErrorKind::TimedOut => {}
+                    Err(err) => {
+                        return Err(format!("Failed to read from GDB session. [Error]: {}", err));
+                    }
+                }
+            }
+            let Some(stop) = output
+                .lines()
+                .filter_map(mi::async_record)
+                .find(|record| record.class == "stopped")
+                .map(mi::ResultRecord::into_value)
+            else {
+                break ("timeout".to_owned(), None);
+            };
+            let reason = mi::field(&stop, "reason").unwrap_or("unknown").to_owned();
+            let number = ["wpt", "hw-rwpt", "hw-awpt"]
+                .iter()
+                .find_map(|name| stop.get(*name))
+                .and_then(|watchpoint| mi::number(watchpoint, "number"));
+            if !reason.ends_with("watchpoint-trigger") || number != Some(watchpoint) {
+                let frame = stop
+                    .get("frame")
+                    .map(|frame| frame_json(frame, Value::Null));
+                break (reason, frame);
+            }
+
+            let thread = mi::field(&stop, "thread-id").map(str::to_owned);
+            let value = stop.get("value").cloned().unwrap_or_default();
+            let thread_option = thread
+                .as_ref()
+                .map(|thread| format!(" --thread {}", thread))
+                .unwrap_or_default();
+            let command = format!(
+                "-stack-list-frames{} 0 {}",
+                thread_option,
+                watchpoints::HIT_BACKTRACE_DEPTH - 1
+            );
+            let frames = session
+                .execute_command(&command)
+                .await
+                .ok()
+                .and_then(|output| mi::result_record(&output).ok())
+                .and_then(|record| record.get("stack").cloned())
+                .and_then(|stack| stack.as_array().cloned())
+                .unwrap_or_default()
+                .iter()
+                .map(|frame| frame_json(frame, Value::Null))
+                .collect();
+            session.watchpoints.record(Hit {
+                watchpoint,
+                thread,
+                old_value: mi::field(&value, "old").map(str::to_owned),
+                new_value: mi::field(&value, "new")
+                    .or(mi::field(&value, "value"))
+                    .map(str::to_owned),
+                frames,
+            });
+            collected += 1;
+        };
+
+        let mut result = session.watchpoints.history_json(Some(watchpoint), since);
+        result["ended_by"] = ended_by.into();
+        if let Some(frame) = last_stop {
+            result["stopped_at"] = frame;
+        }
+        Ok(result)
+    }
+
+    /// Whether a session runs in non-stop mode.
+    async fn non_stop(&self, session_id: &str) -> Result<bool, String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        Ok(session.non_stop)
+    }
+
+    /// Check that a session can connect to remote targets, see [`remote::check_network`], and return its activity.
+    async fn check_connectable(
+        &self,
+        session_id: &str,
+    ) -> Result<Arc<std::sync::Mutex<Activity>>, String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        remote::check_network(session, &self.context.sandbox)?;
+        Ok(session.activity().clone())
+    }
+
+    /// Check that a session can attach to processes outside of it.
+    async fn check_attachable(&self, session_id: &str) -> Result<(), String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        if session.sandbox().is_some() {
+            return Err(
+                "Sandboxed sessions cannot attach to processes outside of the sandbox".to_owned(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Run `-break-list` or `-break-info` and convert the breakpoint table to JSON.
+    async fn list_breakpoints(
+        &self,
+        session_id: &str,
+        command: &str,
+    ) -> Result<Vec<Value>, String> {
+        let record = self.mi_command(session_id, command).await?;
+        let body = record
+            .get("BreakpointTable")
+            .and_then(|table| table.get("body"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Ok(breakpoints_json(body.iter()))
+    }
+}
+
+/// Breakpoint numbers given by the client, separated by spaces for an MI command.
+/// Locations of multi-location breakpoints (as in `2.1`) are only accepted if `locations` is set.
+fn breakpoint_numbers(numbers: &[String], locations: bool) -> Result<String, String> {
+    if numbers.is_empty() {
+        return Err("No breakpoint numbers given".to_owned());
+    }
+    for number in numbers {
+        let valid = !number.is_empty()
+            && number
+                .split('.')
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+            && (locations || !number.contains('.'))
+            && number.split('.').count() <= 2;
+        if !valid {
+            return Err(format!("Invalid breakpoint number '{}'", number));
+        }
+    }
+    Ok(numbers.join(" "))
+}
+
+/// Convert `bkpt` tuples to JSON breakpoints. GDB before 13 lists the locations of a multi-location breakpoint as
+/// separate tuples numbered `N.M` right after it, later versions in its `locations` field.
+fn breakpoints_json<'a>(tuples: impl Iterator<Item = &'a Value>) -> Vec<Value> {
+    let mut breakpoints: Vec<Value> = Vec::new();
+    for tuple in tuples {
+        let is_location = mi::field(tuple, "number").is_some_and(|number| number.contains('.'));
+        if is_location && let Some(Value::Object(breakpoint)) = breakpoints.last_mut() {
+            if let Some(Value::Array(locations)) = breakpoint.get_mut("locations") {
+                locations.push(location_json(tuple));
+            }
+            continue;
+        }
+        breakpoints.push(breakpoint_json(tuple));
+    }
+    breakpoints
+}
+
+fn breakpoint_json(tuple: &Value) -> Value {
+    let address = mi::field(tuple, "addr");
+    let pending = mi::field(tuple, "pending");
+    let mut breakpoint = location_json(tuple);
+    breakpoint["type"] = mi::field(tuple, "type").into();
+    breakpoint["temporary"] = (mi::field(tuple, "disp") == Some("del")).into();
+    breakpoint["hits"] = mi::number(tuple, "times").unwrap_or(0).into();
+    breakpoint["pending"] = (pending.is_some() || address == Some("<PENDING>")).into();
+    breakpoint["pending_location"] = pending.into();
+    breakpoint["original_location"] = mi::field(tuple, "original-location").into();
+    breakpoint["condition"] = mi::field(tuple, "cond").into();
+    breakpoint["ignore_count"] = mi::number(tuple, "ignore").into();
+    breakpoint["thread"] = mi::field(tuple, "thread").into();
+    if let Some(expression) = mi::field(tuple, "what") {
+        breakpoint["expression"] = expression.into();
+    }
+    breakpoint["locations"] = tuple
+        .get("locations")
+        .and_then(Value::as_array)
+        .map(|locations| locations.iter().map(location_json).collect())
+        .unwrap_or(Value::Array(Vec::new()));
+    mi::compact(breakpoint)
+}
+
+/// Fields shared by breakpoints and their locations.
+fn location_json(tuple: &Value) -> Value {
+    let file = mi::field(tuple, "fullname").or(mi::field(tuple, "file"));
+    let line = mi::number(tuple, "line");
+    let location = file
+        .zip(line)
+        .map(|(file, line)| format!("{}:{}", file, line));
+    let address = mi::field(tuple, "addr").filter(|address| address.starts_with("0x"));
+    mi::compact(json!({
+        "number": mi::field(tuple, "number"),
+        "enabled": mi::field(tuple, "enabled") == Some("y"),
+        "address": address,
+        "function": mi::field(tuple, "func"),
+        "file": file,
+        "line": line,
+        "location": location,
+    }))
+}
+
+/// Convert a `frame` tuple and the arguments listed for it to a JSON frame.
+fn frame_json(frame: &Value, args: Value) -> Value {
+    mi::compact(json!({
+        "level": mi::number(frame, "level"),
+        "function": mi::field(frame, "func"),
+        "address": mi::field(frame, "addr"),
+        "file": mi::field(frame, "fullname").or(mi::field(frame, "file")),
+        "line": mi::number(frame, "line"),
+        "library": mi::field(frame, "from"),
+        "args": args,
+    }))
+}
+
+/// Convert a thread tuple of `-thread-info` to JSON, with its top frame if it is stopped.
+fn thread_json(thread: &Value) -> Value {
+    mi::compact(json!({
+        "id": mi::number(thread, "id"),
+        "target_id": mi::field(thread, "target-id"),
+        "name": mi::field(thread, "name"),
+        "state": mi::field(thread, "state"),
+        "core": mi::number(thread, "core"),
+        "frame": thread.get("frame").map(|frame| {
+            frame_json(frame, frame.get("args").cloned().unwrap_or_default())
+        }),
+    }))
+}
+
+/// Error for a single thread to be continued or interrupted in all-stop mode, where threads stop and run together.
+fn single_thread_error(action: &str) -> String {
+    format!(
+        "Single threads can only be {} in non-stop mode, all threads stop and run together otherwise. Start the session with non_stop to control threads one by one",
+        action
+    )
+}
+
+/// Merge runs of consecutive frames of the same function into their innermost frame, which gets the number of frames
+/// in the run as `repeated` and the level of the outermost one as `last_level`.
+fn collapse_recursive_frames(frames: Vec<Value>) -> Vec<Value> {
+    let mut collapsed: Vec<Value> = Vec::new();
+    for frame in frames {
+        let function = frame
+            .get("function")
+            .filter(|function| function.is_string());
+        if let Some(previous) = collapsed.last_mut()
+            && function.is_some()
+            && previous.get("function") == function
+            && previous.get("file") == frame.get("file")
+        {
+            let repeated = previous
+                .get("repeated")
+                .and_then(Value::as_u64)
+                .unwrap_or(1);
+            previous["repeated"] = (repeated + 1).into();
+            previous["last_level"] = frame.get("level").cloned().unwrap_or_default();
+            continue;
+        }
+        collapsed.push(frame);
+    }
+    collapsed
+}
+
+/// Check a value format given by the client against the formats of `-var-set-format`.
+fn var_format(format: &str) -> Result<&'static str, String> {
+    const FORMATS: [&str; 6] = [
+        "natural",
+        "hexadecimal",
+        "decimal",
+        "octal",
+        "binary",
+        "zero-hexadecimal",
+    ];
+    let format = match format.trim() {
+        "hex" => "hexadecimal",
+        format => format,
+    };
+    FORMATS
+        .into_iter()
+        .find(|known| *known == format)
+        .ok_or(format!(
+            "Unknown value format '{}'. Expected one of {}",
+            format,
+            FORMATS.join(", ")
+        ))
+}
+
+/// Convert the fields of a variable object, as reported by `-var-create` or `-var-list-children`, to JSON.
+fn var_json(variable: &Value) -> Value {
+    mi::compact(json!({
+        "name": mi::field(variable, "name"),
+        "expression": mi::field(variable, "exp"),
+        "type": mi::field(variable, "type"),
+        "value": mi::field(variable, "value"),
+        "num_children": mi::number(variable, "numchild"),
+        "has_more": mi::field(variable, "has_more").map(|more| more != "0"),
+    }))
+}
+
+/// Convert an instruction tuple of `-data-disassemble` to an [`Instruction`], without its source line.
+fn instruction_json(tuple: &Value) -> Option<Instruction> {
+    Some(Instruction {
+        address: memory::parse_address(mi::field(tuple, "address")?)?,
+        function: mi::field(tuple, "func-name").map(str::to_owned),
+        offset: mi::number(tuple, "offset"),
+        opcodes: mi::field(tuple, "opcodes").map(str::to_owned),
+        text: mi::field(tuple, "inst").unwrap_or_default().to_owned(),
+        ..Default::default()
+    })
+}
+
+/// Bytes of a hexadecimal string, such as the contents of a `-data-read-memory-bytes` block.
+fn decode_hex(contents: &str) -> Vec<u8> {
+    contents
+        .as_bytes()
+        .chunks(2)
+        .filter_map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Asynchronous GDB/MI records forwarded to the client as log notifications, see [`observe_session`].
+fn mi_event(line: &str) -> Option<(LoggingLevel, &'static str)> {
+    // Skip the optional token in front of the record.
+    let record = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let class = record.split(',').next()?;
+    let event = match class {
+        "*stopped"
+            if record.contains("reason=\"signal-received\"")
+                || record.contains("reason=\"exited-signalled\"") =>
+        {
+            (LoggingLevel::Warning, "signal")
+        }
+        "*stopped" if record.contains("reason=\"exited") => (LoggingLevel::Info, "exited"),
+        "*stopped" => (LoggingLevel::Notice, "stopped"),
+        "*running" => (LoggingLevel::Debug, "running"),
+        "=thread-group-started" => (LoggingLevel::Info, "process-started"),
+        "=thread-group-exited" => (LoggingLevel::Info, "process-exited"),
+        "=thread-created" => (LoggingLevel::Info, "thread-created"),
+        "=thread-exited" => (LoggingLevel::Info, "thread-exited"),
+        "=library-loaded" => (LoggingLevel::Info, "library-loaded"),
+        "=library-unloaded" => (LoggingLevel::Info, "library-unloaded"),
+        "=breakpoint-modified" => (LoggingLevel::Debug, "breakpoint-modified"),
+        _ => return None,
+    };
+    Some(event)
+}
@@ -0,0 +1,1049 @@
+//! HTTP transports for serving MCP to remote clients.
+//!
+//! Two flavours are served from the same listener:
+//! - Streamable HTTP: `POST /mcp` carries client messages, `GET /mcp` opens a stream for server initiated messages
+//!   and `DELETE /mcp` ends the session. Sessions are identified by the `Mcp-Session-Id` header.
+//! - Legacy SSE: `GET /sse` opens an event stream that announces a `/message?sessionId=...` endpoint for `POST`s.
+//!
+//! Requests from browser origins other than localhost are rejected unless allowed in the configuration, so web
+//! pages cannot reach the server through DNS rebinding. Sessions left idle are closed after a timeout. Connections
+//! are served over TLS when [`HttpConfig::tls`] is set. Clients are authenticated by bearer tokens ([`Auth`]) or, when
+//! no tokens are configured, by the name of their verified TLS client certificate, which is their session namespace.
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded},
+};
+use rmcp::{
+    ServerHandler,
+    model::{ClientJsonRpcMessage, RequestId},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::{
+    io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+        BufReader,
+    },
+    net::TcpListener,
+    time::{self, Duration},
+};
+
+use crate::{
+    auth::Auth,
+    client::Client,
+    server::Logger,
+    tls::{self, TlsConfig},
+};
+
+const MCP_PATH: &str = "/mcp";
+const SSE_PATH: &str = "/sse";
+const MESSAGE_PATH: &str = "/message";
+const SESSION_HEADER: &str = "mcp-session-id";
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// How long clients may take to send the headers or the body of a request, and keep connections idle.
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Idle time after which a session is closed, unless configured otherwise.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Settings of the http transport.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Browser origins allowed besides localhost, such as `https://example.com`. Requests carrying any other
+    /// `Origin` header are rejected.
+    pub allowed_origins: Vec<String>,
+    /// Seconds a session may go without requests or open streams before it is closed. Defaults to
+    /// [`DEFAULT_IDLE_TIMEOUT`].
+    pub session_idle_timeout: Option<u64>,
+    /// Serve HTTPS with this certificate, and optionally require client certificates.
+    pub tls: Option<TlsConfig>,
+}
+
+/// Serve MCP over HTTP on the given address until the listener fails.
+/// Every MCP session gets its own service from `make_service`, called with the namespace the client authenticated as
+/// and a handle to the client.
+pub async fn serve<S, F>(
+    make_service: F,
+    bind: SocketAddr,
+    auth: Auth,
+    config: HttpConfig,
+    logger: Logger,
+) -> Result<(), Error>
+where
+    S: ServerHandler,
+    F: Fn(&str, Client) -> S + Send + Sync + 'static,
+{
+    let acceptor = match &config.tls {
+        Some(tls) => Some(tls.acceptor().map_err(Error::other)?),
+        None => None,
+    };
+    let listener = TcpListener::bind(bind).await?;
+    logger.log(format!(
+        "Listening on {}://{} (streamable HTTP at {}, SSE at {})",
+        if acceptor.is_some() { "https" } else { "http" },
+        listener.local_addr()?,
+        MCP_PATH,
+        SSE_PATH
+    ));
+    let idle_timeout = config
+        .session_idle_timeout
+        .map_or(DEFAULT_IDLE_TIMEOUT, Duration::from_secs);
+    let state = Arc::new(HttpState {
+        make_service,
+        auth,
+        allowed_origins: config.allowed_origins,
+        sessions: Mutex::new(HashMap::new()),
+        logger,
+    });
+    let reaper = state.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(idle_timeout.min(Duration::from_secs(60)));
+        loop {
+            interval.tick().await;
+            reaper.close_idle_sessions(idle_timeout);
+        }
+    });
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let state = state.clone();
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let result = match acceptor {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => match client_name(&stream) {
+                        Ok(name) => state.handle_connection(stream, peer, name).await,
+                        Err(err) => Err(err),
+                    },
+                    Err(err) => Err(Error::new(
+                        err.kind(),
+                        format!("TLS handshake failed: {}", err),
+                    )),
+                },
+                None => state.handle_connection(stream, peer, None).await,
+            };
+            if let Err(err) = result {
+                state
+                    .logger
+                    .log(format!("HTTP connection from {} failed: {}", peer, err));
+            }
+        });
+    }
+}
+
+/// Name of the verified certificate the client of a TLS connection presented, if any.
+fn client_name<T>(stream: &tokio_rustls::server::TlsStream<T>) -> Result<Option<String>, Error> {
+    let Some(certificate) = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+    else {
+        return Ok(None);
+    };
+    tls::certificate_name(certificate).map(Some).ok_or_else(|| {
+        Error::new(
+            ErrorKind::PermissionDenied,
+            "Client certificate has no DNS or common name",
+        )
+    })
+}
+
+struct HttpState<F> {
+    make_service: F,
+    auth: Auth,
+    allowed_origins: Vec<String>,
+    sessions: Mutex<HashMap<String, Arc<McpSession>>>,
+    logger: Logger,
+}
+
+/// A single MCP session bridged to an rmcp service through channels.
+struct McpSession {
+    id: String,
+    namespace: String,
+    to_server: UnboundedSender<Value>,
+    routes: Mutex<Routes>,
+    /// When a client last made a request in the session.
+    last_active: Mutex<Instant>,
+}
+
+/// Where messages produced by the service are delivered.
+#[derive(Default)]
+struct Routes {
+    /// Responses awaited by in-flight POST requests, keyed by JSON-RPC request ID.
+    pending: HashMap<RequestId, UnboundedSender<Value>>,
+    /// Stream for messages not tied to a pending request (`GET /mcp` or legacy SSE).
+    stream: Option<UnboundedSender<Value>>,
+}
+
+impl McpSession {
+    fn route(&self, message: Value) {
+        let mut routes = self.routes.lock().unwrap();
+        if routes
+            .stream
+            .as_ref()
+            .is_some_and(|stream| stream.is_closed())
+        {
+            routes.stream = None;
+        }
+        let pending = response_id(&message).and_then(|id| routes.pending.remove(&id));
+        let target = pending
+            .or_else(|| routes.stream.clone())
+            .or_else(|| routes.pending.values().next().cloned());
+        if let Some(target) = target {
+            let _ = target.unbounded_send(message);
+        }
+    }
+
+    /// Record activity of the client, see [`HttpState::close_idle_sessions`].
+    fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    /// Whether the session went without requests for `timeout`, with no stream open. Requests still awaiting a
+    /// response do not keep a session alive, as their client may be long gone.
+    fn is_idle(&self, timeout: Duration) -> bool {
+        self.routes
+            .lock()
+            .unwrap()
+            .stream
+            .as_ref()
+            .is_none_or(|stream| stream.is_closed())
+            && self.last_active.lock().unwrap().elapsed() >= timeout
+    }
+
+    /// Stop delivering messages to the service, and end the responses and the stream awaiting messages from it.
+    fn close(&self) {
+        self.to_server.close_channel();
+        let mut routes = self.routes.lock().unwrap();
+        routes.pending.clear();
+        routes.stream = None;
+    }
+}
+
+struct HttpRequest {
+    peer: SocketAddr,
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    fn accepts_event_stream(&self) -> bool {
+        self.header("accept")
+            .is_some_and(|accept| accept.contains("text/event-stream"))
+    }
+}
+
+/// Response produced by a request handler. Streams take over the connection until they end.
+enum HttpResponse {
+    Full {
+        status: u16,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    },
+    Stream {
+        headers: Vec<(&'static str, String)>,
+        prelude: Option<String>,
+        messages: UnboundedReceiver<Value>,
+        session: Option<Arc<McpSession>>,
+    },
+}
+
+impl HttpResponse {
+    fn status(status: u16, body: impl Into<String>) -> Self {
+        HttpResponse::Full {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    fn json(status: u16, headers: Vec<(&'static str, String)>, body: String) -> Self {
+        let mut headers = headers;
+        headers.push(("Content-Type", "application/json".to_owned()));
+        HttpResponse::Full {
+            status,
+            headers,
+            body,
+        }
+    }
+}
+
+impl<S, F> HttpState<F>
+where
+    S: ServerHandler,
+    F: Fn(&str, Client) -> S + Send + Sync + 'static,
+{
+    /// Serve the requests of a connection. `client_name` is the name of the verified client certificate, if any.
+    async fn handle_connection<T>(
+        &self,
+        stream: T,
+        peer: SocketAddr,
+        client_name: Option<String>,
+    ) -> Result<(), Error>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        while let Some(request) = read_request(&mut reader, peer, REQUEST_READ_TIMEOUT).await? {
+            match self.handle_request(request, client_name.as_deref()).await {
+                HttpResponse::Full {
+                    status,
+                    headers,
+                    body,
+                } => write_response(&mut writer, status, &headers, &body).await?,
+                HttpResponse::Stream {
+                    headers,
+                    prelude,
+                    messages,
+                    session,
+                } => {
+                    let result = write_stream(&mut writer, &headers, prelude, messages).await;
+                    if let Some(session) = session {
+                        self.logger
+                            .log(format!("SSE session {} disconnected", session.id));
+                        self.remove_session(&session.id);
+                    }
+                    return result;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_request(
+        &self,
+        request: HttpRequest,
+        client_name: Option<&str>,
+    ) -> HttpResponse {
+        if let Some(origin) = request.header("origin")
+            && !self.origin_allowed(origin)
+        {
+            self.logger.log(format!(
+                "Rejected {} {} from origin {}",
+                request.method, request.path, origin
+            ));
+            return HttpResponse::status(403, "Origin not allowed");
+        }
+        // Clients with a verified certificate get the namespace of its name, unless bearer tokens are required.
+        let namespace = match client_name {
+            Some(name) if !self.auth.is_enabled() => Some(name),
+            _ => self.auth.authenticate(request.header("authorization")),
+        };
+        let Some(namespace) = namespace else {
+            self.logger.log(format!(
+                "Rejected unauthenticated {} {}",
+                request.method, request.path
+            ));
+            return HttpResponse::Full {
+                status: 401,
+                headers: vec![("WWW-Authenticate", "Bearer".to_owned())],
+                body: "Unauthorized".to_owned(),
+            };
+        };
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", MCP_PATH) => self.post_mcp(request, namespace).await,
+            ("GET", MCP_PATH) => self.get_mcp(request, namespace),
+            ("DELETE", MCP_PATH) => match self.session_from_header(&request, namespace) {
+                Ok(session) => {
+                    self.remove_session(&session.id);
+                    HttpResponse::status(200, "")
+                }
+                Err(response) => response,
+            },
+            ("GET", SSE_PATH) => self.get_sse(namespace, request.peer),
+            ("POST", MESSAGE_PATH) => self.post_message(request, namespace),
+            (_, MCP_PATH | SSE_PATH | MESSAGE_PATH) => {
+                HttpResponse::status(405, "Method not allowed")
+            }
+            _ => HttpResponse::status(404, "Not found"),
+        }
+    }
+
+    /// Streamable HTTP: deliver client messages and answer with the responses to any requests among them.
+    async fn post_mcp(&self, request: HttpRequest, namespace: &str) -> HttpResponse {
+        let (messages, batch) = match parse_messages(&request.body) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                return HttpResponse::status(400, format!("Invalid JSON-RPC message: {}", err));
+            }
+        };
+        let initialize = messages
+            .iter()
+            .any(|message| message.get("method").and_then(Value::as_str) == Some("initialize"));
+        let session = if initialize {
+            match self.create_session(namespace, request.peer) {
+                Ok(session) => session,
+                Err(response) => return response,
+            }
+        } else {
+            match self.session_from_header(&request, namespace) {
+                Ok(session) => session,
+                Err(response) => return response,
+            }
+        };
+
+        let request_ids: Vec<RequestId> = messages
+            .iter()
+            .filter(|message| message.get("method").is_some())
+            .filter_map(|message| serde_json::from_value(message.get("id")?.clone()).ok())
+            .collect();
+        if request_ids.is_empty() {
+            for message in messages {
+                let _ = session.to_server.unbounded_send(message);
+            }
+            return HttpResponse::status(202, "");
+        }
+
+        let (responses_tx, mut responses) = unbounded();
+        {
+            let mut routes = session.routes.lock().unwrap();
+            for id in &request_ids {
+                routes.pending.insert(id.clone(), responses_tx.clone());
+            }
+        }
+        // The stream of responses ends once every pending request has been answered.
+        drop(responses_tx);
+        for message in messages {
+            let _ = session.to_server.unbounded_send(message);
+        }
+
+        let headers = vec![("Mcp-Session-Id", session.id.clone())];
+        if request.accepts_event_stream() {
+            return HttpResponse::Stream {
+                headers,
+                prelude: None,
+                messages: responses,
+                session: None,
+            };
+        }
+        let mut collected = Vec::new();
+        while let Some(message) = responses.next().await {
+            if response_id(&message).is_some() {
+                collected.push(message);
+            } else if let Some(id) = message
+                .get("id")
+                .filter(|_| message.get("method").is_some())
+            {
+                // A plain JSON response cannot carry requests to the client, such as elicitation. Fail them
+                // rather than leave the service waiting for an answer that cannot come.
+                let _ = session.to_server.unbounded_send(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": -32601,
+                        "message": "Requests to the client need a text/event-stream response or an open GET stream",
+                    },
+                }));
+            }
+        }
+        let body = if batch || collected.len() != 1 {
+            serde_json::to_string(&collected)
+        } else {
+            serde_json::to_string(&collected[0])
+        };
+        HttpResponse::json(200, headers, body.unwrap_or_default())
+    }
+
+    /// Streamable HTTP: open a stream for messages that are not responses to a POST.
+    fn get_mcp(&self, request: HttpRequest, namespace: &str) -> HttpResponse {
+        if !request.accepts_event_stream() {
+            return HttpResponse::status(406, "Client must accept text/event-stream");
+        }
+        let session = match self.session_from_header(&request, namespace) {
+            Ok(session) => session,
+            Err(response) => return response,
+        };
+        let (tx, rx) = unbounded();
+        session.routes.lock().unwrap().stream = Some(tx);
+        HttpResponse::Stream {
+            headers: vec![("Mcp-Session-Id", session.id.clone())],
+            prelude: None,
+            messages: rx,
+            session: None,
+        }
+    }
+
+    /// Legacy SSE: open the event stream of a new session and announce where to post messages.
+    fn get_sse(&self, namespace: &str, peer: SocketAddr) -> HttpResponse {
+        let session = match self.create_session(namespace, peer) {
+            Ok(session) => session,
+            Err(response) => return response,
+        };
+        let (tx, rx) = unbounded();
+        session.routes.lock().unwrap().stream = Some(tx);
+        HttpResponse::Stream {
+            headers: Vec::new(),
+            prelude: Some(format!(
+                "event: endpoint\ndata: {}?sessionId={}\n\n",
+                MESSAGE_PATH, session.id
+            )),
+            messages: rx,
+            session: Some(session),
+        }
+    }
+
+    /// Legacy SSE: deliver client messages. Responses are sent on the event stream.
+    fn post_message(&self, request: HttpRequest, namespace: &str) -> HttpResponse {
+        let session = match request.query_param("sessionId") {
+            Some(id) => self.find_session(id, namespace),
+            None => None,
+        };
+        let Some(session) = session else {
+            return HttpResponse::status(404, "Session not found");
+        };
+        match parse_messages(&request.body) {
+            Ok((messages, _)) => {
+                for message in messages {
+                    let _ = session.to_server.unbounded_send(message);
+                }
+                HttpResponse::status(202, "Accepted")
+            }
+            Err(err) => HttpResponse::status(400, format!("Invalid JSON-RPC message: {}", err)),
+        }
+    }
+
+    fn session_from_header(
+        &self,
+        request: &HttpRequest,
+        namespace: &str,
+    ) -> Result<Arc<McpSession>, HttpResponse> {
+        let id = request
+            .header(SESSION_HEADER)
+            .ok_or(HttpResponse::status(400, "Missing Mcp-Session-Id header"))?;
+        self.find_session(id, namespace)
+            .ok_or(HttpResponse::status(404, "Session not found"))
+    }
+
+    /// Look up an MCP session, counting as activity in it. Sessions of other namespaces are reported as not found.
+    fn find_session(&self, id: &str, namespace: &str) -> Option<Arc<McpSession>> {
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|session| session.namespace == namespace)
+            .cloned()?;
+        session.touch();
+        Some(session)
+    }
+
+    /// Whether requests from a browser `Origin` are accepted: localhost or a configured origin.
+    fn origin_allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim().trim_end_matches('/');
+        is_local_origin(origin)
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    }
+
+    /// Start a new rmcp service bridged to a fresh [`McpSession`].
+    fn create_session(
+        &self,
+        namespace: &str,
+        peer: SocketAddr,
+    ) -> Result<Arc<McpSession>, HttpResponse> {
+        let id = random_id().map_err(|err| {
+            self.logger
+                .log(format!("Failed to create a session ID. [Error]: {}", err));
+            HttpResponse::status(500, "Failed to create a session")
+        })?;
+        let (to_server, from_client) = unbounded::<Value>();
+        let (client, mut from_server) = Client::new(format!("http {} session {}", peer, id));
+        let session = Arc::new(McpSession {
+            id,
+            namespace: namespace.to_owned(),
+            to_server,
+            routes: Mutex::new(Routes::default()),
+            last_active: Mutex::new(Instant::now()),
+        });
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.id.clone(), session.clone());
+        self.logger.log(format!(
+            "MCP session {} opened in namespace {}",
+            session.id, namespace
+        ));
+
+        let service = (self.make_service)(namespace, client.clone());
+        let logger = self.logger.clone();
+        let session_id = session.id.clone();
+        tokio::spawn(async move {
+            if let Err(err) = crate::client::serve(service, client, from_client).await {
+                logger.log(format!("MCP session {} failed: {}", session_id, err));
+            }
+        });
+        let router = session.clone();
+        tokio::spawn(async move {
+            while let Some(message) = from_server.next().await {
+                router.route(message);
+            }
+            // The service ended: nothing is coming for the requests still awaiting a response.
+            router.close();
+        });
+        Ok(session)
+    }
+
+    fn remove_session(&self, id: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().remove(id) {
+            session.close();
+            self.logger.log(format!("MCP session {} closed", id));
+        }
+    }
+
+    /// Close the sessions clients abandoned without a `DELETE`.
+    fn close_idle_sessions(&self, timeout: Duration) {
+        let idle: Vec<String> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| session.is_idle(timeout))
+            .map(|session| session.id.clone())
+            .collect();
+        for id in idle {
+            self.logger.log(format!(
+                "MCP session {} idle for {} seconds",
+                id,
+                timeout.as_secs()
+            ));
+            self.remove_session(&id);
+        }
+    }
+}
+
+/// Parse a POST body holding a single JSON-RPC message or a batch. Returns the messages and whether it was a batch.
+fn parse_messages(body: &[u8]) -> Result<(Vec<Value>, bool), serde_json::Error> {
+    let (messages, batch) = match serde_json::from_slice(body)? {
+        Value::Array(values) => (values, true),
+        value => (vec![value], false),
+    };
+    for message in &messages {
+        serde_json::from_value::<ClientJsonRpcMessage>(message.clone())?;
+    }
+    Ok((messages, batch))
+}
+
+/// ID of the request a response or error message answers.
+fn response_id(message: &Value) -> Option<RequestId> {
+    if message.get("result").is_none() && message.get("error").is_none() {
+        return None;
+    }
+    serde_json::from_value(message.get("id")?.clone()).ok()
+}
+
+/// Whether an `Origin` header value names a page served from the local host.
+fn is_local_origin(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map_or(rest, |(host, _)| host),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    matches!(scheme, "http" | "https")
+        && (host.eq_ignore_ascii_case("localhost")
+            || host
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback()))
+}
+
+/// Random identifier for MCP sessions. Session IDs must not be guessable by other clients.
+fn random_id() -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut urandom| std::io::Read::read_exact(&mut urandom, &mut bytes))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Read the next request on a connection. `None` means the client closed the connection, or left it idle for
+/// `timeout`. The headers and the body must each arrive within `timeout`, so slow clients cannot hold connections.
+async fn read_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    peer: SocketAddr,
+    timeout: Duration,
+) -> Result<Option<HttpRequest>, Error> {
+    let mut head = Vec::new();
+    match time::timeout(timeout, read_head(reader, &mut head)).await {
+        Ok(result) => result?,
+        Err(_) if head.is_empty() => return Ok(None),
+        Err(_) => {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "Timed out reading the request headers",
+            ));
+        }
+    }
+    if head.is_empty() {
+        return Ok(None);
+    }
+    let head = String::from_utf8(head)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Request headers are not UTF-8"))?;
+
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Error::new(ErrorKind::InvalidData, "Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect();
+
+    let mut request = HttpRequest {
+        peer,
+        method: method.to_owned(),
+        path: path.to_owned(),
+        query: query.to_owned(),
+        headers,
+        body: Vec::new(),
+    };
+    let length: usize = request
+        .header("content-length")
+        .map(|length| {
+            length
+                .parse()
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid Content-Length"))
+        })
+        .transpose()?
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(Error::new(ErrorKind::InvalidData, "Request body too large"));
+    }
+    request.body.resize(length, 0);
+    time::timeout(timeout, reader.read_exact(&mut request.body))
+        .await
+        .map_err(|_| Error::new(ErrorKind::TimedOut, "Timed out reading the request body"))??;
+    Ok(Some(request))
+}
+
+/// Read the request line and headers into `head`, without the empty line ending them. Empty lines before the
+/// request line are skipped. `head` stays empty when the connection is closed before a request starts. Bytes read
+/// are kept in `head` when the future is dropped, so a timeout can tell a partial request from an idle connection.
+async fn read_head<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    head: &mut Vec<u8>,
+) -> Result<(), Error> {
+    loop {
+        let start = head.len();
+        // Read through a limit, so a line that never ends cannot grow past it.
+        let limit = (MAX_HEADER_BYTES + 1 - start) as u64;
+        if (&mut *reader).take(limit).read_until(b'\n', head).await? == 0 {
+            return if head.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete request"))
+            };
+        }
+        if head.len() > MAX_HEADER_BYTES {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Request headers too large",
+            ));
+        }
+        if matches!(&head[start..], b"\r\n" | b"\n") {
+            head.truncate(start);
+            if !head.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        _ => "Internal Server Error",
+    }
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: u16,
+    headers: &[(&'static str, String)],
+    body: &str,
+) -> Result<(), Error> {
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason_phrase(status));
+    for (key, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    response.push_str(body);
+    writer.write_all(response.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Write an event stream response until the message channel closes or the client goes away.
+async fn write_stream<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    headers: &[(&'static str, String)],
+    prelude: Option<String>,
+    mut messages: UnboundedReceiver<Value>,
+) -> Result<(), Error> {
+    let mut head = String::from(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+    );
+    for (key, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    head.push_str("\r\n");
+    head.push_str(&prelude.unwrap_or_default());
+    writer.write_all(head.as_bytes()).await?;
+    writer.flush().await?;
+
+    let mut keep_alive = time::interval(KEEP_ALIVE_INTERVAL);
+    keep_alive.tick().await;
+    loop {
+        let event = tokio::select! {
+            message = messages.next() => match message {
+                Some(message) => format!("event: message\ndata: {}\n\n", message),
+                None => break,
+            },
+            _ = keep_alive.tick() => ": keep-alive\n\n".to_owned(),
+        };
+        writer.write_all(event.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:40000".parse().unwrap()
+    }
+
+    async fn read(bytes: &[u8]) -> Result<Option<HttpRequest>, Error> {
+        let mut reader = bytes;
+        read_request(&mut reader, peer(), REQUEST_READ_TIMEOUT).await
+    }
+
+    #[tokio::test]
+    async fn requests_are_parsed() {
+        let mut reader: &[u8] = b"\r\nPOST /message?sessionId=abc&x=1 HTTP/1.1\r\n\
+            Host: localhost\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\
+            Accept: application/json, text/event-stream\r\n\r\n{}\
+            GET /mcp HTTP/1.1\nmcp-session-id: abc\n\n";
+        let request = read_request(&mut reader, peer(), REQUEST_READ_TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/message");
+        assert_eq!(request.query_param("sessionId"), Some("abc"));
+        assert_eq!(request.query_param("x"), Some("1"));
+        assert_eq!(request.query_param("y"), None);
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert!(request.accepts_event_stream());
+        assert_eq!(request.body, b"{}");
+
+        // The next request on the same connection, with bare newlines.
+        let request = read_request(&mut reader, peer(), REQUEST_READ_TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.header(SESSION_HEADER), Some("abc"));
+        assert!(!request.accepts_event_stream());
+        assert!(request.body.is_empty());
+        assert!(
+            read_request(&mut reader, peer(), REQUEST_READ_TIMEOUT)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_are_errors() {
+        assert!(read(b"").await.unwrap().is_none());
+        assert!(read(b"GET /mcp HTTP/1.1\r\nHost: x\r\n").await.is_err());
+        assert!(read(b"GARBAGE\r\n\r\n").await.is_err());
+        assert!(
+            read(b"POST /mcp HTTP/1.1\r\nContent-Length: ten\r\n\r\n")
+                .await
+                .is_err()
+        );
+        assert!(
+            read(b"POST /mcp HTTP/1.1\r\nContent-Length: 5\r\n\r\n{}")
+                .await
+                .is_err()
+        );
+        let too_large = format!(
+            "POST /mcp HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert!(read(too_large.as_bytes()).await.is_err());
+        let long_headers = format!(
+            "GET /mcp HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_BYTES)
+        );
+        assert!(read(long_headers.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn endless_header_lines_are_errors() {
+        // A line without an end is rejected once it passes the limit, even though more of it keeps coming.
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let mut written = client.write_all(b"GET /mcp HTTP/1.1\r\nX-Padding: ").await;
+            while written.is_ok() {
+                written = client.write_all(&[b'a'; 1024]).await;
+            }
+        });
+        let mut reader = BufReader::new(server);
+        let result = read_request(&mut reader, peer(), REQUEST_READ_TIMEOUT).await;
+        assert!(matches!(result, Err(err) if err.kind() == ErrorKind::InvalidData));
+    }
+
+    #[tokio::test]
+    async fn slow_requests_time_out() {
+        let timeout = Duration::from_millis(50);
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut reader = BufReader::new(server);
+        // An idle connection is closed quietly.
+        assert!(
+            read_request(&mut reader, peer(), timeout)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        client
+            .write_all(b"GET /mcp HTTP/1.1\r\nHost: ")
+            .await
+            .unwrap();
+        let result = read_request(&mut reader, peer(), timeout).await;
+        assert!(matches!(result, Err(err) if err.kind() == ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn messages_and_responses() {
+        let (messages, batch) =
+            parse_messages(br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(!batch);
+        let (messages, batch) = parse_messages(
+            br#"[{"jsonrpc":"2.0","method":"notifications/initialized"},{"jsonrpc":"2.0","id":"a","result":{}}]"#,
+        )
+        .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(batch);
+        assert!(parse_messages(b"{").is_err());
+        assert!(parse_messages(br#"{"hello":"world"}"#).is_err());
+
+        assert_eq!(
+            response_id(&json!({"jsonrpc": "2.0", "id": 7, "result": {}})),
+            Some(RequestId::Number(7))
+        );
+        assert_eq!(
+            response_id(&json!({"jsonrpc": "2.0", "id": "x", "error": {"code": 1, "message": ""}})),
+            Some(RequestId::String("x".into()))
+        );
+        assert_eq!(
+            response_id(&json!({"jsonrpc": "2.0", "id": 7, "method": "roots/list"})),
+            None
+        );
+    }
+
+    #[test]
+    fn local_origins() {
+        for origin in [
+            "http://localhost",
+            "http://localhost:6274",
+            "https://LOCALHOST:8443",
+            "http://127.0.0.1:3000",
+            "http://127.1.2.3",
+            "http://[::1]:8080",
+        ] {
+            assert!(is_local_origin(origin), "{}", origin);
+        }
+        for origin in [
+            "null",
+            "http://evil.example",
+            "http://localhost.evil.example",
+            "http://127.0.0.1.nip.io",
+            "http://[::2]",
+            "file://localhost",
+            "localhost",
+        ] {
+            assert!(!is_local_origin(origin), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn session_ids_are_random() {
+        let first = random_id().unwrap();
+        assert_eq!(first.len(), 32);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first, random_id().unwrap());
+    }
+
+    #[test]
+    fn abandoned_requests_do_not_keep_sessions() {
+        let (to_server, _from_client) = unbounded();
+        let session = McpSession {
+            id: random_id().unwrap(),
+            namespace: "default".to_owned(),
+            to_server,
+            routes: Mutex::new(Routes::default()),
+            last_active: Mutex::new(Instant::now()),
+        };
+        let (responses_tx, mut responses) = unbounded();
+        session
+            .routes
+            .lock()
+            .unwrap()
+            .pending
+            .insert(RequestId::Number(1), responses_tx);
+        assert!(!session.is_idle(Duration::from_secs(60)));
+        assert!(session.is_idle(Duration::ZERO));
+
+        let (stream_tx, _stream) = unbounded();
+        session.routes.lock().unwrap().stream = Some(stream_tx);
+        assert!(!session.is_idle(Duration::ZERO));
+
+        // Once the service ends, the pending request gets its stream of responses closed.
+        session.close();
+        assert!(matches!(responses.try_next(), Ok(None)));
+        assert!(session.is_idle(Duration::ZERO));
+    }
+}
@@ -1,22 +1,63 @@
-//! Wrapper over tokio::process::Command to interact with a CLI debugger process.
+//! MCP server exposing GDB, LLDB and PDB debugging sessions to AI assistants.
+//!
+//! A single server ([`server::DbgServer`]) multiplexes the tools of any subset of debuggers ([`gdb`], [`lldb`],
+//! [`pdb`]) over stdio ([`stdio`]) or streamable HTTP ([`http`]), as configured by [`cli`]. Every session drives a CLI
+//! debugger process through [`CLIDebugger`] and [`CLIDebugSession`], with its commands checked against the
+//! [`policy`] and the process confined by the [`sandbox`].
+use std::collections::VecDeque;
 use std::ffi::OsStr;
 use std::process::Stdio;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt},
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt},
+    sync::Notify,
     time::{self, Duration},
 };
 
+pub mod audit;
+pub mod auth;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod coredump;
+pub mod disassembly;
+pub mod gdb;
+pub mod http;
+pub mod lldb;
+pub mod memory;
+pub mod mi;
+pub mod pdb;
+pub mod policy;
+pub mod process;
+pub mod progress;
+pub mod prompts;
+pub mod quota;
+pub mod readonly;
+pub mod registers;
+pub mod remote;
+pub mod resources;
+pub mod sandbox;
+pub mod scope;
+pub mod server;
+pub mod stdio;
+pub mod tls;
+pub mod watchpoints;
+
 /// A debugging session that wraps a running CLI debugger process. It abstracts interaction with the inner debugger process.
 /// Use [`CLIDebugger::spawn`] to create a new CLIDebugSession instance.
 pub struct CLIDebugSession {
     child: tokio::process::Child,
     stdin: tokio::process::ChildStdin,
-    stdout: tokio::io::BufReader<tokio::process::ChildStdout>,
-    stderr: tokio::io::BufReader<tokio::process::ChildStderr>,
+    /// Output of the inner debugger process as it is read, see [`read_stdout`] and [`read_stderr`].
+    output: OutputReceiver,
     prompt: String,
     quit_command: String,
+    sandbox: Option<String>,
+    output_bytes: u64,
 }
 
 /// A CLI debugger program that when spawned, creates a new [`CLIDebugSession`] instance.
@@ -24,8 +65,13 @@ pub struct CLIDebugger {
     command: tokio::process::Command,
     prompt: Option<String>,
     quit_command: Option<String>,
+    sandbox: Option<sandbox::Sandbox>,
+    observer: Option<LineObserver>,
 }
 
+/// Callback receiving every complete line the debugger writes, see [`CLIDebugger::observe_lines`].
+pub type LineObserver = Arc<dyn Fn(&str) + Send + Sync>;
+
 impl CLIDebugger {
     /// Creates a new [`CLIDebugger`] instance from the given program.
     pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
@@ -34,6 +80,8 @@ impl CLIDebugger {
             command,
             prompt: None,
             quit_command: None,
+            sandbox: None,
+            observer: None,
         }
     }
 
@@ -48,6 +96,12 @@ impl CLIDebugger {
         self
     }
 
+    /// Sets the working directory of the debugger program. Relative paths given to the debugger resolve against it.
+    pub fn current_dir<P: AsRef<std::path::Path>>(mut self, dir: P) -> Self {
+        self.command.current_dir(dir);
+        self
+    }
+
     /// Sets the prompt used by the debugger program.
     /// The prompt (example: "(gdb)") is used to keep the interaction in sync with the debugger.
     /// The default is ">".
@@ -64,27 +118,66 @@ impl CLIDebugger {
         self
     }
 
+    /// Runs the debugger program and everything it starts inside the given sandbox.
+    pub fn sandbox(mut self, sandbox: sandbox::Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Calls `observer` with every complete line the debugger writes to stdout or stderr as soon as it is written,
+    /// whether or not a response is being read. Used to forward asynchronous events.
+    pub fn observe_lines(mut self, observer: LineObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
     /// Start a new debugger session. The Ok value returned is a [`CLIDebugSession`] instance that corresponds to the spawned debugger process.
-    pub fn spawn(mut self) -> Result<CLIDebugSession, std::io::Error> {
-        let mut child = self
-            .command
+    pub fn spawn(self) -> Result<CLIDebugSession, std::io::Error> {
+        let (mut command, sandbox) = match &self.sandbox {
+            Some(sandbox) => {
+                let (command, description) = sandbox.apply(self.command)?;
+                (command, Some(description))
+            }
+            None => (self.command, None),
+        };
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .kill_on_drop(true)
+            .process_group(0)
             .spawn()?;
+        let (sender, output) = output_queue(MAX_QUEUED_OUTPUT);
+        tokio::spawn(read_stdout(
+            child.stdout.take().unwrap(),
+            sender.clone(),
+            self.observer.clone(),
+        ));
+        tokio::spawn(read_stderr(
+            child.stderr.take().unwrap(),
+            sender,
+            self.observer,
+        ));
         Ok(CLIDebugSession {
             stdin: child.stdin.take().unwrap(),
-            stdout: tokio::io::BufReader::new(child.stdout.take().unwrap()),
-            stderr: tokio::io::BufReader::new(child.stderr.take().unwrap()),
+            output,
             child,
             prompt: self.prompt.unwrap_or(String::from(">")),
             quit_command: self.quit_command.unwrap_or(String::from("quit")),
+            sandbox,
+            output_bytes: 0,
         })
     }
 }
 
 const CHILD_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often progress is reported while waiting for the debugger.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+/// How long to wait for the prompt after interrupting a cancelled command.
+const INTERRUPT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest program output line included in progress reports.
+const PROGRESS_LINE_CHARS: usize = 200;
+
 impl CLIDebugSession {
     /// Send a command to the inner debugger process followed by a newline.
     pub async fn send_command(&mut self, command: &str) -> Result<(), std::io::Error> {
@@ -95,26 +188,72 @@ impl CLIDebugSession {
     }
 
     /// Read the response from the inner debugger process until a pattern is matched or a timeout occurs.
+    /// Progress is reported to the client while waiting. If the client cancels the tool call, the read is abandoned
+    /// with an [`std::io::ErrorKind::Interrupted`] error; output that arrives later is returned by the next read.
     pub async fn read_response_until<S: AsRef<str>>(
         &mut self,
         pattern: Option<S>,
         timeout: Duration,
     ) -> Result<String, std::io::Error> {
-        let mut stdout_buffer = String::new();
-        let mut stderr_buffer = String::new();
+        self.read_until(pattern, timeout, true).await
+    }
+
+    /// Reads that are not `cancellable` only drain or resynchronize output, they report no progress either.
+    async fn read_until<S: AsRef<str>>(
+        &mut self,
+        pattern: Option<S>,
+        timeout: Duration,
+        cancellable: bool,
+    ) -> Result<String, std::io::Error> {
         let mut output = String::new();
 
         let sleep = time::sleep(timeout);
         tokio::pin!(sleep);
+        let started = time::Instant::now();
+        let mut ticks = time::interval_at(started + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+        let cancelled = progress::cancelled();
+        tokio::pin!(cancelled);
 
         loop {
             tokio::select! {
-                _ = self.stdout.read_line(&mut stdout_buffer) => {
-                    output.push_str(&stdout_buffer);
-                },
-                _ = self.stderr.read_line(&mut stderr_buffer) => {
-                    output.push_str("[stderr] ");
-                    output.push_str(&stderr_buffer);
+                received = self.output.recv() => {
+                    let Some(received) = received else {
+                        // The debugger closed its output, nothing more will arrive.
+                        if output.is_empty() {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::UnexpectedEof,
+                                "The debugger exited",
+                            ));
+                        }
+                        break Ok(output);
+                    };
+                    self.output_bytes += received.len() as u64;
+                    output.push_str(&received);
+                }
+                _ = ticks.tick(), if cancellable => {
+                    let last_line = output
+                        .lines()
+                        .rev()
+                        .map(str::trim)
+                        .find(|line| !line.is_empty())
+                        .map(|line| {
+                            let line: String = line.chars().take(PROGRESS_LINE_CHARS).collect();
+                            format!(", last line: {}", line)
+                        })
+                        .unwrap_or_default();
+                    progress::report(format!(
+                        "Waiting for the debugger: {} s elapsed, {} bytes of output received{}",
+                        started.elapsed().as_secs(),
+                        output.len(),
+                        last_line
+                    ));
+                    continue;
+                }
+                _ = &mut cancelled, if cancellable => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        "Cancelled by the client",
+                    ));
                 }
                 _ = &mut sleep => {
                     // Timeout occurred, stop reading
@@ -127,8 +266,6 @@ impl CLIDebugSession {
                     break Ok(output);
                 }
             }
-            stdout_buffer.clear();
-            stderr_buffer.clear();
 
             // Check if we got next input prompt and the expected pattern if any
             if let Some(pattern) = &pattern {
@@ -155,21 +292,68 @@ impl CLIDebugSession {
         command: S,
     ) -> Result<String, std::io::Error> {
         let one_msecond = Duration::from_millis(1);
+        if progress::is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "Cancelled by the client",
+            ));
+        }
         let mut response = self
-            .read_response_until::<&str>(None, one_msecond)
+            .read_until::<&str>(None, one_msecond, false)
             .await
             .unwrap_or_default();
         self.send_command(command.as_ref()).await?;
-        response.push_str(&self.read_response().await?);
+        match self.read_response().await {
+            Ok(output) => response.push_str(&output),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                // Stop whatever the command is doing, and wait for the prompt so the next command starts clean.
+                self.interrupt()?;
+                let _ = self
+                    .read_until::<&str>(None, INTERRUPT_TIMEOUT, false)
+                    .await;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "Cancelled by the client, the command was interrupted",
+                ));
+            }
+            Err(err) => return Err(err),
+        }
         response.push_str(
             &self
-                .read_response_until::<&str>(None, one_msecond)
+                .read_until::<&str>(None, one_msecond, false)
                 .await
                 .unwrap_or_default(),
         );
         Ok(response)
     }
 
+    /// Interrupt the inner debugger process and the program it runs, like Ctrl-C in a terminal.
+    pub fn interrupt(&self) -> Result<(), std::io::Error> {
+        let pid = self
+            .pid()
+            .ok_or(std::io::Error::other("The debugger has exited"))?;
+        // The debugger leads its own process group, see `spawn`.
+        if unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGINT) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Description of the sandbox the debugger runs in, if any.
+    pub fn sandbox(&self) -> Option<&str> {
+        self.sandbox.as_deref()
+    }
+
+    /// Process ID of the inner debugger process, `None` once it has exited.
+    pub fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Number of bytes the inner debugger process has written since it started.
+    pub fn output_bytes(&self) -> u64 {
+        self.output_bytes
+    }
+
     /// Gracefully terminate the inner debugger process.
     pub async fn terminate(&mut self) -> Result<(), std::io::Error> {
         self.send_command(self.quit_command.clone().as_str())
@@ -179,8 +363,208 @@ impl CLIDebugSession {
     }
 }
 
+/// Forward stdout of the debugger as it arrives, including partial lines such as prompts without a newline.
+async fn read_stdout<R: AsyncRead + Unpin>(
+    mut stdout: R,
+    output: OutputSender,
+    observer: Option<LineObserver>,
+) {
+    let mut buffer = [0u8; 8192];
+    // Bytes of a UTF-8 sequence split across reads.
+    let mut pending = Vec::new();
+    let mut line = String::new();
+    loop {
+        let read = match stdout.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        pending.extend_from_slice(&buffer[..read]);
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+        pending.drain(..valid);
+
+        if let Some(observer) = &observer {
+            for part in text.split_inclusive('\n') {
+                line.push_str(part);
+                if line.ends_with('\n') {
+                    observer(line.trim_end());
+                    line.clear();
+                }
+            }
+        }
+        if !output.send(text) {
+            break;
+        }
+    }
+}
+
+/// Forward stderr of the debugger line by line, marking each line as coming from stderr.
+async fn read_stderr<R: AsyncRead + Unpin>(
+    stderr: R,
+    output: OutputSender,
+    observer: Option<LineObserver>,
+) {
+    let mut stderr = tokio::io::BufReader::new(stderr);
+    let mut line = String::new();
+    while let Ok(read) = stderr.read_line(&mut line).await {
+        if read == 0 {
+            break;
+        }
+        if let Some(observer) = &observer {
+            observer(line.trim_end());
+        }
+        if !output.send(format!("[stderr] {}", line)) {
+            break;
+        }
+        line.clear();
+    }
+}
+
+/// Most debugger output kept until it is read. Programs writing faster than tools read, or while no tool reads, lose
+/// their oldest output beyond this, so the debugger never blocks on a full pipe and its events keep being observed.
+const MAX_QUEUED_OUTPUT: usize = 4 * 1024 * 1024;
+
+/// Output of the debugger waiting to be read, shared by the reading tasks and the session.
+struct OutputQueue {
+    state: Mutex<QueuedOutput>,
+    /// Signalled when output is queued or the last sender is dropped.
+    changed: Notify,
+    max_bytes: usize,
+}
+
+#[derive(Default)]
+struct QueuedOutput {
+    chunks: VecDeque<String>,
+    bytes: usize,
+    /// Bytes dropped since the receiver last read, reported by a marker before the next chunk.
+    dropped: usize,
+    senders: usize,
+    closed: bool,
+}
+
+/// A queue of debugger output holding at most `max_bytes`, dropping the oldest output when full.
+fn output_queue(max_bytes: usize) -> (OutputSender, OutputReceiver) {
+    let queue = Arc::new(OutputQueue {
+        state: Mutex::new(QueuedOutput {
+            senders: 1,
+            ..Default::default()
+        }),
+        changed: Notify::new(),
+        max_bytes,
+    });
+    (OutputSender(queue.clone()), OutputReceiver(queue))
+}
+
+struct OutputSender(Arc<OutputQueue>);
+
+impl OutputSender {
+    /// Queue output, dropping the oldest chunks beyond the limit. Returns false once the receiver is gone.
+    fn send(&self, text: String) -> bool {
+        let mut state = self.0.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        state.bytes += text.len();
+        state.chunks.push_back(text);
+        while state.bytes > self.0.max_bytes && state.chunks.len() > 1 {
+            let dropped = state.chunks.pop_front().unwrap_or_default();
+            state.bytes -= dropped.len();
+            state.dropped += dropped.len();
+        }
+        drop(state);
+        self.0.changed.notify_one();
+        true
+    }
+}
+
+impl Clone for OutputSender {
+    fn clone(&self) -> Self {
+        self.0.state.lock().unwrap().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for OutputSender {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().senders -= 1;
+        self.0.changed.notify_one();
+    }
+}
+
+struct OutputReceiver(Arc<OutputQueue>);
+
+impl OutputReceiver {
+    /// Next chunk of output, or `None` once every sender is gone and the queue is empty. Cancel safe.
+    async fn recv(&mut self) -> Option<String> {
+        loop {
+            {
+                let mut state = self.0.state.lock().unwrap();
+                if state.dropped > 0 {
+                    let marker = format!(
+                        "[{} bytes of debugger output dropped, it was not read in time]\n",
+                        state.dropped
+                    );
+                    state.dropped = 0;
+                    return Some(marker);
+                }
+                if let Some(chunk) = state.chunks.pop_front() {
+                    state.bytes -= chunk.len();
+                    return Some(chunk);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+            self.0.changed.notified().await;
+        }
+    }
+}
+
+impl Drop for OutputReceiver {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.closed = true;
+        state.chunks.clear();
+    }
+}
+
 /// Get a unique number for debugger session identifier.
 pub fn generate_session_id() -> u32 {
     static SESSION_ID: AtomicUsize = AtomicUsize::new(0);
     SESSION_ID.fetch_add(1, Ordering::Relaxed) as u32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn output_queue_drops_oldest_output() {
+        let (sender, mut receiver) = output_queue(8);
+        for chunk in ["abcd", "efgh", "ijkl"] {
+            assert!(sender.send(chunk.to_owned()));
+        }
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            "[4 bytes of debugger output dropped, it was not read in time]\n"
+        );
+        assert_eq!(receiver.recv().await.unwrap(), "efgh");
+        assert_eq!(receiver.recv().await.unwrap(), "ijkl");
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn output_queue_wakes_receiver() {
+        let (sender, mut receiver) = output_queue(8);
+        let reader = tokio::spawn(async move { receiver.recv().await });
+        tokio::task::yield_now().await;
+        assert!(sender.clone().send("prompt".to_owned()));
+        assert_eq!(reader.await.unwrap().as_deref(), Some("prompt"));
+        assert!(!sender.send("late".to_owned()));
+    }
+}
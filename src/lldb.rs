@@ -0,0 +1,1506 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    CLIDebugger,
+    client::Client,
+    coredump,
+    disassembly::{self, Instruction},
+    generate_session_id,
+    memory::{self, Decoding, Memory},
+    mi, process,
+    registers::{self, RegisterGroup},
+    remote::{self, Connection, Stub},
+    resources::Activity,
+    server::{
+        Backend, DEFAULT_NAMESPACE, ServerContext, Session, SessionMap, SessionNamespaces,
+        observe_session,
+    },
+    watchpoints::{self, Hit, Target, WatchKind},
+};
+
+use rmcp::{
+    ServerHandler,
+    model::{LoggingLevel, ServerCapabilities, ServerInfo},
+    tool,
+};
+use serde_json::{Value, json};
+
+/// Frames of the crashing thread listed when loading a core.
+const CORE_BACKTRACE_DEPTH: u64 = 64;
+
+/// MCP server exposing LLDB sessions as tools.
+#[derive(Clone)]
+pub struct LldbServer {
+    sessions: SessionMap,
+    namespaces: SessionNamespaces,
+    context: ServerContext,
+    namespace: String,
+    client: Client,
+}
+
+#[tool(tool_box)]
+impl ServerHandler for LldbServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some("LLVM Debugger".into()),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tool(tool_box)]
+impl LldbServer {
+    pub fn new(context: ServerContext) -> Self {
+        let namespaces = SessionNamespaces::default();
+        Self {
+            sessions: namespaces.get(DEFAULT_NAMESPACE),
+            namespaces,
+            context,
+            namespace: DEFAULT_NAMESPACE.to_owned(),
+            client: Client::default(),
+        }
+    }
+
+    /// Returns a handle to this server that only sees the sessions of the given client namespace.
+    pub fn in_namespace(&self, namespace: &str) -> Self {
+        Self {
+            sessions: self.namespaces.get(namespace),
+            namespace: namespace.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Sessions of the client namespace of this handle.
+    pub fn sessions(&self) -> &SessionMap {
+        &self.sessions
+    }
+
+    /// Returns a handle to this server that talks to the given client.
+    pub fn with_client(&self, client: Client) -> Self {
+        Self {
+            client,
+            ..self.clone()
+        }
+    }
+
+    #[tool(
+        description = "Start a new LLDB debugging session. When done using it, terminate the session"
+    )]
+    async fn lldb_start(
+        &self,
+        #[tool(param)]
+        #[schemars(
+            description = "Only allow inspection commands (backtraces, printing, memory reads, disassembly, source listing) in this session"
+        )]
+        read_only: Option<bool>,
+    ) -> Result<String, String> {
+        let session_id = format!("lldb-{}", generate_session_id());
+        let slot = self.context.limits.acquire(&self.namespace)?;
+
+        let activity = Activity::shared(&session_id);
+        let mut debugger = CLIDebugger::new("lldb")
+            .args(["--no-use-colors", "--source-quietly"])
+            .prompt("(lldb)")
+            .observe_lines(observe_session(
+                self.client.clone(),
+                Backend::Lldb,
+                &session_id,
+                activity.clone(),
+                lldb_event,
+            ));
+        if let Some(workspace) = self.context.workspace(&self.client).await {
+            debugger = debugger.current_dir(workspace);
+        }
+        if self.context.sandbox.enabled {
+            debugger = debugger.sandbox(self.context.sandbox.as_ref().clone());
+        }
+        let session = debugger
+            .spawn()
+            .map_err(|err| format!("Failed to start LLDB session. [Error]: {}", err))?;
+
+        let sandbox = session
+            .sandbox()
+            .map(|sandbox| format!(" [Sandbox]: {}.", sandbox))
+            .unwrap_or_default();
+        self.sessions.lock().await.insert(
+            session_id.clone(),
+            Session::new(session, read_only.unwrap_or(false), activity),
+        );
+        slot.commit();
+        self.context
+            .logger
+            .log(format!("LLDB session {} started", session_id));
+        Ok(format!(
+            "LLDB session started with ID {}.{}",
+            session_id, sandbox
+        ))
+    }
+
+    #[tool(description = "Load a program into existing LLDB session")]
+    async fn lldb_load(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the program to debug")]
+        program: String,
+    ) -> Result<String, String> {
+        let program = self.context.resolve_path(&self.client, &program).await?;
+        let file_command = format!("file {}", quote(&program.display().to_string()));
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Lldb,
+                &self.sessions,
+                &session_id,
+                &file_command,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        let response = session
+            .execute_command(&file_command)
+            .await
+            .map_err(|err| format!("Failed to execute LLDB command. [Error]: {}", err))?;
+
+        Ok(format!(
+            "Program loaded into LLDB.\n [LLDB output]: {}",
+            response
+        ))
+    }
+
+    #[tool(description = "Execute a LLDB command")]
+    async fn lldb_command(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "LLDB command to execute")]
+        command: String,
+    ) -> Result<String, String> {
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Lldb,
+                &self.sessions,
+                &session_id,
+                &command,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        let response = session
+            .execute_command(&command)
+            .await
+            .map_err(|err| format!("Failed to execute LLDB command. [Error]: {}", err))?;
+
+        Ok(format!("Command executed.\n[LLDB output]: {}", response))
+    }
+
+    #[tool(description = "Wait for LLDB debugee to hit a breakpoint or stop running")]
+    async fn lldb_wait(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Timeout in seconds")]
+        timeout: Option<u64>,
+    ) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let timeout = Duration::from_secs(timeout.unwrap_or(10));
+
+        let response = session
+            .read_response_until(Some("stop reason"), timeout)
+            .await
+            .map_err(|err| format!("Failed to read from GDB session. [Error]: {}", err))?;
+
+        Ok(format!(
+            "LLDB debugee stopped.\n[LLDB output]: {}",
+            response
+        ))
+    }
+
+    #[tool(
+        description = "Set a hardware watchpoint stopping when a variable or address range is written, read or accessed, with an optional condition. With collect, the program is continued on every trigger and the old value, new value, thread and backtrace of each are recorded. Returns the watchpoint and the collected hits as JSON"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn lldb_watch(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Variable to watch, such as `counter` or `node->next`")]
+        expression: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Start address of a range to watch, as an expression, instead of a variable"
+        )]
+        address: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Length of the range to watch in bytes (default 1)")]
+        length: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Accesses to stop on: write (default), read or access")]
+        kind: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Only stop when this expression is true")]
+        condition: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Continue the program on every trigger and record the hits, until another stop, max_hits or the timeout"
+        )]
+        collect: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Hits to collect at most (default 100)")]
+        max_hits: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = "Time to collect for at most, in seconds (default 30)")]
+        timeout: Option<u64>,
+    ) -> Result<String, String> {
+        let kind: WatchKind = kind.as_deref().unwrap_or("write").parse()?;
+        let target = Target::new(expression, address, length)?;
+        let access = match kind {
+            WatchKind::Write => "write",
+            WatchKind::Read => "read",
+            WatchKind::Access => "read_write",
+        };
+        let command = match &target {
+            Target::Expression(expression) => format!(
+                "watchpoint set variable -w {} -- {}",
+                access,
+                quote(expression)
+            ),
+            // Everything after `--` is evaluated as is, quotes would make the address a string literal. The
+            // expression only has to stay on the command line.
+            Target::Range(address, _) if address.contains(['\n', '\r']) => {
+                return Err("The address must be a single line".to_owned());
+            }
+            Target::Range(address, length) => format!(
+                "watchpoint set expression -w {} -s {} -- {}",
+                access, length, address
+            ),
+        };
+        let output = self.run_command(&session_id, &command).await?;
+        let Some((number, start, length)) = output.lines().find_map(watchpoint_line) else {
+            return Err(format!(
+                "Failed to set the watchpoint. [LLDB output]: {}",
+                output.trim()
+            ));
+        };
+
+        // LLDB only reports the address of a variable once the watchpoint is set, so check the debug registers now.
+        let needed = watchpoints::slots(start, length);
+        let existing: Vec<u64> = self
+            .run_command(&session_id, "watchpoint list --brief")
+            .await?
+            .lines()
+            .filter_map(watchpoint_line)
+            .map(|(number, _, _)| number)
+            .collect();
+        let checked = {
+            let mut sessions = self.sessions.lock().await;
+            let session = sessions.get_mut(&session_id).ok_or(format!(
+                "Session with ID {} not found. Start a new session",
+                session_id
+            ))?;
+            let checked = session.watchpoints.check_slots(&existing, needed);
+            if checked.is_ok() {
+                session.watchpoints.add(number, needed);
+            }
+            checked
+        };
+        if let Err(err) = checked {
+            self.run_command(&session_id, &format!("watchpoint delete {}", number))
+                .await?;
+            return Err(err);
+        }
+        let condition = condition.filter(|condition| !condition.trim().is_empty());
+        if let Some(condition) = &condition {
+            let command = format!(
+                "watchpoint modify -c {} {}",
+                quote(condition.trim()),
+                number
+            );
+            let output = self.run_command(&session_id, &command).await?;
+            if output.contains("error:") {
+                return Err(format!(
+                    "Failed to set the condition of watchpoint {}. [LLDB output]: {}",
+                    number,
+                    output.trim()
+                ));
+            }
+        }
+
+        let watchpoint = mi::compact(json!({
+            "number": number,
+            "kind": kind.name(),
+            "expression": match &target {
+                Target::Expression(expression) => Some(expression),
+                Target::Range(..) => None,
+            },
+            "address": format!("{:#x}", start),
+            "length": length,
+            "condition": condition,
+            "hardware_slots": needed,
+        }));
+        if !collect.unwrap_or(false) {
+            return Ok(watchpoint.to_string());
+        }
+        let collected = self
+            .collect_hits(
+                &session_id,
+                number,
+                max_hits.unwrap_or(watchpoints::DEFAULT_COLLECT_HITS),
+                Duration::from_secs(timeout.unwrap_or(30)),
+            )
+            .await?;
+        Ok(json!({ "watchpoint": watchpoint, "collected": collected }).to_string())
+    }
+
+    #[tool(
+        description = "Get the watchpoint hits collected in a session as JSON: old value, new value, thread and backtrace of each, oldest first"
+    )]
+    async fn lldb_watch_history(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Only return the hits of this watchpoint")]
+        watchpoint: Option<u64>,
+    ) -> Result<String, String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        Ok(session.watchpoints.history_json(watchpoint, 0).to_string())
+    }
+
+    #[tool(
+        description = "Read memory as a hexdump plus optional decodings, as JSON. Unreadable parts of the range are reported instead of failing the read"
+    )]
+    async fn lldb_read_memory(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Expression giving the start address, such as &buf, ptr + 16 or 0x601040"
+        )]
+        address: String,
+        #[tool(param)]
+        #[schemars(description = "Number of bytes to read, at most 65536")]
+        length: u64,
+        #[tool(param)]
+        #[schemars(description = "Decodings to add: u8, u16, u32, u64, ascii, utf16")]
+        decode: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(
+            description = "Decode integers and UTF-16 as big endian instead of little endian"
+        )]
+        big_endian: Option<bool>,
+    ) -> Result<String, String> {
+        memory::check_length(length)?;
+        let decodings = decode
+            .unwrap_or_default()
+            .iter()
+            .map(|decoding| decoding.parse())
+            .collect::<Result<Vec<Decoding>, String>>()?;
+
+        let command = format!(
+            "memory read --force --format x --size 1 --count {} -- {}",
+            length,
+            quote(&address)
+        );
+        let output = self.run_command(&session_id, &command).await?;
+        let mut memory: Option<Memory> = None;
+        let mut failed_at = None;
+        for line in output.lines() {
+            let line = line.trim_start_matches("(lldb) ").trim();
+            // Nothing could be read: `error: memory read failed for 0x0`.
+            if let Some(address) = line.strip_prefix("error: memory read failed for ") {
+                failed_at = memory::parse_address(address);
+                continue;
+            }
+            // A line of bytes: `0x100003f8c: 0x48 0x65 0x6c 0x6c 0x6f 0x2c 0x20 0x77`.
+            let Some((address, bytes)) = line.split_once(": ") else {
+                continue;
+            };
+            let Some(address) = memory::parse_address(address) else {
+                continue;
+            };
+            let bytes: Vec<u8> = bytes
+                .split_whitespace()
+                .map_while(|byte| u8::from_str_radix(byte.strip_prefix("0x")?, 16).ok())
+                .collect();
+            memory
+                .get_or_insert_with(|| Memory::new(address, length))
+                .add(address, bytes);
+        }
+        let memory = match (memory, failed_at) {
+            (Some(memory), _) => memory,
+            (None, Some(address)) => Memory::new(address, length),
+            (None, None) => {
+                return Err(format!(
+                    "Failed to read memory. [LLDB output]: {}",
+                    output.trim()
+                ));
+            }
+        };
+        Ok(memory
+            .to_json(&decodings, big_endian.unwrap_or(false))
+            .to_string())
+    }
+
+    #[tool(
+        description = "Read registers of the selected frame as JSON, filtered by group and optionally only those changed since an earlier stop"
+    )]
+    async fn lldb_registers(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Register group: general (default), float, vector or all")]
+        group: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Only return registers that changed since the last earlier stop at which this tool read them"
+        )]
+        changed_since_last_stop: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = "Value format: hex (default) or natural")]
+        format: Option<String>,
+    ) -> Result<String, String> {
+        let group: RegisterGroup = group.as_deref().unwrap_or("general").parse()?;
+        let changed_only = changed_since_last_stop.unwrap_or(false);
+        let command = match format.as_deref().map(str::trim).unwrap_or("hex") {
+            "hex" | "hexadecimal" => "register read --all --format x",
+            "natural" => "register read --all",
+            format => {
+                return Err(format!(
+                    "Unknown register format '{}'. Expected hex or natural",
+                    format
+                ));
+            }
+        };
+
+        // All registers are read, so that the next call can tell which ones changed.
+        let output = self.run_command(&session_id, command).await?;
+        let all: Vec<(String, String)> = output.lines().filter_map(register_value).collect();
+        if all.is_empty() {
+            return Err(format!(
+                "Failed to read registers. [LLDB output]: {}",
+                output.trim()
+            ));
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let stop = session.activity().lock().unwrap().stops();
+        session
+            .registers
+            .record(stop, all.iter().cloned().collect());
+
+        let names: Vec<&str> = all.iter().map(|(name, _)| name.as_str()).collect();
+        let selected = registers::select(&names, group);
+        let mut values = Vec::new();
+        for (name, value) in &all {
+            if !selected.contains(&name.as_str()) {
+                continue;
+            }
+            if changed_only {
+                match session.registers.changed(name) {
+                    Some(true) => {}
+                    Some(false) => continue,
+                    None => {
+                        return Err(
+                            "Registers were not read at an earlier stop of this session, so changes are unknown. \
+                            The values read now are compared at the next stop"
+                                .to_owned(),
+                        );
+                    }
+                }
+            }
+            values.push((name.clone(), value.clone()));
+        }
+        Ok(registers::to_json(group, changed_only, &values).to_string())
+    }
+
+    #[tool(
+        description = "Disassemble a function, an address range, or the function around the current instruction, as JSON with the source line of every instruction and the current instruction marked"
+    )]
+    async fn lldb_disassemble(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Function to disassemble, by name or by an address inside it")]
+        function: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Start address expression of the range to disassemble")]
+        start: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "End address expression of the range to disassemble, exclusive")]
+        end: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Without function or range, number of instructions to show before and after the current one (default 10)"
+        )]
+        window: Option<usize>,
+        #[tool(param)]
+        #[schemars(description = "Add the source file and line of instructions (default true)")]
+        source: Option<bool>,
+    ) -> Result<String, String> {
+        let range = disassembly::Range::new(function, start, end, window)?;
+        let mut command = "disassemble --bytes".to_owned();
+        if source.unwrap_or(true) {
+            command.push_str(" --mixed");
+        }
+        match &range {
+            disassembly::Range::Function(function) => {
+                command.push_str(&format!(" --address {}", quote(function)));
+            }
+            disassembly::Range::Addresses(start, end) => command.push_str(&format!(
+                " --start-address {} --end-address {}",
+                quote(start),
+                quote(end)
+            )),
+            disassembly::Range::AroundPc(_) => command.push_str(" --frame"),
+        }
+        let output = self.run_command(&session_id, &command).await?;
+        let (instructions, pc) = parse_disassembly(&output);
+        if instructions.is_empty() {
+            return Err(format!(
+                "Failed to disassemble. [LLDB output]: {}",
+                output.trim()
+            ));
+        }
+        Ok(disassembly::to_json(&instructions, pc, &range).to_string())
+    }
+
+    #[tool(
+        description = "List the running processes of the user running the server with PID, name, user and command line, as JSON, to find a process to attach to"
+    )]
+    async fn lldb_list_processes(
+        &self,
+        #[tool(param)]
+        #[schemars(
+            description = "Only list processes whose name or command line contains this text"
+        )]
+        filter: Option<String>,
+    ) -> Result<String, String> {
+        Ok(process::list_json(filter.as_deref()).to_string())
+    }
+
+    #[tool(
+        description = "Attach a LLDB session to a running process, by PID or by name. The process stops until continued"
+    )]
+    async fn lldb_attach(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "PID of the process")]
+        pid: Option<u32>,
+        #[tool(param)]
+        #[schemars(
+            description = "Name of the process, if not given by PID. It must match exactly one process"
+        )]
+        name: Option<String>,
+    ) -> Result<String, String> {
+        let target = process::find(pid, name.as_deref())?;
+        {
+            let sessions = self.sessions.lock().await;
+            let session = sessions.get(&session_id).ok_or(format!(
+                "Session with ID {} not found. Start a new session",
+                session_id
+            ))?;
+            if session.sandbox().is_some() {
+                return Err(
+                    "Sandboxed sessions cannot attach to processes outside of the sandbox"
+                        .to_owned(),
+                );
+            }
+        }
+
+        let command = format!("process attach --pid {}", target.pid);
+        let output = self.run_command(&session_id, &command).await?;
+        if output.contains("error:") {
+            let explanation = process::explain_attach_failure("lldb", &target, &output)
+                .map(|explanation| format!(" {}", explanation))
+                .unwrap_or_default();
+            return Err(format!(
+                "Failed to attach to process {}.{} [LLDB output]: {}",
+                target.pid,
+                explanation,
+                output.trim()
+            ));
+        }
+        Ok(format!(
+            "Attached to process {} ({}). It is stopped until continued. Detach with lldb_detach to leave it running.\n[LLDB output]: {}",
+            target.pid, target.command, output
+        ))
+    }
+
+    #[tool(
+        description = "Detach a LLDB session from the process it is attached to, leaving the process running"
+    )]
+    async fn lldb_detach(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let output = self.run_command(&session_id, "process detach").await?;
+        if output.contains("error:") {
+            return Err(format!(
+                "Failed to detach. [LLDB output]: {}",
+                output.trim()
+            ));
+        }
+        Ok(format!(
+            "Detached from the process, which keeps running.\n[LLDB output]: {}",
+            output
+        ))
+    }
+
+    #[tool(
+        description = "Connect a LLDB session to a remote target with gdb-remote, such as lldb-server or gdbserver in another container or QEMU user mode started with -g"
+    )]
+    async fn lldb_connect(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Address of the remote stub: host:port, or a port on localhost")]
+        address: String,
+    ) -> Result<String, String> {
+        remote::check_address(&address)?;
+        self.check_connectable(&session_id).await?;
+        let output = self
+            .run_command(&session_id, &format!("gdb-remote {}", address))
+            .await?;
+        if output.contains("error:") {
+            return Err(format!(
+                "Failed to connect to {}. [LLDB output]: {}",
+                address,
+                output.trim()
+            ));
+        }
+        if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+            session.remote = Some(Connection::new(&address, "gdb-remote"));
+        }
+        Ok(format!(
+            "Connected to the remote target at {}. Disconnect with lldb_disconnect.\n[LLDB output]: {}",
+            address, output
+        ))
+    }
+
+    #[tool(
+        description = "Disconnect a LLDB session from its remote target, leaving the program stopped. A lldb-server started with lldb_spawn_server is stopped"
+    )]
+    async fn lldb_disconnect(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let output = self
+            .run_command(&session_id, "process detach --keep-stopped true")
+            .await?;
+        if output.contains("error:") {
+            return Err(format!(
+                "Failed to disconnect. [LLDB output]: {}",
+                output.trim()
+            ));
+        }
+        let remote = match self.sessions.lock().await.get_mut(&session_id) {
+            Some(session) => session.remote.take(),
+            None => None,
+        };
+        Ok(match remote {
+            Some(remote) => format!(
+                "Disconnected from the remote target at {}.\n[LLDB output]: {}",
+                remote.address, output
+            ),
+            None => format!(
+                "Disconnected from the remote target.\n[LLDB output]: {}",
+                output
+            ),
+        })
+    }
+
+    #[tool(
+        description = "Start a program under a local lldb-server on a free port and connect a LLDB session to it. The program is stopped at its first instruction until continued, and lldb-server is stopped with the session"
+    )]
+    async fn lldb_spawn_server(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the program to debug")]
+        program: String,
+        #[tool(param)]
+        #[schemars(description = "Arguments to pass to the program")]
+        arguments: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        let activity = self.check_connectable(&session_id).await?;
+        let program = self.context.resolve_path(&self.client, &program).await?;
+        let sandbox = self
+            .context
+            .sandbox
+            .enabled
+            .then_some(self.context.sandbox.as_ref());
+        let (stub, address) = remote::spawn(
+            Stub::LldbServer,
+            &program,
+            &arguments.unwrap_or_default(),
+            sandbox,
+            activity,
+        )
+        .await?;
+        let pid = stub.id();
+
+        let mut output = String::new();
+        for command in [
+            format!("target create {}", quote(&program.display().to_string())),
+            format!("gdb-remote {}", address),
+        ] {
+            output.push_str(&self.run_command(&session_id, &command).await?);
+            if output.contains("error:") {
+                return Err(format!(
+                    "Failed to connect to lldb-server. [LLDB output]: {}",
+                    output.trim()
+                ));
+            }
+        }
+        if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+            session.remote = Some(Connection::spawned(
+                &address,
+                "gdb-remote",
+                Stub::LldbServer,
+                stub,
+            ));
+        }
+        Ok(format!(
+            "Started {} under lldb-server (PID {}) on {} and connected to it. The program is stopped at its first instruction until continued.\n[LLDB output]: {}",
+            program.display(),
+            pid.map(|pid| pid.to_string())
+                .unwrap_or("unknown".to_owned()),
+            address,
+            output
+        ))
+    }
+
+    #[tool(
+        description = "Load a core dump for post-mortem analysis and summarize the crash as JSON: signal, faulting address, backtrace of the crashing thread and libraries that could not be found"
+    )]
+    async fn lldb_load_core(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "Path to the core file")]
+        core: String,
+        #[tool(param)]
+        #[schemars(
+            description = "Path to the executable that dumped the core. Found from the core if not given"
+        )]
+        executable: Option<String>,
+        #[tool(param)]
+        #[schemars(
+            description = "Directory holding a copy of the root filesystem of the machine that dumped the core, to find its libraries"
+        )]
+        sysroot: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "Directories to search for shared libraries")]
+        solib_search_paths: Option<Vec<String>>,
+    ) -> Result<String, String> {
+        let core = self.context.resolve_path(&self.client, &core).await?;
+        let executable =
+            coredump::executable(&self.context, &self.client, &core, executable.as_deref()).await?;
+        let sysroot = match sysroot {
+            Some(sysroot) => Some(self.context.resolve_path(&self.client, &sysroot).await?),
+            None => None,
+        };
+        let mut search_paths = Vec::new();
+        for path in solib_search_paths.unwrap_or_default() {
+            search_paths.push(self.context.resolve_path(&self.client, &path).await?);
+        }
+
+        let mut commands = Vec::new();
+        if let Some(sysroot) = &sysroot {
+            commands.push(format!(
+                "platform select --sysroot {} host",
+                quote(&sysroot.display().to_string())
+            ));
+        }
+        if !search_paths.is_empty() {
+            let paths: Vec<String> = search_paths
+                .iter()
+                .map(|path| quote(&path.display().to_string()))
+                .collect();
+            commands.push(format!(
+                "settings append target.exec-search-paths {}",
+                paths.join(" ")
+            ));
+        }
+        commands.push(format!(
+            "target create --core {} {}",
+            quote(&core.display().to_string()),
+            quote(&executable.path.display().to_string())
+        ));
+        commands.push(format!(
+            "thread backtrace --count {}",
+            CORE_BACKTRACE_DEPTH + 1
+        ));
+        let mut output = String::new();
+        for command in &commands {
+            let response = self.run_command(&session_id, command).await?;
+            if let Some(error) = response.lines().find(|line| line.contains("error:")) {
+                return Err(format!(
+                    "Failed to load core. [Command]: {} [LLDB output]: {}",
+                    command,
+                    error.trim_start_matches("(lldb) ")
+                ));
+            }
+            output.push_str(&response);
+        }
+
+        // `* thread #1, name = 'crash', stop reason = signal SIGSEGV: invalid address (fault address: 0x0)`
+        let stop = output
+            .lines()
+            .map(|line| line.trim_start_matches("(lldb) ").trim_start())
+            .find(|line| line.starts_with("* thread #"));
+        let thread = stop
+            .and_then(|stop| stop.strip_prefix("* thread #")?.split([',', ' ']).next())
+            .and_then(|thread| thread.parse::<u64>().ok());
+        let reason = stop
+            .and_then(|stop| stop.split_once("stop reason = signal "))
+            .map(|(_, reason)| {
+                reason
+                    .split_once(" (fault")
+                    .map_or(reason, |(reason, _)| reason)
+            });
+        let (signal, description) = match reason.map(|reason| reason.split_once(": ")) {
+            Some(Some((signal, description))) => (Some(signal), Some(description)),
+            Some(None) => (reason, None),
+            None => (None, None),
+        };
+        let fault_address = stop
+            .and_then(|stop| stop.split_once("(fault address: ")?.1.split_once(')'))
+            .map(|(address, _)| address);
+        let mut frames: Vec<Value> = output.lines().filter_map(backtrace_frame).collect();
+        let truncated = frames.len() as u64 > CORE_BACKTRACE_DEPTH;
+        frames.truncate(CORE_BACKTRACE_DEPTH as usize);
+
+        // LLDB does not report libraries it could not find, so look for those the process had mapped.
+        let missing_libraries: Vec<String> = coredump::mapped_libraries(&core)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|library| {
+                let file_name = library.rsplit('/').next().unwrap_or_default();
+                let found = match &sysroot {
+                    Some(sysroot) => sysroot.join(library.trim_start_matches('/')).is_file(),
+                    None => Path::new(library).is_file(),
+                };
+                !found
+                    && !search_paths
+                        .iter()
+                        .any(|path| path.join(file_name).is_file())
+            })
+            .collect();
+        let mut warnings: Vec<String> = executable.mismatch.into_iter().collect();
+        for line in output.lines() {
+            let line = line.trim_start_matches("(lldb) ").trim();
+            if line.starts_with("warning:") && !warnings.iter().any(|warning| warning == line) {
+                warnings.push(line.to_owned());
+            }
+        }
+
+        Ok(json!({
+            "executable": executable.path.display().to_string(),
+            "executable_inferred": executable.inferred,
+            "core": core.display().to_string(),
+            "signal": signal,
+            "signal_description": description,
+            "fault_address": fault_address,
+            "thread": thread,
+            "frames": frames,
+            "frames_truncated": truncated,
+            "missing_libraries": missing_libraries,
+            "warnings": warnings,
+        })
+        .to_string())
+    }
+
+    #[tool(description = "Terminate a LLDB session")]
+    async fn lldb_terminate(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "LLDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        session
+            .terminate()
+            .await
+            .map_err(|err| format!("Failed to terminate LLDB session, [Error]: {}", err))?;
+        sessions.remove(&session_id);
+        self.context.limits.release(&self.namespace);
+        self.context
+            .logger
+            .log(format!("LLDB session {} terminated", session_id));
+        Ok("LLDB session terminated".to_string())
+    }
+}
+
+impl LldbServer {
+    /// Run a LLDB command in a session, checked like any other command, and return its output.
+    async fn run_command(&self, session_id: &str, command: &str) -> Result<String, String> {
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Lldb,
+                &self.sessions,
+                session_id,
+                command,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        session
+            .execute_command(command)
+            .await
+            .map_err(|err| format!("Failed to execute LLDB command. [Error]: {}", err))
+    }
+
+    /// Continue the program on every trigger of a watchpoint and record the hits, until it stops for another reason,
+    /// `max_hits` were collected or `timeout` elapsed. Returns why collecting ended and the hits as JSON.
+    async fn collect_hits(
+        &self,
+        session_id: &str,
+        watchpoint: u64,
+        max_hits: u64,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        // The commands are checked once, not on every trigger.
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Lldb,
+                &self.sessions,
+                session_id,
+                "process continue\nthread backtrace",
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        let since = session.watchpoints.recorded();
+        let deadline = Instant::now() + timeout;
+        let mut collected = 0;
+        let (ended_by, last_stop) = loop {
+            if collected >= max_hits {
+                break ("max_hits".to_owned(), None);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break ("timeout".to_owned(), None);
+            }
+            let mut output = session
+                .execute_command("process continue")
+                .await
+                .map_err(|err| format!("Failed to execute LLDB command. [Error]: {}", err))?;
+            if output.contains("error:") {
+                return Err(format!(
+                    "Failed to continue the program. [LLDB output]: {}",
+                    output.trim()
+                ));
+            }
+            // Without a synchronous debugger, the stop is printed after the prompt.
+            let stopped = |output: &str| {
+                output.contains("stop reason = ") || output.contains(" exited with status = ")
+            };
+            while !stopped(&output) {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match session.read_response_until::<&str>(None, remaining).await {
+                    Ok(more) => output.push_str(&more),
+                    Err(err) if err.kind() == std::io::ErrorKind::TimedOut => break,
+                    Err(err) => {
+                        return Err(format!(
+                            "Failed to read from LLDB session. [Error]: {}",
+                            err
+                        ));
+                    }
+                }
+                if Instant::now() >= deadline {
+                    break;
+                }
+            }
+            if let Some(exit) = output
+                .lines()
+                .find(|line| line.contains(" exited with status = "))
+            {
+                break (exit.trim_start_matches("(lldb) ").to_owned(), None);
+            }
+            let Some((thread, reason)) = output.lines().find_map(|line| {
+                let line = line.trim_start_matches("(lldb) ").trim_start();
+                let thread = line.strip_prefix("* thread #")?;
+                let thread = thread.split([',', ' ']).next()?.to_owned();
+                Some((thread, line.split_once("stop reason = ")?.1.to_owned()))
+            }) else {
+                break ("timeout".to_owned(), None);
+            };
+            if reason.split_whitespace().collect::<Vec<_>>()
+                != ["watchpoint", &watchpoint.to_string()]
+            {
+                let frame = output.lines().find_map(backtrace_frame);
+                break (reason, frame);
+            }
+
+            let value = |prefix: &str| {
+                output.lines().find_map(|line| {
+                    line.trim_start()
+                        .strip_prefix(prefix)
+                        .map(|value| value.trim().to_owned())
+                })
+            };
+            let command = format!(
+                "thread backtrace --count {}",
+                watchpoints::HIT_BACKTRACE_DEPTH
+            );
+            let frames = session
+                .execute_command(&command)
+                .await
+                .unwrap_or_default()
+                .lines()
+                .filter_map(backtrace_frame)
+                .collect();
+            session.watchpoints.record(Hit {
+                watchpoint,
+                thread: Some(thread),
+                old_value: value("old value:"),
+                new_value: value("new value:").or(value("value:")),
+                frames,
+            });
+            collected += 1;
+        };
+
+        let mut result = session.watchpoints.history_json(Some(watchpoint), since);
+        result["ended_by"] = ended_by.into();
+        if let Some(frame) = last_stop {
+            result["stopped_at"] = frame;
+        }
+        Ok(result)
+    }
+
+    /// Check that a session can connect to remote targets, see [`remote::check_network`], and return its activity.
+    async fn check_connectable(
+        &self,
+        session_id: &str,
+    ) -> Result<Arc<std::sync::Mutex<Activity>>, String> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+        remote::check_network(session, &self.context.sandbox)?;
+        Ok(session.activity().clone())
+    }
+}
+
+/// Quote an argument of a LLDB command, so that expressions with spaces stay one argument.
+fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Instructions printed by `disassemble --bytes`, and the address of the current one, marked with `->`.
+///
+/// Instructions follow a `module`function:` header and look like `-> 0x100003f8c <+28>: c7 00 2a 00  movl $0x2a,
+/// (%rax)`. With `--mixed`, source lines are printed before their instructions, a `file:line` header followed by
+/// numbered lines, the line of the instructions marked with `**` when context lines are shown.
+fn parse_disassembly(output: &str) -> (Vec<Instruction>, Option<u64>) {
+    let mut instructions = Vec::new();
+    let mut pc = None;
+    let mut function: Option<String> = None;
+    let mut file: Option<String> = None;
+    let mut line: Option<(u64, String)> = None;
+    // Whether the current source line was marked with `**`, in which case context lines after it are ignored.
+    let mut marked = false;
+    for text in output.lines() {
+        let text = text.trim_start_matches("(lldb) ").trim_end();
+        let trimmed = text.trim_start();
+        let (current, rest) = match trimmed.strip_prefix("->") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, trimmed),
+        };
+
+        // Load address `0x100003f8c` or file address `a.out[0x100003f8c]`, then `<+28>:`.
+        let (address, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let address = address.trim_end_matches(':');
+        let address = address
+            .split_once('[')
+            .map_or(address, |(_, address)| address.trim_end_matches(']'));
+        if let Some(address) = memory::parse_address(address) {
+            let rest = rest.trim_start();
+            let (offset, rest) = match rest.strip_prefix("<+") {
+                Some(offset) => offset
+                    .split_once(">:")
+                    .map_or((None, rest), |(offset, rest)| (offset.parse().ok(), rest)),
+                None => (None, rest.strip_prefix(':').unwrap_or(rest)),
+            };
+            // Bytes are pairs of hex digits, or whole words like `0xa9bf7bfd` on some architectures.
+            let mut rest = rest.trim_start();
+            let mut opcodes = Vec::new();
+            while let Some(token) = rest.split_whitespace().next()
+                && (token.len() == 2 && token.chars().all(|c| c.is_ascii_hexdigit())
+                    || memory::parse_address(token).is_some())
+            {
+                opcodes.push(token);
+                rest = rest[token.len()..].trim_start();
+            }
+            if current {
+                pc = Some(address);
+            }
+            instructions.push(Instruction {
+                address,
+                function: function.clone(),
+                offset,
+                opcodes: (!opcodes.is_empty()).then(|| opcodes.join(" ")),
+                text: rest.to_owned(),
+                file: file.clone(),
+                line: line.as_ref().map(|(number, _)| *number),
+                source: line.as_ref().map(|(_, source)| source.clone()),
+            });
+            marked = false;
+            continue;
+        }
+
+        // Source line, `** 5   \tint main() {` or `   6   \t    int *p = 0;`.
+        let (is_marked, numbered) = match trimmed.strip_prefix("**") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, trimmed),
+        };
+        if let Some((number, source)) = numbered.split_once(char::is_whitespace)
+            && let Ok(number) = number.parse::<u64>()
+        {
+            if is_marked || !marked {
+                // The number is padded with spaces and separated from the text by a tab.
+                let source = source.trim_start_matches(' ');
+                let source = source.strip_prefix('\t').unwrap_or(source);
+                line = Some((number, source.to_owned()));
+                marked = is_marked;
+            }
+            continue;
+        }
+        // Header of the source lines, `crash.c:5`.
+        if !text.starts_with(char::is_whitespace)
+            && let Some((path, number)) = text.rsplit_once(':')
+            && number.parse::<u64>().is_ok()
+        {
+            file = Some(path.to_owned());
+            line = None;
+            marked = false;
+            continue;
+        }
+        // Header of the function, `a.out`main:`.
+        if let Some(name) = text.strip_suffix(':')
+            && !text.starts_with(char::is_whitespace)
+        {
+            function = Some(
+                name.split_once('`')
+                    .map_or(name, |(_, function)| function)
+                    .to_owned(),
+            );
+            file = None;
+            line = None;
+        }
+    }
+    (instructions, pc)
+}
+
+/// Number, address and size of a watchpoint printed by `watchpoint set` or `watchpoint list --brief`, such as
+/// `Watchpoint created: Watchpoint 1: addr = 0x7ffd2a4c size = 4 state = enabled type = w`.
+fn watchpoint_line(line: &str) -> Option<(u64, u64, u64)> {
+    let line = line.trim_start_matches("(lldb) ").trim_start();
+    let line = line.strip_prefix("Watchpoint created: ").unwrap_or(line);
+    let (number, rest) = line.strip_prefix("Watchpoint ")?.split_once(':')?;
+    let field = |name: &str| {
+        rest.split_once(name)
+            .and_then(|(_, value)| value.split_whitespace().next())
+    };
+    let address = field("addr = ").and_then(memory::parse_address)?;
+    let size = field("size = ")?.parse().ok()?;
+    Some((number.parse().ok()?, address, size))
+}
+
+/// A frame printed by `thread backtrace`, such as `  * frame #0: 0x0000555555555131 crash`main at crash.c:6:8` or
+/// `frame #2: 0x00007ffff7829e40 libc.so.6`__libc_start_main + 128`.
+fn backtrace_frame(line: &str) -> Option<Value> {
+    let line = line.trim_start_matches("(lldb) ").trim_start();
+    let line = line.strip_prefix("* ").unwrap_or(line);
+    let (level, rest) = line.strip_prefix("frame #")?.split_once(": ")?;
+    let (address, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let (library, rest) = rest.split_once('`').unwrap_or(("", rest));
+    // Arguments in parentheses may contain ` at ` themselves.
+    let mut depth = 0;
+    let mut location = None;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ' ' if depth == 0 && rest[index..].starts_with(" at ") => {
+                location = Some(index);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (function, location) = match location {
+        Some(index) => (&rest[..index], Some(&rest[index + 4..])),
+        None => (rest, None),
+    };
+    let function = function
+        .split(['(', ' '])
+        .next()
+        .filter(|function| !function.is_empty());
+    let mut location = location.map(|location| location.split(':'));
+    let file = location.as_mut().and_then(Iterator::next);
+    let line = location
+        .as_mut()
+        .and_then(Iterator::next)
+        .and_then(|line| line.parse::<u64>().ok());
+    Some(mi::compact(json!({
+        "level": level.parse::<u64>().ok(),
+        "function": function,
+        "address": address,
+        "file": file,
+        "line": line,
+        "library": (!library.is_empty()).then_some(library),
+    })))
+}
+
+/// Name and value of a register printed by `register read`, such as `rip = 0x0000000100003f8c  a.out`main + 12`.
+/// Vector registers are printed as a list of bytes in braces. Symbolic annotations after the value are dropped.
+fn register_value(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.trim_start_matches("(lldb) ").split_once(" = ")?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let value = value.trim();
+    let value = match value.find('}') {
+        Some(end) if value.starts_with('{') => &value[..=end],
+        _ => value.split_whitespace().next()?,
+    };
+    Some((name.to_owned(), value.to_owned()))
+}
+
+/// Process state changes printed by LLDB, forwarded to the client as log notifications, see [`observe_session`].
+fn lldb_event(line: &str) -> Option<(LoggingLevel, &'static str)> {
+    let line = line.trim_start_matches("(lldb) ").trim_start();
+    if line.starts_with("* thread #") {
+        return match line.split_once("stop reason = ")?.1 {
+            reason if reason.starts_with("signal") || reason.starts_with("EXC_") => {
+                Some((LoggingLevel::Warning, "signal"))
+            }
+            _ => Some((LoggingLevel::Notice, "stopped")),
+        };
+    }
+    let state = line.strip_prefix("Process ")?.split_once(' ')?.1;
+    let event = if state.starts_with("launched") {
+        (LoggingLevel::Info, "launched")
+    } else if state.starts_with("stopped") {
+        (LoggingLevel::Notice, "stopped")
+    } else if state.starts_with("exited") {
+        (LoggingLevel::Info, "exited")
+    } else if state.starts_with("resuming") {
+        (LoggingLevel::Debug, "running")
+    } else if state.starts_with("crashed") {
+        (LoggingLevel::Error, "crashed")
+    } else {
+        return None;
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassembly_with_bytes() {
+        let output = "(lldb) disassemble --bytes --frame\n\
+                      a.out`main:\n    \
+                      0x100003f70 <+0>:  55                    pushq  %rbp\n    \
+                      0x100003f71 <+1>:  48 89 e5              movq   %rsp, %rbp\n\
+                      ->  0x100003f8c <+28>: c7 00 2a 00 00 00     movl   $0x2a, (%rax)\n\
+                      libc.so.6`abort:\n    \
+                      libc.so.6[0x2a4d8]: 0xa9bf7bfd   stp    x29, x30, [sp, #-0x10]!\n";
+        let (instructions, pc) = parse_disassembly(output);
+        assert_eq!(pc, Some(0x1_0000_3f8c));
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0].address, 0x1_0000_3f70);
+        assert_eq!(instructions[0].function.as_deref(), Some("main"));
+        assert_eq!(instructions[0].offset, Some(0));
+        assert_eq!(instructions[0].opcodes.as_deref(), Some("55"));
+        assert_eq!(instructions[0].text, "pushq  %rbp");
+        assert_eq!(instructions[2].offset, Some(28));
+        assert_eq!(
+            instructions[2].opcodes.as_deref(),
+            Some("c7 00 2a 00 00 00")
+        );
+        assert_eq!(instructions[2].text, "movl   $0x2a, (%rax)");
+        assert_eq!(instructions[3].address, 0x2a4d8);
+        assert_eq!(instructions[3].function.as_deref(), Some("abort"));
+        assert_eq!(instructions[3].offset, None);
+        assert_eq!(instructions[3].opcodes.as_deref(), Some("0xa9bf7bfd"));
+        assert_eq!(instructions[3].text, "stp    x29, x30, [sp, #-0x10]!");
+    }
+
+    #[test]
+    fn mixed_disassembly_keeps_the_marked_source_line() {
+        let output = "crash`main:\n\
+                      crash.c:5\n   \
+                      4   \t\n\
+                      ** 5   \tint main() {\n   \
+                      6   \t    int *p = 0;\n\n    \
+                      0x1139 <+0>: pushq  %rbp\n\
+                      crash.c:6\n\
+                      ** 6   \t    int *p = 0;\n\n\
+                      ->  0x113d <+4>: movq   $0x0, -0x8(%rbp)\n";
+        let (instructions, pc) = parse_disassembly(output);
+        assert_eq!(pc, Some(0x113d));
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].file.as_deref(), Some("crash.c"));
+        assert_eq!(instructions[0].line, Some(5));
+        assert_eq!(instructions[0].source.as_deref(), Some("int main() {"));
+        assert_eq!(instructions[0].opcodes, None);
+        assert_eq!(instructions[1].line, Some(6));
+        assert_eq!(instructions[1].source.as_deref(), Some("    int *p = 0;"));
+        assert_eq!(instructions[1].function.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn disassembly_without_instructions() {
+        let (instructions, pc) = parse_disassembly("error: invalid frame\n");
+        assert!(instructions.is_empty());
+        assert_eq!(pc, None);
+    }
+
+    #[test]
+    fn backtrace_frames() {
+        assert_eq!(
+            backtrace_frame("  * frame #0: 0x0000555555555131 crash`main at crash.c:6:8"),
+            Some(json!({
+                "level": 0,
+                "function": "main",
+                "address": "0x0000555555555131",
+                "file": "crash.c",
+                "line": 6,
+                "library": "crash",
+            }))
+        );
+        assert_eq!(
+            backtrace_frame("    frame #2: 0x00007ffff7829e40 libc.so.6`__libc_start_main + 128"),
+            Some(json!({
+                "level": 2,
+                "function": "__libc_start_main",
+                "address": "0x00007ffff7829e40",
+                "library": "libc.so.6",
+            }))
+        );
+        // Arguments may contain ` at ` themselves.
+        assert_eq!(
+            backtrace_frame(
+                "frame #1: 0x1000 a.out`visit(name=\"look at me\", depth=2) at tree.cpp:41:5"
+            ),
+            Some(json!({
+                "level": 1,
+                "function": "visit",
+                "address": "0x1000",
+                "file": "tree.cpp",
+                "line": 41,
+                "library": "a.out",
+            }))
+        );
+        assert_eq!(
+            backtrace_frame("frame #3: 0x0000000000000000"),
+            Some(json!({ "level": 3, "address": "0x0000000000000000" }))
+        );
+        assert_eq!(
+            backtrace_frame("* thread #1, name = 'crash', stop reason = signal SIGSEGV"),
+            None
+        );
+    }
+
+    #[test]
+    fn watchpoint_lines() {
+        assert_eq!(
+            watchpoint_line(
+                "Watchpoint created: Watchpoint 1: addr = 0x7ffd2a4c size = 4 state = enabled type = w"
+            ),
+            Some((1, 0x7ffd_2a4c, 4))
+        );
+        assert_eq!(
+            watchpoint_line(
+                "(lldb) Watchpoint 12: addr = 0x00005555555592a0 size = 8 state = disabled type = rw"
+            ),
+            Some((12, 0x5555_5555_92a0, 8))
+        );
+        assert_eq!(watchpoint_line("Watchpoint 1: addr = 0x10 size = x"), None);
+        assert_eq!(watchpoint_line("Watchpoint 1 hit:"), None);
+        assert_eq!(
+            watchpoint_line("Number of supported hardware watchpoints: 4"),
+            None
+        );
+    }
+}
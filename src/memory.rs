@@ -0,0 +1,320 @@
+//! Memory read by the debuggers, rendered as a canonical hexdump with optional decodings.
+//!
+//! Reads may only partly succeed, for example when a range crosses into an unmapped page. The bytes that could not
+//! be read are shown as `??` and listed as unreadable ranges, instead of failing the whole read.
+use std::str::FromStr;
+
+use serde_json::{Map, Value, json};
+
+/// Most bytes a single read tool call returns.
+pub const MAX_READ_BYTES: u64 = 64 * 1024;
+/// Bytes per hexdump line.
+const LINE_BYTES: u64 = 16;
+
+/// How to decode the bytes read, besides the hexdump.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoding {
+    U8,
+    U16,
+    U32,
+    U64,
+    Ascii,
+    Utf16,
+}
+
+impl Decoding {
+    pub const ALL: [Decoding; 6] = [
+        Decoding::U8,
+        Decoding::U16,
+        Decoding::U32,
+        Decoding::U64,
+        Decoding::Ascii,
+        Decoding::Utf16,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Decoding::U8 => "u8",
+            Decoding::U16 => "u16",
+            Decoding::U32 => "u32",
+            Decoding::U64 => "u64",
+            Decoding::Ascii => "ascii",
+            Decoding::Utf16 => "utf16",
+        }
+    }
+}
+
+impl FromStr for Decoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Decoding::ALL
+            .into_iter()
+            .find(|decoding| decoding.name() == s.trim().to_ascii_lowercase())
+            .ok_or(format!(
+                "Unknown decoding '{}'. Expected one of u8, u16, u32, u64, ascii, utf16",
+                s
+            ))
+    }
+}
+
+/// Check the length of a read requested by the client.
+pub fn check_length(length: u64) -> Result<(), String> {
+    if length == 0 || length > MAX_READ_BYTES {
+        return Err(format!(
+            "Invalid length {}. Between 1 and {} bytes can be read at once",
+            length, MAX_READ_BYTES
+        ));
+    }
+    Ok(())
+}
+
+/// Parse an address printed by a debugger, such as `0x00007ffe5a3c`.
+pub fn parse_address(address: &str) -> Option<u64> {
+    let address = address.trim();
+    let hex = address.strip_prefix("0x").or(address.strip_prefix("0X"))?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// A range of memory and the parts of it that could be read.
+pub struct Memory {
+    address: u64,
+    length: u64,
+    /// Readable chunks as start address and bytes, in address order.
+    chunks: Vec<(u64, Vec<u8>)>,
+}
+
+impl Memory {
+    /// `length` bytes from `address`, of which nothing could be read yet.
+    pub fn new(address: u64, length: u64) -> Self {
+        Self {
+            address,
+            length,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Add bytes read starting at `address`. Bytes outside the range are ignored.
+    pub fn add(&mut self, address: u64, bytes: Vec<u8>) {
+        self.chunks.push((address, bytes));
+        self.chunks.sort_by_key(|(address, _)| *address);
+    }
+
+    fn byte(&self, address: u64) -> Option<u8> {
+        self.chunks.iter().find_map(|(start, bytes)| {
+            let offset = address.checked_sub(*start)?;
+            bytes.get(usize::try_from(offset).ok()?).copied()
+        })
+    }
+
+    fn bytes(&self) -> Vec<Option<u8>> {
+        (0..self.length)
+            .map(|offset| self.byte(self.address.wrapping_add(offset)))
+            .collect()
+    }
+
+    /// Ranges that could not be read, as start address and length.
+    pub fn unreadable(&self) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for (offset, byte) in self.bytes().into_iter().enumerate() {
+            if byte.is_some() {
+                continue;
+            }
+            let address = self.address.wrapping_add(offset as u64);
+            match ranges.last_mut() {
+                Some((start, length)) if start.wrapping_add(*length) == address => *length += 1,
+                _ => ranges.push((address, 1)),
+            }
+        }
+        ranges
+    }
+
+    /// Canonical hexdump: address, 16 bytes in two groups of 8, and the printable ASCII characters.
+    pub fn hexdump(&self) -> String {
+        let bytes = self.bytes();
+        let mut dump = String::new();
+        for (line, line_bytes) in bytes.chunks(LINE_BYTES as usize).enumerate() {
+            let address = self.address.wrapping_add(line as u64 * LINE_BYTES);
+            dump.push_str(&format!("{:016x} ", address));
+            for index in 0..LINE_BYTES as usize {
+                if index % 8 == 0 {
+                    dump.push(' ');
+                }
+                match line_bytes.get(index) {
+                    Some(Some(byte)) => dump.push_str(&format!("{:02x} ", byte)),
+                    Some(None) => dump.push_str("?? "),
+                    None => dump.push_str("   "),
+                }
+            }
+            dump.push_str(" |");
+            for byte in line_bytes {
+                dump.push(match byte {
+                    Some(byte) if byte.is_ascii_graphic() || *byte == b' ' => *byte as char,
+                    Some(_) => '.',
+                    None => '?',
+                });
+            }
+            dump.push_str("|\n");
+        }
+        dump
+    }
+
+    /// Decode the bytes. Integers overlapping unreadable bytes are `null`, as are trailing bytes too few for one.
+    fn decode(&self, decoding: Decoding, big_endian: bool) -> Value {
+        let bytes = self.bytes();
+        let integers = |size: usize| -> Value {
+            bytes
+                .chunks(size)
+                .map(|chunk| {
+                    let chunk: Option<Vec<u8>> = chunk.iter().copied().collect();
+                    let chunk = chunk.filter(|chunk| chunk.len() == size)?;
+                    let mut value = 0u64;
+                    for index in 0..size {
+                        let byte = if big_endian {
+                            chunk[index]
+                        } else {
+                            chunk[size - 1 - index]
+                        };
+                        value = (value << 8) | u64::from(byte);
+                    }
+                    Some(value)
+                })
+                .collect::<Vec<Option<u64>>>()
+                .into()
+        };
+        match decoding {
+            Decoding::U8 => integers(1),
+            Decoding::U16 => integers(2),
+            Decoding::U32 => integers(4),
+            Decoding::U64 => integers(8),
+            Decoding::Ascii => bytes
+                .iter()
+                .map(|byte| match byte {
+                    Some(byte) if byte.is_ascii() => *byte as char,
+                    Some(_) => '.',
+                    None => char::REPLACEMENT_CHARACTER,
+                })
+                .collect::<String>()
+                .into(),
+            Decoding::Utf16 => {
+                let units = bytes.chunks(2).map(|pair| match pair {
+                    [Some(first), Some(second)] if big_endian => {
+                        u16::from_be_bytes([*first, *second])
+                    }
+                    [Some(first), Some(second)] => u16::from_le_bytes([*first, *second]),
+                    _ => 0xfffd,
+                });
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect::<String>()
+                    .into()
+            }
+        }
+    }
+
+    /// The read as JSON: address, length, hexdump, unreadable ranges and the requested decodings.
+    pub fn to_json(&self, decodings: &[Decoding], big_endian: bool) -> Value {
+        let mut read = Map::new();
+        read.insert("address".to_owned(), format!("{:#x}", self.address).into());
+        read.insert("length".to_owned(), self.length.into());
+        read.insert("hexdump".to_owned(), self.hexdump().into());
+        let unreadable: Vec<Value> = self
+            .unreadable()
+            .into_iter()
+            .map(|(address, length)| json!({ "address": format!("{:#x}", address), "length": length }))
+            .collect();
+        if !unreadable.is_empty() {
+            read.insert("unreadable".to_owned(), unreadable.into());
+        }
+        if !decodings.is_empty() {
+            read.insert(
+                "endian".to_owned(),
+                if big_endian { "big" } else { "little" }.into(),
+            );
+        }
+        for decoding in decodings {
+            read.insert(
+                decoding.name().to_owned(),
+                self.decode(*decoding, big_endian),
+            );
+        }
+        Value::Object(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20 bytes at 0x1000, with 4 unreadable bytes in the first line and 2 at the end.
+    fn partly_read() -> Memory {
+        let mut memory = Memory::new(0x1000, 20);
+        memory.add(0x100c, vec![0x00, 0x7f, b'A', b'\n', 0xff, b'B']);
+        memory.add(0x1000, b"Hi there".to_vec());
+        memory
+    }
+
+    #[test]
+    fn hexdump_marks_unreadable_bytes() {
+        let second_line = format!(
+            "0000000000001010  ff 42 ?? ?? {} |.B??|\n",
+            " ".repeat(3 * 12 + 1)
+        );
+        assert_eq!(
+            partly_read().hexdump(),
+            format!(
+                "0000000000001000  48 69 20 74 68 65 72 65  ?? ?? ?? ?? 00 7f 41 0a  |Hi there????..A.|\n{}",
+                second_line
+            )
+        );
+    }
+
+    #[test]
+    fn unreadable_ranges_are_merged() {
+        assert_eq!(partly_read().unreadable(), vec![(0x1008, 4), (0x1012, 2)]);
+        assert_eq!(Memory::new(0x10, 3).unreadable(), vec![(0x10, 3)]);
+
+        let mut memory = Memory::new(0x10, 4);
+        memory.add(0x0, vec![0; 0x20]);
+        assert!(memory.unreadable().is_empty());
+
+        // Ranges continue across the end of the address space.
+        let mut memory = Memory::new(u64::MAX - 1, 4);
+        memory.add(u64::MAX - 1, vec![1, 2]);
+        assert_eq!(memory.unreadable(), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn decodings_skip_unreadable_bytes() {
+        let mut memory = Memory::new(0x2000, 5);
+        memory.add(0x2000, vec![0x12, 0x34, 0x56, 0x78]);
+        let read = memory.to_json(&[Decoding::U16, Decoding::U32, Decoding::Ascii], false);
+        assert_eq!(read["address"], "0x2000");
+        assert_eq!(read["endian"], "little");
+        assert_eq!(read["u16"], json!([0x3412, 0x7856, null]));
+        assert_eq!(read["u32"], json!([0x7856_3412, null]));
+        assert_eq!(read["ascii"], "\u{12}4Vx\u{fffd}");
+        assert_eq!(
+            read["unreadable"],
+            json!([{ "address": "0x2004", "length": 1 }])
+        );
+
+        let read = memory.to_json(&[Decoding::U16, Decoding::Utf16], true);
+        assert_eq!(read["endian"], "big");
+        assert_eq!(read["u16"], json!([0x1234, 0x5678, null]));
+        assert_eq!(read["utf16"], "\u{1234}\u{5678}\u{fffd}");
+        assert!(memory.to_json(&[], false).get("endian").is_none());
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        assert_eq!(parse_address(" 0x00007ffe5a3c\n"), Some(0x7ffe_5a3c));
+        assert_eq!(parse_address("0XFF"), Some(0xff));
+        assert_eq!(parse_address("4096"), None);
+        assert_eq!(" UTF16 ".parse::<Decoding>(), Ok(Decoding::Utf16));
+        assert!("u128".parse::<Decoding>().is_err());
+        assert!(check_length(0).is_err());
+        assert!(check_length(MAX_READ_BYTES).is_ok());
+        assert!(check_length(MAX_READ_BYTES + 1).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Parsing of GDB/MI output records, for tools returning structured results.
+//!
+//! Values are converted to JSON: c-strings to strings, tuples to objects and lists to arrays. The names of list
+//! elements (as in `stack=[frame={...},frame={...}]`) are dropped, since they repeat for every element.
+use serde_json::{Map, Value};
+
+/// The result record of a command: its class (`done`, `running`, `connected`, `exit`) and results, in order.
+/// Results without a name, such as the extra locations GDB prints after a multi-location breakpoint, have an
+/// empty name.
+#[derive(Debug)]
+pub struct ResultRecord {
+    pub class: String,
+    pub results: Vec<(String, Value)>,
+}
+
+impl ResultRecord {
+    /// The results as a JSON object, keeping the first of results with the same name.
+    pub fn into_value(self) -> Value {
+        let mut results = Map::new();
+        for (name, value) in self.results {
+            results.entry(name).or_insert(value);
+        }
+        Value::Object(results)
+    }
+
+    /// The first result with the given name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.results
+            .iter()
+            .find(|(result, _)| result == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Find the result record in the output of a command. An `^error` record is returned as its message.
+pub fn result_record(output: &str) -> Result<ResultRecord, String> {
+    let line = output
+        .lines()
+        .map(|line| line.trim_start_matches(|c: char| c.is_ascii_digit()))
+        .find(|line| line.starts_with('^'))
+        .ok_or(format!("No result record in GDB output: {}", output.trim()))?;
+    let mut parser = Parser::new(&line[1..]);
+    let class = parser.word();
+    let mut results = Vec::new();
+    while parser.eat(',') {
+        results.push(parser.result()?);
+    }
+    if class == "error" {
+        let message = results
+            .iter()
+            .find(|(name, _)| name == "msg")
+            .and_then(|(_, value)| value.as_str())
+            .unwrap_or("unknown error");
+        return Err(message.to_owned());
+    }
+    Ok(ResultRecord { class, results })
+}
+
+/// Parse an asynchronous record, such as `*stopped,...` or `=thread-exited,...`, without its leading `*`, `+` or
+/// `=` in the class. `None` if the line is not a well-formed asynchronous record.
+pub fn async_record(line: &str) -> Option<ResultRecord> {
+    let line = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let record = line.strip_prefix(['*', '+', '='])?;
+    let mut parser = Parser::new(record.trim_end());
+    let class = parser.word();
+    let mut results = Vec::new();
+    while parser.eat(',') {
+        results.push(parser.result().ok()?);
+    }
+    Some(ResultRecord { class, results })
+}
+
+/// Text of a c-string, such as the payload of a stream record.
+pub fn c_string(text: &str) -> Result<String, String> {
+    Parser::new(text.trim()).string()
+}
+
+/// Arguments of an MI command, as GDB splits them: at whitespace, with c-strings taken as one argument and unquoted.
+pub fn arguments(text: &str) -> Result<Vec<String>, String> {
+    let mut arguments = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if rest.starts_with('"') {
+            let mut parser = Parser::new(rest);
+            arguments.push(parser.string()?);
+            rest = &rest[parser.position..];
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            arguments.push(rest[..end].to_owned());
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(arguments)
+}
+
+/// Quote an argument of an MI command as a c-string.
+pub fn quote(argument: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in argument.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// String field of an MI tuple.
+pub fn field<'a>(tuple: &'a Value, name: &str) -> Option<&'a str> {
+    tuple.get(name).and_then(Value::as_str)
+}
+
+/// Numeric field of an MI tuple.
+pub fn number(tuple: &Value, name: &str) -> Option<u64> {
+    field(tuple, name).and_then(|value| value.parse().ok())
+}
+
+/// Drop the fields of a JSON object that GDB did not report, to keep tool results short.
+pub fn compact(mut value: Value) -> Value {
+    if let Value::Object(fields) = &mut value {
+        fields.retain(|_, field| !field.is_null());
+    }
+    value
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Malformed GDB/MI record, expected '{}' at column {}: {}",
+                expected, self.position, self.input
+            ))
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let rest = &self.input[self.position..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(rest.len());
+        self.position += end;
+        rest[..end].to_owned()
+    }
+
+    /// `name=value`, or a bare value.
+    fn result(&mut self) -> Result<(String, Value), String> {
+        if matches!(self.peek(), Some('"' | '{' | '[')) {
+            return Ok((String::new(), self.value()?));
+        }
+        let name = self.word();
+        self.expect('=')?;
+        Ok((name, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.string().map(Value::String),
+            Some('{') => {
+                self.position += 1;
+                let mut tuple = Map::new();
+                if !self.eat('}') {
+                    loop {
+                        let (name, value) = self.result()?;
+                        tuple.entry(name).or_insert(value);
+                        if self.eat('}') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Value::Object(tuple))
+            }
+            Some('[') => {
+                self.position += 1;
+                let mut list = Vec::new();
+                if !self.eat(']') {
+                    loop {
+                        list.push(self.result()?.1);
+                        if self.eat(']') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Value::Array(list))
+            }
+            _ => Err(format!(
+                "Malformed GDB/MI record, expected a value at column {}: {}",
+                self.position, self.input
+            )),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        // Bytes rather than characters, as GDB escapes bytes of non-ASCII text in octal.
+        let mut text = Vec::new();
+        let bytes = self.input.as_bytes();
+        let mut index = self.position;
+        while let Some(&byte) = bytes.get(index) {
+            index += 1;
+            match byte {
+                b'"' => {
+                    self.position = index;
+                    return Ok(String::from_utf8_lossy(&text).into_owned());
+                }
+                b'\\' => {
+                    let Some(&escaped) = bytes.get(index) else {
+                        break;
+                    };
+                    index += 1;
+                    match escaped {
+                        b'n' => text.push(b'\n'),
+                        b't' => text.push(b'\t'),
+                        b'r' => text.push(b'\r'),
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match bytes.get(index) {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        index += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            text.push(value as u8);
+                        }
+                        escaped => text.push(escaped),
+                    }
+                }
+                byte => text.push(byte),
+            }
+        }
+        Err(format!(
+            "Unterminated c-string in GDB/MI record: {}",
+            self.input
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn result_record_is_found_after_stream_records() {
+        let output = "~\"Breakpoint 1 at 0x1139\\n\"\n12^done,bkpt={number=\"1\",type=\"breakpoint\",\
+                      locations=[{number=\"1.1\"},{number=\"1.2\"}]},{number=\"1.3\"}\n(gdb) \n";
+        let record = result_record(output).unwrap();
+        assert_eq!(record.class, "done");
+        assert_eq!(
+            record.get("bkpt"),
+            Some(&json!({
+                "number": "1",
+                "type": "breakpoint",
+                "locations": [{ "number": "1.1" }, { "number": "1.2" }],
+            }))
+        );
+        assert_eq!(
+            record.results[1],
+            (String::new(), json!({ "number": "1.3" }))
+        );
+        assert_eq!(
+            record.into_value(),
+            json!({
+                "bkpt": {
+                    "number": "1",
+                    "type": "breakpoint",
+                    "locations": [{ "number": "1.1" }, { "number": "1.2" }],
+                },
+                "": { "number": "1.3" },
+            })
+        );
+    }
+
+    #[test]
+    fn result_record_without_results() {
+        let record = result_record("^running\n*running,thread-id=\"all\"\n").unwrap();
+        assert_eq!(record.class, "running");
+        assert!(record.results.is_empty());
+    }
+
+    #[test]
+    fn error_record_is_its_message() {
+        assert_eq!(
+            result_record("^error,msg=\"No symbol \\\"foo\\\" in current context.\"").unwrap_err(),
+            "No symbol \"foo\" in current context."
+        );
+        assert_eq!(result_record("^error").unwrap_err(), "unknown error");
+        assert!(
+            result_record("~\"no record\"\n")
+                .unwrap_err()
+                .starts_with("No result record")
+        );
+    }
+
+    #[test]
+    fn malformed_records_are_errors() {
+        assert!(result_record("^done,value=").is_err());
+        assert!(result_record("^done,value=\"unterminated").is_err());
+        assert!(result_record("^done,tuple={a=\"1\"").is_err());
+        assert!(result_record("^done,list=[\"1\" \"2\"]").is_err());
+    }
+
+    #[test]
+    fn c_strings_are_unescaped() {
+        assert_eq!(
+            c_string("\"a\\tb\\n\\\"c\\\"\\\\\"").unwrap(),
+            "a\tb\n\"c\"\\"
+        );
+        // Octal escapes are bytes of UTF-8 text, invalid bytes are replaced.
+        assert_eq!(c_string("\"\\303\\251t\\351\"").unwrap(), "ét\u{fffd}");
+    }
+
+    #[test]
+    fn arguments_unquote_c_strings() {
+        assert_eq!(
+            arguments("  -var-create - * \"a + \\\"b\\\"\"  x").unwrap(),
+            vec!["-var-create", "-", "*", "a + \"b\"", "x"]
+        );
+        assert!(arguments("\"open").is_err());
+        assert_eq!(arguments(&quote("p \"x\"\n")).unwrap(), vec!["p \"x\"\n"]);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    CLIDebugger,
+    client::Client,
+    generate_session_id,
+    resources::Activity,
+    server::{
+        Backend, DEFAULT_NAMESPACE, ServerContext, Session, SessionMap, SessionNamespaces,
+        observe_session,
+    },
+};
+
+use rmcp::{
+    ServerHandler,
+    model::{LoggingLevel, ServerCapabilities, ServerInfo},
+    tool,
+};
+
+/// MCP server exposing PDB sessions as tools.
+#[derive(Clone)]
+pub struct PdbServer {
+    sessions: SessionMap,
+    namespaces: SessionNamespaces,
+    context: ServerContext,
+    namespace: String,
+    client: Client,
+}
+
+#[tool(tool_box)]
+impl ServerHandler for PdbServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some("Python Debugger".into()),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tool(tool_box)]
+impl PdbServer {
+    pub fn new(context: ServerContext) -> Self {
+        let namespaces = SessionNamespaces::default();
+        Self {
+            sessions: namespaces.get(DEFAULT_NAMESPACE),
+            namespaces,
+            context,
+            namespace: DEFAULT_NAMESPACE.to_owned(),
+            client: Client::default(),
+        }
+    }
+
+    /// Returns a handle to this server that only sees the sessions of the given client namespace.
+    pub fn in_namespace(&self, namespace: &str) -> Self {
+        Self {
+            sessions: self.namespaces.get(namespace),
+            namespace: namespace.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Sessions of the client namespace of this handle.
+    pub fn sessions(&self) -> &SessionMap {
+        &self.sessions
+    }
+
+    /// Returns a handle to this server that talks to the given client.
+    pub fn with_client(&self, client: Client) -> Self {
+        Self {
+            client,
+            ..self.clone()
+        }
+    }
+
+    #[tool(
+        description = "Start a new PDB debugging session. When done using it, terminate the session"
+    )]
+    async fn pdb_start(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Path to the python script to debug")]
+        program: String,
+        #[tool(param)]
+        #[schemars(description = "Arguments to pass to the python script")]
+        arguments: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(
+            description = "Only allow inspection commands (backtraces, printing, memory reads, disassembly, source listing) in this session"
+        )]
+        read_only: Option<bool>,
+    ) -> Result<String, String> {
+        let session_id = format!("pdb-{}", generate_session_id());
+        let slot = self.context.limits.acquire(&self.namespace)?;
+        let program = self.context.resolve_path(&self.client, &program).await?;
+        let mut pdb_args = vec![
+            "-m".to_owned(),
+            "pdb".to_owned(),
+            program.display().to_string(),
+        ];
+        if let Some(arg) = arguments {
+            pdb_args.extend(arg);
+        }
+        let activity = Activity::shared(&session_id);
+        let mut debugger = CLIDebugger::new("python3")
+            .args(pdb_args)
+            .prompt("(Pdb)")
+            .observe_lines(observe_session(
+                self.client.clone(),
+                Backend::Pdb,
+                &session_id,
+                activity.clone(),
+                pdb_event,
+            ));
+        if let Some(workspace) = self.context.workspace(&self.client).await {
+            debugger = debugger.current_dir(workspace);
+        }
+        if self.context.sandbox.enabled {
+            debugger = debugger.sandbox(self.context.sandbox.as_ref().clone());
+        }
+        let mut session = debugger
+            .spawn()
+            .map_err(|err| format!("Failed to start PDB session. [Error]: {}", err))?;
+        let response = session
+            .read_response()
+            .await
+            .map_err(|err| format!("Failed to read from PDB session. [Error]: {}", err))?;
+
+        let sandbox = session
+            .sandbox()
+            .map(|sandbox| format!(" [Sandbox]: {}.", sandbox))
+            .unwrap_or_default();
+        self.sessions.lock().await.insert(
+            session_id.clone(),
+            Session::new(session, read_only.unwrap_or(false), activity),
+        );
+        slot.commit();
+        self.context
+            .logger
+            .log(format!("PDB session {} started", session_id));
+        Ok(format!(
+            "PDB session started with ID {}.{} [PDB output]: {}",
+            session_id, sandbox, response
+        ))
+    }
+
+    #[tool(description = "Execute a PDB command")]
+    async fn pdb_command(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "PDB session ID")]
+        session_id: String,
+        #[tool(param)]
+        #[schemars(description = "PDB command to execute")]
+        command: String,
+    ) -> Result<String, String> {
+        self.context
+            .authorize(
+                &self.client,
+                Backend::Pdb,
+                &self.sessions,
+                &session_id,
+                &command,
+            )
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        let response = session
+            .execute_command(&command)
+            .await
+            .map_err(|err| format!("Failed to execute PDB command. [Error]: {}", err))?;
+
+        Ok(format!("Command executed.\n[PDB output]: {}", response))
+    }
+
+    #[tool(description = "Terminate a PDB session")]
+    async fn pdb_terminate(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "PDB session ID")]
+        session_id: String,
+    ) -> Result<String, String> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(&session_id).ok_or(format!(
+            "Session with ID {} not found. Start a new session",
+            session_id
+        ))?;
+
+        session
+            .terminate()
+            .await
+            .map_err(|err| format!("Failed to terminate PDB session. [Error]: {}", err))?;
+        sessions.remove(&session_id);
+        self.context.limits.release(&self.namespace);
+        self.context
+            .logger
+            .log(format!("PDB session {} terminated", session_id));
+        Ok("PDB session terminated".to_string())
+    }
+}
+
+/// Stops, exceptions and program exits reported by PDB, forwarded to the client as log notifications, see
+/// [`observe_session`].
+fn pdb_event(line: &str) -> Option<(LoggingLevel, &'static str)> {
+    let line = line.trim_start_matches("(Pdb) ");
+    if line.starts_with("Uncaught exception") {
+        return Some((LoggingLevel::Error, "uncaught-exception"));
+    }
+    if line.starts_with("The program finished") || line.starts_with("The program exited") {
+        return Some((LoggingLevel::Info, "exited"));
+    }
+    // Location printed whenever PDB stops, such as `> /tmp/script.py(4)<module>()`.
+    if let Some(location) = line.strip_prefix("> ")
+        && location.contains('(')
+    {
+        return Some((LoggingLevel::Debug, "stopped"));
+    }
+    // Last line of a traceback, such as `ZeroDivisionError: division by zero`.
+    let (name, _) = line.split_once(':')?;
+    let is_exception = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && ["Error", "Exception", "Interrupt", "Exit"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix));
+    is_exception.then_some((LoggingLevel::Error, "exception"))
+}
@@ -0,0 +1,997 @@
+//! Allow/deny rules applied to debugger commands before they reach the debugger process.
+//!
+//! A rule is a command verb of one or more words, for example `shell` or `platform shell`.
+//! Commands are split into words per debugger, with shortcuts such as GDB's `!` expanded,
+//! and a rule matches when each of its words matches the corresponding command word.
+//! Command words may be abbreviated (`she` matches `shell`), as the debuggers accept unique prefixes.
+use std::fmt::Display;
+
+use serde::Deserialize;
+
+use crate::{mi, readonly, server::Backend};
+
+/// Shortest abbreviation of a command word that is matched against rules.
+const MIN_ABBREVIATION: usize = 2;
+
+/// Rules for a single debugger.
+/// Deny rules are checked first. If any allow rule is configured, commands must also match one of them.
+/// Commands matching an approve rule are only run once a human approves them through the client.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub approve: Vec<String>,
+}
+
+impl RuleSet {
+    fn deny<const N: usize>(rules: [&str; N]) -> Self {
+        Self {
+            deny: rules.into_iter().map(String::from).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Command rules for every debugger. A debugger missing from the configuration keeps its default rules,
+/// which block commands that run programs or code on the host or write files.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Only allow inspection commands in every session, see [`crate::readonly`].
+    pub read_only: bool,
+    pub gdb: RuleSet,
+    pub lldb: RuleSet,
+    pub pdb: RuleSet,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            gdb: RuleSet::deny([
+                "shell",
+                "pipe",
+                "python",
+                "guile",
+                "dump",
+                "restore",
+                "alias",
+                "define",
+                "eval",
+                "make",
+                "compile",
+                "jit-reader-load",
+                // Commands writing files on the host
+                "set logging",
+                "-gdb-set logging",
+                "set history",
+                "-gdb-set history",
+                "gcore",
+                "generate-core-file",
+                "save",
+                "append",
+                "target remote |",
+                "target extended-remote |",
+                "-target-select remote |",
+                "-target-select extended-remote |",
+            ]),
+            lldb: RuleSet::deny([
+                "platform shell",
+                "shell",
+                "script",
+                "command alias",
+                "command regex",
+                "command script",
+            ]),
+            // Expressions given to `p`, `pp`, `display` or `condition` are Python code, which can do anything
+            // the debugged program can: pdb sessions are only confined by the sandbox, not by these rules.
+            pdb: RuleSet::deny(["!", "interact", "debug", "alias"]),
+        }
+    }
+}
+
+/// Outcome of checking a command that the [`Policy`] does not reject outright.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// The command can be sent to the debugger.
+    Allow,
+    /// The command matches the given approve rule and must be approved by a human first.
+    NeedsApproval(String),
+}
+
+/// A command rejected by the [`Policy`].
+#[derive(Clone, Debug)]
+pub struct PolicyViolation {
+    pub backend: Backend,
+    pub command: String,
+    pub rule: String,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command rejected by {} policy. [Rule]: {} [Command]: {}",
+            self.backend.name(),
+            self.rule,
+            self.command
+        )
+    }
+}
+
+impl From<PolicyViolation> for String {
+    fn from(violation: PolicyViolation) -> Self {
+        violation.to_string()
+    }
+}
+
+impl Policy {
+    /// Rules of the given debugger.
+    pub fn rules(&self, backend: Backend) -> &RuleSet {
+        match backend {
+            Backend::Gdb => &self.gdb,
+            Backend::Lldb => &self.lldb,
+            Backend::Pdb => &self.pdb,
+        }
+    }
+
+    /// Check a command before it is sent to the debugger.
+    /// Every line is checked separately, since each one reaches the debugger as its own command.
+    /// `read_only` restricts the command to inspection, in addition to the server wide `read_only` setting.
+    pub fn check(
+        &self,
+        backend: Backend,
+        command: &str,
+        read_only: bool,
+    ) -> Result<Decision, PolicyViolation> {
+        let rules = self.rules(backend);
+        let mut decision = Decision::Allow;
+        for line in command.lines() {
+            for words in command_words(backend, line) {
+                if words.is_empty() {
+                    continue;
+                }
+                let violation = |rule: String| PolicyViolation {
+                    backend,
+                    command: line.trim().to_owned(),
+                    rule,
+                };
+                if let Some(rule) = rules.deny.iter().find(|rule| matches(rule, &words)) {
+                    return Err(violation(format!("deny '{}'", rule)));
+                }
+                if !rules.allow.is_empty() && !rules.allow.iter().any(|rule| matches(rule, &words))
+                {
+                    return Err(violation("not in allow list".to_owned()));
+                }
+                if self.read_only || read_only {
+                    readonly::check(backend, &words).map_err(violation)?;
+                }
+                if decision == Decision::Allow
+                    && let Some(rule) = rules.approve.iter().find(|rule| matches(rule, &words))
+                {
+                    decision = Decision::NeedsApproval(rule.clone());
+                }
+            }
+        }
+        Ok(decision)
+    }
+}
+
+fn matches(rule: &str, words: &[String]) -> bool {
+    let rule_words: Vec<&str> = rule.split_whitespace().collect();
+    !rule_words.is_empty()
+        && rule_words.len() <= words.len()
+        && rule_words
+            .iter()
+            .zip(words)
+            .all(|(rule_word, word)| word_matches(rule_word, word))
+}
+
+fn word_matches(rule_word: &str, word: &str) -> bool {
+    word == rule_word
+        || (word.len() >= MIN_ABBREVIATION
+            && word
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            && rule_word.starts_with(word))
+}
+
+/// Split a command line into the words of each debugger command it contains, see [`commands`].
+fn command_words(backend: Backend, line: &str) -> Vec<Vec<String>> {
+    commands(backend, line)
+        .iter()
+        .map(|command| split_command(backend, command))
+        .collect()
+}
+
+/// Split the text of a single debugger command into words.
+fn split_command(backend: Backend, command: &str) -> Vec<String> {
+    match backend {
+        Backend::Gdb => gdb_words(command),
+        Backend::Lldb => split_words(command),
+        Backend::Pdb => pdb_words(command),
+    }
+}
+
+/// Text of every debugger command a command line runs: the commands separated by `;;` in pdb, or the command and
+/// the commands nested in it by wrappers such as GDB's `thread apply all COMMAND` or LLDB's
+/// `breakpoint command add -o COMMAND`. Nested commands follow the command running them.
+fn commands(backend: Backend, line: &str) -> Vec<String> {
+    match backend {
+        Backend::Gdb => {
+            let line = gdb_unwrap(line);
+            let mut commands = vec![line.clone()];
+            for nested in gdb_nested(&line) {
+                commands.extend(self::commands(backend, &nested));
+            }
+            commands
+        }
+        Backend::Lldb => {
+            let mut commands = vec![line.to_owned()];
+            for nested in lldb_nested(line) {
+                commands.extend(self::commands(backend, &nested));
+            }
+            commands
+        }
+        Backend::Pdb => line.split(";;").map(str::to_owned).collect(),
+    }
+}
+
+/// Whether the leading words of a command name a command, each word possibly abbreviated to any prefix. Stricter
+/// than [`matches`], since taking a command for a wrapper only means checking more commands.
+fn starts_with_command(words: &[String], command: &str) -> bool {
+    let command: Vec<&str> = command.split_whitespace().collect();
+    command.len() <= words.len()
+        && command
+            .iter()
+            .zip(words)
+            .all(|(name, word)| !word.is_empty() && name.starts_with(word.as_str()))
+}
+
+/// The text of a command after its first `count` words.
+fn skip_words(text: &str, count: usize) -> &str {
+    let mut rest = text.trim_start();
+    for _ in 0..count {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    rest
+}
+
+/// Commands nested in a GDB command by a wrapper:
+/// - `thread apply all|ID... [FLAG]... COMMAND`, `frame apply all|COUNT|level LEVEL... [FLAG]... COMMAND`
+/// - `taas`, `faas` and `tfaas [FLAG]... COMMAND`
+/// - `with SETTING [VALUE] -- COMMAND`
+/// - `-break-commands NUMBER COMMAND...`, each command a separate argument
+fn gdb_nested(line: &str) -> Vec<String> {
+    let words = split_words(line);
+    let nested_after = |skip: usize| {
+        let index = skip
+            + words[skip..]
+                .iter()
+                .take_while(|word| {
+                    matches!(word.as_str(), "all" | "level")
+                        || word.starts_with(['-', '$'])
+                        || word.starts_with(|c: char| c.is_ascii_digit())
+                })
+                .count();
+        let nested = skip_words(line, index);
+        if nested.is_empty() {
+            Vec::new()
+        } else {
+            vec![nested.to_owned()]
+        }
+    };
+    if starts_with_command(&words, "thread apply") || starts_with_command(&words, "frame apply") {
+        nested_after(2)
+    } else if ["taas", "faas", "tfaas"].contains(&words.first().map_or("", String::as_str)) {
+        nested_after(1)
+    } else if starts_with_command(&words, "with") && words.len() > 1 {
+        match words.iter().position(|word| word == "--") {
+            Some(separator) => vec![skip_words(line, separator + 1).to_owned()],
+            None => Vec::new(),
+        }
+    } else if words.first().is_some_and(|verb| verb == "-break-commands") {
+        mi::arguments(skip_words(line, 1))
+            .unwrap_or_default()
+            .into_iter()
+            .skip(1)
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// LLDB commands running other commands when something happens, with the options holding those commands.
+const LLDB_HOOKS: &[&str] = &[
+    "breakpoint command add",
+    "watchpoint command add",
+    "target stop-hook add",
+];
+
+/// LLDB commands registering Python code, with the options naming it, as in `type summary add --python-script`.
+/// The hooks in [`LLDB_HOOKS`] also run Python code given a `-s`/`--script-type` other than `command`.
+const LLDB_SCRIPTED: &[(&str, &[&str])] = &[
+    ("breakpoint command add", &["-F", "--python-function"]),
+    ("watchpoint command add", &["-F", "--python-function"]),
+    ("target stop-hook add", &["-P", "--python-class"]),
+    (
+        "type summary add",
+        &["-o", "--python-script", "-F", "--python-function"],
+    ),
+    ("type synthetic add", &["-l", "--python-class"]),
+];
+
+/// Commands nested in a LLDB command: the `-o`/`--one-liner` commands of breakpoint, watchpoint and stop hooks.
+/// Commands registering Python code nest a `script` command, which is what they amount to.
+fn lldb_nested(line: &str) -> Vec<String> {
+    let arguments = lldb_arguments(line);
+    let words: Vec<String> = arguments.iter().map(|word| word.to_lowercase()).collect();
+    // Values of the given options. Long options may be abbreviated, as LLDB accepts unique prefixes.
+    let options = |names: &[&str]| -> Vec<String> {
+        let mut values = Vec::new();
+        let mut arguments = arguments.iter();
+        while let Some(argument) = arguments.next() {
+            let (name, value) = match argument.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+                _ if argument.starts_with("--") || argument.len() == 2 => (argument.as_str(), None),
+                // A short option with its value attached, as in `-spython`.
+                _ if argument.starts_with('-') && argument.is_char_boundary(2) => {
+                    (&argument[..2], Some(argument[2..].to_owned()))
+                }
+                _ => continue,
+            };
+            let is_option = |option: &&str| {
+                *option == name
+                    || (name.len() > 2 && name.starts_with("--") && option.starts_with(name))
+            };
+            if names.iter().any(is_option) {
+                values.extend(value.or_else(|| arguments.next().cloned()));
+            }
+        }
+        values
+    };
+    let hook = LLDB_HOOKS
+        .iter()
+        .any(|command| starts_with_command(&words, command));
+    let scripted = LLDB_SCRIPTED.iter().any(|(command, script_options)| {
+        starts_with_command(&words, command) && !options(script_options).is_empty()
+    }) || (hook
+        && options(&["-s", "--script-type"])
+            .iter()
+            .any(|language| !language.eq_ignore_ascii_case("command")));
+    let mut nested = Vec::new();
+    if scripted {
+        nested.push("script".to_owned());
+    }
+    if hook {
+        nested.extend(options(&["-o", "--one-liner"]));
+    }
+    nested
+}
+
+fn split_words(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Expand a leading shortcut character (such as `!cmd`) into a verb followed by the remaining words.
+fn expand_shortcut(line: &str, shortcuts: &[(char, &str)]) -> Option<Vec<String>> {
+    let line = line.trim_start();
+    let first = line.chars().next()?;
+    let (_, verb) = shortcuts.iter().find(|(shortcut, _)| *shortcut == first)?;
+    let mut words = vec![verb.to_string()];
+    words.extend(split_words(&line[first.len_utf8()..]));
+    Some(words)
+}
+
+/// Strip the numeric token of an MI command (as in `12-exec-run`) and unwrap console commands wrapped in
+/// `-interpreter-exec console "..."`, which are classified as the console command.
+fn gdb_unwrap(line: &str) -> String {
+    let line = line
+        .trim_start()
+        .trim_start_matches(|c: char| c.is_ascii_digit());
+    if let Some(mi_command) = line.strip_prefix('-')
+        && split_words(mi_command)
+            .first()
+            .is_some_and(|verb| verb == "interpreter-exec")
+        && let Some((_, quoted)) = mi_command.split_once('"')
+    {
+        let inner = quoted
+            .strip_suffix('"')
+            .unwrap_or(quoted)
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+        return gdb_unwrap(&inner);
+    }
+    line.to_owned()
+}
+
+fn gdb_words(line: &str) -> Vec<String> {
+    let line = gdb_unwrap(line);
+    let line = line.as_str();
+    if let Some(mi_command) = line.strip_prefix('-') {
+        return split_pipe_target(
+            split_words(mi_command)
+                .into_iter()
+                .enumerate()
+                .map(|(index, word)| {
+                    if index == 0 {
+                        format!("-{}", word)
+                    } else {
+                        word
+                    }
+                })
+                .collect(),
+        );
+    }
+    if let Some(words) = expand_shortcut(line, &[('!', "shell"), ('|', "pipe")]) {
+        return words;
+    }
+    let mut words = split_words(line);
+    if let Some(verb) = words.first_mut()
+        && matches!(verb.as_str(), "pi" | "python-interactive")
+    {
+        *verb = "python".to_owned();
+    }
+    split_pipe_target(words)
+}
+
+/// Make the `|` of a pipe target, as in `target remote |gdbserver - prog`, a word of its own so that rules can
+/// match it. GDB runs the rest of the target as a shell command.
+fn split_pipe_target(mut words: Vec<String>) -> Vec<String> {
+    if let Some(target) = words.get(2)
+        && let Some(command) = target.trim_start_matches('"').strip_prefix('|')
+    {
+        let command = command.to_owned();
+        words[2] = "|".to_owned();
+        if !command.is_empty() {
+            words.insert(3, command);
+        }
+    }
+    words
+}
+
+/// Commands that load files, with their options taking a path. The first argument that is not an option is a path too.
+const PATH_COMMANDS: &[(Backend, &str, &[&str])] = &[
+    (Backend::Gdb, "file", &[]),
+    (Backend::Gdb, "exec-file", &[]),
+    (Backend::Gdb, "symbol-file", &[]),
+    (Backend::Gdb, "add-symbol-file", &[]),
+    (Backend::Gdb, "core-file", &[]),
+    (Backend::Gdb, "target exec", &[]),
+    (Backend::Gdb, "target core", &[]),
+    (Backend::Gdb, "source", &[]),
+    (Backend::Gdb, "-file-exec-and-symbols", &[]),
+    (Backend::Gdb, "-file-exec-file", &[]),
+    (Backend::Gdb, "-file-symbol-file", &[]),
+    (Backend::Gdb, "-target-select exec", &[]),
+    (Backend::Gdb, "-target-select core", &[]),
+    (Backend::Lldb, "file", &["-c", "--core", "-s", "--symfile"]),
+    (
+        Backend::Lldb,
+        "target create",
+        &["-c", "--core", "-s", "--symfile"],
+    ),
+    (Backend::Lldb, "target symbols add", &[]),
+    (Backend::Lldb, "command source", &[]),
+];
+
+/// File paths that a command loads, such as programs, core files and scripts.
+pub fn path_arguments(backend: Backend, command: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for line in command.lines().flat_map(|line| commands(backend, line)) {
+        let words = split_command(backend, &line);
+        let Some((_, verb, path_options)) =
+            PATH_COMMANDS.iter().find(|(command_backend, verb, _)| {
+                *command_backend == backend && matches(verb, &words)
+            })
+        else {
+            continue;
+        };
+        // Paths are quoted by the tools as c-strings, which both debuggers understand.
+        let arguments = mi::arguments(&line)
+            .unwrap_or_else(|_| line.split_whitespace().map(str::to_owned).collect());
+        let mut arguments = arguments
+            .iter()
+            .skip(verb.split_whitespace().count())
+            .map(|argument| argument.trim_matches(|c| c == '"' || c == '\''));
+        let mut positional = true;
+        while let Some(argument) = arguments.next() {
+            if path_options.contains(&argument) {
+                paths.extend(arguments.next().map(str::to_owned));
+            } else if positional && !argument.starts_with('-') {
+                paths.push(argument.to_owned());
+                positional = false;
+            }
+        }
+    }
+    paths
+}
+
+/// Commands that change the working directory of the debugger, which relative paths are resolved against.
+const DIRECTORY_COMMANDS: &[(Backend, &str)] =
+    &[(Backend::Gdb, "cd"), (Backend::Gdb, "-environment-cd")];
+
+/// The first line of a command that changes the working directory of the debugger.
+pub fn directory_change(backend: Backend, command: &str) -> Option<String> {
+    command
+        .lines()
+        .find(|line| {
+            command_words(backend, line).iter().any(|words| {
+                DIRECTORY_COMMANDS.iter().any(|(command_backend, verb)| {
+                    *command_backend == backend && matches(verb, words)
+                })
+            })
+        })
+        .map(|line| line.trim().to_owned())
+}
+
+/// Commands understood by pdb. Anything else is executed as a Python statement.
+const PDB_COMMANDS: &[&str] = &[
+    "h",
+    "help",
+    "w",
+    "where",
+    "bt",
+    "d",
+    "down",
+    "u",
+    "up",
+    "b",
+    "break",
+    "tbreak",
+    "cl",
+    "clear",
+    "disable",
+    "enable",
+    "ignore",
+    "condition",
+    "commands",
+    "s",
+    "step",
+    "n",
+    "next",
+    "unt",
+    "until",
+    "j",
+    "jump",
+    "r",
+    "return",
+    "c",
+    "cont",
+    "continue",
+    "l",
+    "list",
+    "ll",
+    "longlist",
+    "a",
+    "args",
+    "p",
+    "pp",
+    "whatis",
+    "source",
+    "display",
+    "undisplay",
+    "interact",
+    "alias",
+    "unalias",
+    "q",
+    "quit",
+    "exit",
+    "run",
+    "restart",
+    "debug",
+    "retval",
+    "rv",
+    "exceptions",
+];
+
+fn pdb_words(line: &str) -> Vec<String> {
+    if let Some(words) = expand_shortcut(line, &[('!', "!")]) {
+        return words;
+    }
+    let words = split_words(line);
+    match words.first() {
+        Some(verb) if !PDB_COMMANDS.contains(&verb.as_str()) => {
+            // Python statement executed in the frame, same as `!statement`.
+            std::iter::once("!".to_owned()).chain(words).collect()
+        }
+        _ => words,
+    }
+}
+
+/// Arguments of a LLDB command as LLDB splits them: at whitespace, with quotes removed and backslash escapes
+/// resolved outside single quotes.
+pub(crate) fn lldb_arguments(text: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut argument: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => arguments.extend(argument.take()),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                argument.get_or_insert_default();
+            }
+            (Some(open), c) if c == open => quote = None,
+            (Some('\''), c) => argument.get_or_insert_default().push(c),
+            (_, '\\') => argument.get_or_insert_default().extend(chars.next()),
+            (_, c) => argument.get_or_insert_default().push(c),
+        }
+    }
+    arguments.extend(argument);
+    arguments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(backend: Backend, line: &str) -> Vec<Vec<String>> {
+        command_words(backend, line)
+    }
+
+    fn rule(text: &str) -> String {
+        text.to_owned()
+    }
+
+    #[test]
+    fn gdb_words_expand_shortcuts_and_unwrap_mi() {
+        assert_eq!(
+            words(Backend::Gdb, "!ls -l"),
+            vec![vec!["shell", "ls", "-l"]]
+        );
+        assert_eq!(
+            words(Backend::Gdb, "|echo x"),
+            vec![vec!["pipe", "echo", "x"]]
+        );
+        assert_eq!(words(Backend::Gdb, "pi 1"), vec![vec!["python", "1"]]);
+        assert_eq!(words(Backend::Gdb, "12-exec-run"), vec![vec!["-exec-run"]]);
+        assert_eq!(
+            words(
+                Backend::Gdb,
+                "-interpreter-exec console \"shell echo \\\"hi\\\"\""
+            ),
+            vec![vec!["shell", "echo", "\"hi\""]]
+        );
+        assert_eq!(words(Backend::Gdb, "Shell LS"), vec![vec!["shell", "ls"]]);
+    }
+
+    #[test]
+    fn pipe_targets_are_split() {
+        assert_eq!(
+            words(Backend::Gdb, "target remote |gdbserver - prog"),
+            vec![vec!["target", "remote", "|", "gdbserver", "-", "prog"]]
+        );
+        assert_eq!(
+            words(Backend::Gdb, "-target-select remote \"|nc host 1\"")[0][..3],
+            ["-target-select", "remote", "|"]
+        );
+        assert_eq!(
+            words(Backend::Gdb, "target remote | nc host 1"),
+            vec![vec!["target", "remote", "|", "nc", "host", "1"]]
+        );
+        assert_eq!(
+            words(Backend::Gdb, "target remote localhost:1234"),
+            vec![vec!["target", "remote", "localhost:1234"]]
+        );
+    }
+
+    #[test]
+    fn pdb_statements_are_python() {
+        assert_eq!(
+            words(Backend::Pdb, "p x;;import os"),
+            vec![vec!["p", "x"], vec!["!", "import", "os"]]
+        );
+        assert_eq!(
+            words(Backend::Pdb, "!x = 1"),
+            vec![vec!["!", "x", "=", "1"]]
+        );
+        assert_eq!(words(Backend::Pdb, "next"), vec![vec!["next"]]);
+    }
+
+    #[test]
+    fn rules_match_abbreviated_words() {
+        let command = |line: &str| split_words(line);
+        assert!(matches(&rule("shell"), &command("she ls")));
+        assert!(!matches(&rule("shell"), &command("s ls")));
+        assert!(matches(&rule("platform shell"), &command("platform sh ls")));
+        assert!(!matches(&rule("platform shell"), &command("platform")));
+        assert!(!matches(&rule("shell"), &command("shellx")));
+        assert!(!matches(&rule(""), &command("anything")));
+        assert!(!matches(
+            &rule("target remote |"),
+            &command("target remote host:1")
+        ));
+    }
+
+    #[test]
+    fn default_policy_denies_host_commands() {
+        let policy = Policy::default();
+        for (backend, command) in [
+            (Backend::Gdb, "shell id"),
+            (Backend::Gdb, "!id"),
+            (
+                Backend::Gdb,
+                "-interpreter-exec console \"python import os\"",
+            ),
+            (Backend::Gdb, "bt\ndump memory /tmp/x 0 1"),
+            (Backend::Gdb, "target remote |sh -c id"),
+            (Backend::Gdb, "-target-select extended-remote \"| sh\""),
+            (Backend::Lldb, "platform shell id"),
+            (Backend::Lldb, "script print(1)"),
+            (Backend::Pdb, "import os"),
+            (Backend::Pdb, "p 1;;!import os"),
+        ] {
+            assert!(
+                policy.check(backend, command, false).is_err(),
+                "{}",
+                command
+            );
+        }
+        for (backend, command) in [
+            (Backend::Gdb, "bt"),
+            (Backend::Gdb, "target remote localhost:1234"),
+            (Backend::Lldb, "frame variable"),
+            (Backend::Pdb, "p x"),
+        ] {
+            assert_eq!(
+                policy.check(backend, command, false).unwrap(),
+                Decision::Allow,
+                "{}",
+                command
+            );
+        }
+    }
+
+    fn assert_denied(backend: Backend, commands: &[&str]) {
+        let policy = Policy::default();
+        for command in commands {
+            assert!(
+                policy.check(backend, command, false).is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+
+    fn assert_allowed(backend: Backend, commands: &[&str]) {
+        let policy = Policy::default();
+        for command in commands {
+            assert_eq!(
+                policy.check(backend, command, false).unwrap(),
+                Decision::Allow,
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn gdb_thread_apply_is_unwrapped() {
+        assert_denied(
+            Backend::Gdb,
+            &[
+                "thread apply all shell id",
+                "thread apply 1 2-3 $sel -q shell id",
+                "thr app all -s !id",
+                "-interpreter-exec console \"thread apply all shell id\"",
+            ],
+        );
+        assert_allowed(
+            Backend::Gdb,
+            &["thread apply all bt", "thread apply 1 -q p x"],
+        );
+    }
+
+    #[test]
+    fn gdb_frame_apply_is_unwrapped() {
+        assert_denied(
+            Backend::Gdb,
+            &[
+                "frame apply all shell id",
+                "frame apply 3 -q shell id",
+                "frame apply level 1-2 python print(1)",
+            ],
+        );
+        assert_allowed(Backend::Gdb, &["frame apply all p $pc"]);
+    }
+
+    #[test]
+    fn gdb_thread_and_frame_shortcuts_are_unwrapped() {
+        assert_denied(
+            Backend::Gdb,
+            &["taas shell id", "faas -q shell id", "tfaas shell id"],
+        );
+        assert_allowed(Backend::Gdb, &["tfaas p x"]);
+    }
+
+    #[test]
+    fn gdb_with_is_unwrapped() {
+        assert_denied(
+            Backend::Gdb,
+            &["with print pretty -- shell id", "w print elements 4 -- !id"],
+        );
+        assert_allowed(
+            Backend::Gdb,
+            &["with print pretty -- p x", "with print pretty on"],
+        );
+    }
+
+    #[test]
+    fn gdb_break_commands_are_unwrapped() {
+        assert_denied(
+            Backend::Gdb,
+            &[
+                "-break-commands 1 \"shell id\"",
+                "12-break-commands 1 \"bt\" \"python print(1)\"",
+            ],
+        );
+        assert_allowed(Backend::Gdb, &["-break-commands 1 \"bt\" \"continue\""]);
+    }
+
+    #[test]
+    fn gdb_host_file_writes_are_denied() {
+        assert_denied(
+            Backend::Gdb,
+            &[
+                "set logging file /tmp/out",
+                "set logging on",
+                "-gdb-set logging file /tmp/out",
+                "set history filename /tmp/out",
+                "gcore /tmp/core",
+                "generate-core-file",
+                "save breakpoints /tmp/bps",
+                "append memory /tmp/out 0 1",
+            ],
+        );
+    }
+
+    #[test]
+    fn lldb_breakpoint_commands_are_unwrapped() {
+        assert_denied(
+            Backend::Lldb,
+            &[
+                "breakpoint command add -o \"platform shell id\" 1",
+                "br command add --one-liner=\"script print(1)\" 1",
+                "breakpoint command add --one \"shell id\" 1",
+                "breakpoint command add -s python -o \"print(1)\" 1",
+                "breakpoint command add --script-type=python 1",
+                "breakpoint command add -F module.function 1",
+            ],
+        );
+        assert_allowed(
+            Backend::Lldb,
+            &[
+                "breakpoint command add -o bt 1",
+                "breakpoint command add -s command -o \"frame variable\" 1",
+            ],
+        );
+    }
+
+    #[test]
+    fn lldb_watchpoint_commands_are_unwrapped() {
+        assert_denied(
+            Backend::Lldb,
+            &[
+                "watchpoint command add -o \"shell id\" 1",
+                "watchpoint command add -s python 1",
+                "watchpoint command add -slua 1",
+            ],
+        );
+        assert_allowed(Backend::Lldb, &["watchpoint command add -o bt 1"]);
+    }
+
+    #[test]
+    fn lldb_stop_hooks_are_unwrapped() {
+        assert_denied(
+            Backend::Lldb,
+            &[
+                "target stop-hook add -o \"platform shell id\"",
+                "target stop-hook add -o'script print(1)'",
+                "target stop-hook add -P module.Hook",
+            ],
+        );
+        assert_allowed(Backend::Lldb, &["target stop-hook add -o \"bt\""]);
+    }
+
+    #[test]
+    fn lldb_python_formatters_are_scripts() {
+        assert_denied(
+            Backend::Lldb,
+            &[
+                "type summary add --python-script \"import os\" Point",
+                "type summary add -F module.summary Point",
+                "type synthetic add -l module.Provider Point",
+            ],
+        );
+        assert_allowed(
+            Backend::Lldb,
+            &["type summary add --summary-string \"${var.x}\" Point"],
+        );
+    }
+
+    #[test]
+    fn lldb_arguments_are_unquoted() {
+        assert_eq!(
+            lldb_arguments(r#"memory read "a b" 'c\d' e\ f """#),
+            vec!["memory", "read", "a b", "c\\d", "e f", ""]
+        );
+    }
+
+    #[test]
+    fn allow_and_approve_rules() {
+        let mut policy = Policy::default();
+        policy.gdb.allow = vec![rule("bt"), rule("info"), rule("run")];
+        policy.gdb.approve = vec![rule("run")];
+        assert_eq!(
+            policy.check(Backend::Gdb, "bt", false).unwrap(),
+            Decision::Allow
+        );
+        assert_eq!(
+            policy.check(Backend::Gdb, "bt\nrun", false).unwrap(),
+            Decision::NeedsApproval(rule("run"))
+        );
+        let violation = policy.check(Backend::Gdb, "next", false).unwrap_err();
+        assert_eq!(violation.rule, "not in allow list");
+        assert_eq!(violation.command, "next");
+        assert!(policy.check(Backend::Gdb, "run", true).is_err());
+    }
+
+    #[test]
+    fn paths_of_loading_commands() {
+        assert_eq!(
+            path_arguments(Backend::Gdb, "file \"/tmp/scope dir/prog\""),
+            vec!["/tmp/scope dir/prog"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Gdb, "bt\ntarget core /tmp/core\nsource x.gdb"),
+            vec!["/tmp/core", "x.gdb"]
+        );
+        assert_eq!(
+            path_arguments(
+                Backend::Gdb,
+                "-interpreter-exec console \"core-file /tmp/core\""
+            ),
+            vec!["/tmp/core"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Gdb, "-file-exec-and-symbols /bin/ls"),
+            vec!["/bin/ls"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Lldb, "target create -c /tmp/core /bin/prog"),
+            vec!["/tmp/core", "/bin/prog"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Lldb, "file --symfile /tmp/a.debug a.out"),
+            vec!["/tmp/a.debug", "a.out"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Lldb, "command source '/tmp/cmds'"),
+            vec!["/tmp/cmds"]
+        );
+        assert_eq!(
+            path_arguments(Backend::Gdb, "thread apply all source /tmp/cmds"),
+            vec!["/tmp/cmds"]
+        );
+        assert!(path_arguments(Backend::Gdb, "print file").is_empty());
+        assert!(path_arguments(Backend::Pdb, "source x").is_empty());
+    }
+
+    #[test]
+    fn directory_changes_are_found() {
+        assert_eq!(
+            directory_change(Backend::Gdb, "bt\n  cd /tmp  "),
+            Some(rule("cd /tmp"))
+        );
+        assert_eq!(
+            directory_change(Backend::Gdb, "-environment-cd /"),
+            Some(rule("-environment-cd /"))
+        );
+        assert_eq!(directory_change(Backend::Gdb, "print cd"), None);
+        assert_eq!(directory_change(Backend::Lldb, "cd /tmp"), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Running processes, read from `/proc`, for attaching debuggers to them.
+//!
+//! Attaching fails with a terse `Operation not permitted` for several unrelated reasons: the Yama `ptrace_scope`
+//! setting, a missing `CAP_SYS_PTRACE` capability, a process of another user, or a process that is already traced.
+//! [`explain_attach_failure`] tells them apart, so the client learns what to change instead of seeing the raw error.
+use std::collections::HashMap;
+use std::fs;
+
+use serde_json::{Value, json};
+
+use crate::mi;
+
+/// Bit of `CAP_SYS_PTRACE` in the capability sets of `/proc/PID/status`.
+const CAP_SYS_PTRACE: u32 = 19;
+
+/// A running process.
+pub struct Process {
+    pub pid: u32,
+    /// Name of the executable, as in `/proc/PID/comm`.
+    pub name: String,
+    /// Command line, empty for kernel threads.
+    pub command: String,
+    pub uid: Option<u32>,
+    /// Process tracing this one, such as an attached debugger.
+    pub tracer: Option<u32>,
+}
+
+impl Process {
+    /// Read a process from `/proc`. `None` if it does not exist (any more).
+    pub fn read(pid: u32) -> Option<Process> {
+        let name = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+        let command = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+        let command = String::from_utf8_lossy(&command)
+            .split('\0')
+            .filter(|arg| !arg.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+        Some(Process {
+            pid,
+            name: name.trim_end().to_owned(),
+            command,
+            uid: status_field(&status, "Uid"),
+            tracer: status_field(&status, "TracerPid").filter(|tracer| *tracer != 0),
+        })
+    }
+
+    /// Whether this process is the one the client means by `name`: its executable name, or the file name of the
+    /// program it runs, as `comm` is cut to 15 characters.
+    fn is_named(&self, name: &str) -> bool {
+        let program = self.command.split(' ').next().unwrap_or_default();
+        self.name == name || program.rsplit('/').next() == Some(name)
+    }
+
+    fn to_json(&self, users: &HashMap<u32, String>) -> Value {
+        mi::compact(json!({
+            "pid": self.pid,
+            "name": self.name,
+            "user": self.uid.map(|uid| users.get(&uid).cloned().unwrap_or(uid.to_string())),
+            "command": self.command,
+            "traced_by": self.tracer,
+        }))
+    }
+}
+
+/// First number of a field of `/proc/PID/status`, such as the real user ID in `Uid:`.
+fn status_field(status: &str, field: &str) -> Option<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Processes of the user running the server, without kernel threads, in PID order. The server itself is left out.
+/// Other users' processes are not listed, their command lines may hold secrets and they cannot be attached to anyway
+/// without `CAP_SYS_PTRACE`.
+pub fn processes() -> Vec<Process> {
+    let own = std::process::id();
+    let user = unsafe { libc::geteuid() };
+    let mut pids: Vec<u32> = fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .filter(|pid| *pid != own)
+        .collect();
+    pids.sort_unstable();
+    pids.into_iter()
+        .filter_map(Process::read)
+        .filter(|process| !process.command.is_empty() && process.uid == Some(user))
+        .collect()
+}
+
+/// The processes as JSON, those whose name or command line contains `filter` if given.
+pub fn list_json(filter: Option<&str>) -> Value {
+    let users = users();
+    let listed: Vec<Value> = processes()
+        .iter()
+        .filter(|process| {
+            filter.is_none_or(|filter| {
+                process.name.contains(filter) || process.command.contains(filter)
+            })
+        })
+        .map(|process| process.to_json(&users))
+        .collect();
+    json!({ "processes": listed })
+}
+
+/// The process to attach to, by PID or by name. A name must match exactly one process.
+pub fn find(pid: Option<u32>, name: Option<&str>) -> Result<Process, String> {
+    match (pid, name) {
+        (Some(pid), None) => Process::read(pid).ok_or(format!("No process with PID {}", pid)),
+        (None, Some(name)) => {
+            let mut matching: Vec<Process> = processes()
+                .into_iter()
+                .filter(|process| process.is_named(name))
+                .collect();
+            match matching.len() {
+                0 => Err(format!(
+                    "No process named '{}'. List the running processes to find it",
+                    name
+                )),
+                1 => Ok(matching.remove(0)),
+                _ => Err(format!(
+                    "Several processes are named '{}', attach by PID instead: {}",
+                    name,
+                    matching
+                        .iter()
+                        .map(|process| format!("{} ({})", process.pid, process.command))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            }
+        }
+        _ => Err("Give either the PID or the name of the process".to_owned()),
+    }
+}
+
+/// User names by user ID, from `/etc/passwd`.
+fn users() -> HashMap<u32, String> {
+    fs::read_to_string("/etc/passwd")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_owned()))
+        })
+        .collect()
+}
+
+/// Why attaching `debugger` to `process` failed, if the debugger output says it was not permitted.
+pub fn explain_attach_failure(debugger: &str, process: &Process, output: &str) -> Option<String> {
+    // lldb-server exits when it is denied, which LLDB reports as a lost connection.
+    let denied = ["not permitted", "attach failed: lost connection"]
+        .iter()
+        .any(|message| output.contains(message));
+    if !denied {
+        return None;
+    }
+    let own_status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let own_uid = status_field(&own_status, "Uid");
+    let capable = own_status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+        .is_some_and(|caps| caps & (1 << CAP_SYS_PTRACE) != 0);
+    let scope: Option<u32> = fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope")
+        .ok()
+        .and_then(|scope| scope.trim().parse().ok());
+    // LLDB on Linux attaches through lldb-server.
+    let tracer = if debugger == "lldb" {
+        "lldb-server"
+    } else {
+        debugger
+    };
+    let setcap = format!(
+        "give the debugger the CAP_SYS_PTRACE capability with `sudo setcap cap_sys_ptrace=eip $(command -v {})`",
+        tracer
+    );
+
+    if let Some(tracer) = process.tracer {
+        let name = Process::read(tracer)
+            .map(|tracer| format!(" ({})", tracer.name))
+            .unwrap_or_default();
+        return Some(format!(
+            "Process {} is already traced by process {}{}. Only one debugger can attach at a time: detach the other one first.",
+            process.pid, tracer, name
+        ));
+    }
+    if scope == Some(3) {
+        return Some(
+            "Attaching is disabled on this system (kernel.yama.ptrace_scope = 3), and stays disabled until a reboot. \
+             Set a lower value in /etc/sysctl.d/ and reboot, or debug the program by starting it in the debugger."
+                .to_owned(),
+        );
+    }
+    if !capable && process.uid.is_some() && own_uid != Some(0) && process.uid != own_uid {
+        return Some(format!(
+            "Process {} belongs to another user than the debugger. Run the server as the same user or as root, or {}.",
+            process.pid, setcap
+        ));
+    }
+    match scope {
+        Some(1) if !capable => Some(format!(
+            "The kernel only lets processes trace their own descendants (kernel.yama.ptrace_scope = 1). \
+             Allow attaching to any process of the same user with `sudo sysctl -w kernel.yama.ptrace_scope=0` \
+             (add it to /etc/sysctl.d/ to keep it after a reboot), or {}, or have the target allow the debugger \
+             with prctl(PR_SET_PTRACER, ...).",
+            setcap
+        )),
+        Some(2) if !capable => Some(format!(
+            "The kernel only lets processes with the CAP_SYS_PTRACE capability attach (kernel.yama.ptrace_scope = 2). \
+             Run the server as root, or {}.",
+            setcap
+        )),
+        _ => Some(
+            "The kernel denied tracing the process for another reason than Yama or the process owner. In a container, add the capability \
+             with `--cap-add=SYS_PTRACE` and use a seccomp profile that allows ptrace. Security modules such as \
+             SELinux or AppArmor can also deny it (see the `deny_ptrace` boolean and the audit log)."
+                .to_owned(),
+        ),
+    }
+}
@@ -0,0 +1,84 @@
+//! Progress notifications and cancellation of the tool call running in the current task.
+//!
+//! [`crate::server::DbgServer`] runs every tool call inside [`scope`], so code reading from a debugger can report
+//! progress and notice cancellation without the call being passed down to it.
+use std::future::Future;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use crate::client::Client;
+
+tokio::task_local! {
+    static CALL: ToolCall;
+}
+
+/// Number of the next tool call.
+static NEXT_CALL: AtomicU64 = AtomicU64::new(0);
+
+/// A tool call in flight.
+#[derive(Clone)]
+pub struct ToolCall {
+    /// Number telling the call apart from every other call of the server.
+    id: u64,
+    client: Client,
+    token: Option<Value>,
+    cancelled: CancellationToken,
+    progress: Arc<AtomicU32>,
+}
+
+impl ToolCall {
+    /// `token` is the progress token sent by the client, without one no progress is reported.
+    /// `cancelled` fires when the client cancels the call.
+    pub fn new(client: Client, token: Option<Value>, cancelled: CancellationToken) -> Self {
+        Self {
+            id: NEXT_CALL.fetch_add(1, Ordering::Relaxed),
+            client,
+            token,
+            cancelled,
+            progress: Arc::new(AtomicU32::new(0)),
+        }
+    }
+}
+
+/// Run a tool call with `call` as the current call.
+pub async fn scope<F: Future>(call: ToolCall, future: F) -> F::Output {
+    CALL.scope(call, future).await
+}
+
+/// Number of the current call, `None` outside of a tool call.
+pub fn current() -> Option<u64> {
+    CALL.try_with(|call| call.id).ok()
+}
+
+/// Tell the client how the current call is doing. Does nothing if the client did not ask for progress.
+pub fn report(message: String) {
+    let _ = CALL.try_with(|call| {
+        let Some(token) = &call.token else {
+            return;
+        };
+        let progress = call.progress.fetch_add(1, Ordering::SeqCst) + 1;
+        call.client.notify(
+            "notifications/progress",
+            json!({ "progressToken": token, "progress": progress, "message": message }),
+        );
+    });
+}
+
+/// Whether the client cancelled the current call.
+pub fn is_cancelled() -> bool {
+    CALL.try_with(|call| call.cancelled.is_cancelled())
+        .unwrap_or(false)
+}
+
+/// Completes when the client cancels the current call. Never completes outside of a tool call.
+pub async fn cancelled() {
+    match CALL.try_with(|call| call.cancelled.clone()) {
+        Ok(cancelled) => cancelled.cancelled().await,
+        Err(_) => std::future::pending().await,
+    }
+}
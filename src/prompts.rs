@@ -0,0 +1,209 @@
+//! MCP prompts for recurring debugging workflows, telling the model which tools to use and in which order.
+//!
+//! Templates are plain text with `{name}` placeholders for their arguments. Two more placeholders are filled in by
+//! the server: `{backend}`, the first served backend the template is meant for, and `{backends}`, all of them.
+//! Templates given in the configuration replace built-in templates of the same name.
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::server::Backend;
+
+/// An argument of a prompt template.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A prompt template.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptTemplate {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<TemplateArgument>,
+    /// Backends whose tools the template uses. The prompt is only offered if one of them is served.
+    /// An empty list means any backend.
+    #[serde(default)]
+    pub backends: Vec<Backend>,
+    pub text: String,
+}
+
+impl PromptTemplate {
+    /// Fill in the template with the arguments given by the client, `served` being the backends of the server.
+    pub fn render(
+        &self,
+        name: &str,
+        arguments: &Map<String, Value>,
+        served: &[Backend],
+    ) -> Result<String, String> {
+        let backends: Vec<&str> = served
+            .iter()
+            .filter(|backend| self.backends.is_empty() || self.backends.contains(backend))
+            .map(Backend::name)
+            .collect();
+        let mut text = self
+            .text
+            .replace("{backends}", &backends.join(", "))
+            .replace("{backend}", backends.first().copied().unwrap_or_default());
+        for argument in &self.arguments {
+            let value = match arguments.get(&argument.name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None if argument.required => {
+                    return Err(format!(
+                        "Missing required argument '{}' of prompt '{}'",
+                        argument.name, name
+                    ));
+                }
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            };
+            text = text.replace(&format!("{{{}}}", argument.name), &value);
+        }
+        Ok(text)
+    }
+
+    /// Whether the template can be used with the given served backends.
+    pub fn is_served(&self, served: &[Backend]) -> bool {
+        self.backends.is_empty() || self.backends.iter().any(|backend| served.contains(backend))
+    }
+}
+
+/// Prompt templates offered to clients.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prompts {
+    /// Offer the built-in templates.
+    pub builtin: bool,
+    /// Templates by prompt name. They replace built-in templates of the same name.
+    pub templates: BTreeMap<String, PromptTemplate>,
+}
+
+impl Default for Prompts {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            templates: BTreeMap::new(),
+        }
+    }
+}
+
+impl Prompts {
+    /// All templates offered with the given served backends, by prompt name.
+    pub fn templates(&self, served: &[Backend]) -> BTreeMap<String, PromptTemplate> {
+        let mut templates = if self.builtin {
+            builtin_templates()
+        } else {
+            BTreeMap::new()
+        };
+        templates.extend(self.templates.clone());
+        templates.retain(|_, template| template.is_served(served));
+        templates
+    }
+}
+
+fn argument(name: &str, description: &str, required: bool) -> TemplateArgument {
+    TemplateArgument {
+        name: name.to_owned(),
+        description: Some(description.to_owned()),
+        required,
+    }
+}
+
+fn builtin_templates() -> BTreeMap<String, PromptTemplate> {
+    let mut templates = BTreeMap::new();
+    templates.insert(
+        "triage_crash".to_owned(),
+        PromptTemplate {
+            description: Some("Find out why a native program crashes".to_owned()),
+            arguments: vec![
+                argument("program", "Path to the crashing program", true),
+                argument(
+                    "args",
+                    "Arguments that make the program crash, separated by spaces",
+                    false,
+                ),
+            ],
+            backends: vec![Backend::Gdb, Backend::Lldb],
+            text: "Triage the crash of the program `{program}`.
+Arguments that make it crash, if any: {args}
+
+1. Start a session with `{backend}_start`, then load the program with `{backend}_load`, passing the arguments.
+2. Run it with `{backend}_command` and the command `run`, then call `{backend}_wait` until the program stops.
+3. Note the signal or exception that stopped it. If the program exited normally, say so and stop here.
+4. Get the backtrace with `{backend}_command` and `bt`. Find the innermost frame in the program's own code.
+5. Select that frame (`frame N` in GDB, `frame select N` in LLDB) and print the local variables (`info locals` in GDB, `frame variable` in LLDB) \
+and the pointers and indices used on the faulting line (`p <expr>`).
+6. Terminate the session with `{backend}_terminate`.
+
+Report the signal, the faulting function and line, the values that explain the crash, and a likely fix."
+                .to_owned(),
+        },
+    );
+    templates.insert(
+        "bisect_value".to_owned(),
+        PromptTemplate {
+            description: Some("Find where an expression gets a wrong value".to_owned()),
+            arguments: vec![
+                argument("expr", "Expression whose value is wrong", true),
+                argument("program", "Path to the program or Python script", false),
+                argument(
+                    "location",
+                    "Where the value is known to be wrong, such as file:line",
+                    false,
+                ),
+            ],
+            backends: Vec::new(),
+            text: "Find the first point where the expression `{expr}` gets a wrong value.
+Program, if given: {program}
+Where the value is known to be wrong, if given: {location}
+
+Use the tools of the debugger matching the program, among {backends}: `<debugger>_start` to start a session, \
+`<debugger>_command` to run debugger commands, and `<debugger>_terminate` when done.
+
+1. Set a breakpoint where the value is known to be wrong, or where the wrong value shows, and run to it. \
+Print `{expr}` to confirm the wrong value.
+2. Get the backtrace and pick a point earlier in the execution where the value should already be right.
+3. Restart the program with a breakpoint at that point and print `{expr}` there.
+4. Bisect: keep moving the breakpoint to the middle of the range between the last point with a right value and \
+the first point with a wrong one, until both are adjacent statements. In GDB and LLDB a watchpoint on the \
+underlying variable (`watch` or `watchpoint set variable`) can find the writing statement directly.
+5. Terminate the session.
+
+Report the statement that produces the wrong value, the values of its inputs, and why the result is wrong."
+                .to_owned(),
+        },
+    );
+    templates.insert(
+        "debug_python_test".to_owned(),
+        PromptTemplate {
+            description: Some("Find out why a Python test fails".to_owned()),
+            arguments: vec![
+                argument("test", "Path to the test file", true),
+                argument("name", "Name of the failing test function", false),
+            ],
+            backends: vec![Backend::Pdb],
+            text: "Find out why a Python test in `{test}` fails.
+Name of the failing test, if given: {name}
+
+1. Start a session with `pdb_start` on `{test}`. The file must run its tests when executed, as with \
+`unittest.main()`; otherwise pass the test runner's arguments.
+2. Set a breakpoint on the failing test function with `pdb_command` and `break {test}:<line>`, then `continue`.
+3. Step through the test with `next` and `step`, printing the values involved in the failing assertion with \
+`p` and `pp`.
+4. When an exception is raised, use `where`, `up` and `down` to inspect the frames that led to it.
+5. Terminate the session with `pdb_terminate`.
+
+Report whether the test or the code under test is wrong, the values that show it, and a fix."
+                .to_owned(),
+        },
+    );
+    templates
+}
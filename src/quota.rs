@@ -0,0 +1,227 @@
+//! Quotas that keep a runaway client from piling up debugger processes: concurrent sessions, command rate, CPU time
+//! and output of each session.
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::{CLIDebugSession, progress};
+
+/// Window the command rate is measured over.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Configurable quotas. A missing value means no limit.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quotas {
+    /// Sessions alive at the same time across all clients and backends. `--max-sessions` takes precedence.
+    pub max_sessions: Option<usize>,
+    /// Sessions alive at the same time per client namespace.
+    pub max_sessions_per_client: Option<usize>,
+    /// Tool calls a session accepts within any 60 second window, however many debugger commands each runs.
+    pub commands_per_minute: Option<usize>,
+    /// CPU time used by a session's debugger, every program it runs and the stub spawned for it, in seconds. Only
+    /// enforced on Linux.
+    pub cpu_seconds: Option<u64>,
+    /// Bytes of output a session may produce.
+    pub output_bytes: Option<u64>,
+}
+
+/// A quota that was hit.
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    /// Name of the limit, as in the configuration.
+    pub limit: &'static str,
+    /// Configured value of the limit.
+    pub value: u64,
+    /// What was used.
+    pub used: String,
+    /// When, or under which condition, the limit allows more.
+    pub resets: String,
+}
+
+impl Display for QuotaExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Quota exceeded. [Limit]: {} = {} [Used]: {} [Resets]: {}",
+            self.limit, self.value, self.used, self.resets
+        )
+    }
+}
+
+impl From<QuotaExceeded> for String {
+    fn from(exceeded: QuotaExceeded) -> Self {
+        exceeded.to_string()
+    }
+}
+
+/// Resources used by one session so far.
+#[derive(Debug, Default)]
+pub struct Usage {
+    /// Times of the commands accepted within the last [`RATE_WINDOW`].
+    commands: VecDeque<Instant>,
+    /// Tool call admitted last, see [`progress::current`].
+    admitted_call: Option<u64>,
+}
+
+impl Usage {
+    /// Check the quotas of a session before it runs another command, and count the command if it may run.
+    /// A tool call running several commands is only checked and counted once. `stub` is the process of a stub
+    /// spawned for the session, whose CPU time counts too.
+    pub fn admit(
+        &mut self,
+        quotas: &Quotas,
+        session: &CLIDebugSession,
+        stub: Option<u32>,
+    ) -> Result<(), QuotaExceeded> {
+        let call = progress::current();
+        if call.is_some() && call == self.admitted_call {
+            return Ok(());
+        }
+        if let Some(max) = quotas.output_bytes
+            && session.output_bytes() >= max
+        {
+            return Err(QuotaExceeded {
+                limit: "output_bytes",
+                value: max,
+                used: format!("{} bytes", session.output_bytes()),
+                resets: "never, terminate the session and start a new one".to_owned(),
+            });
+        }
+        if let Some(max) = quotas.cpu_seconds
+            && let Some(used) = session
+                .pid()
+                .and_then(cpu_time)
+                .map(|used| used + stub.and_then(cpu_time).unwrap_or_default())
+            && used.as_secs() >= max
+        {
+            return Err(QuotaExceeded {
+                limit: "cpu_seconds",
+                value: max,
+                used: format!("{:.1} seconds", used.as_secs_f64()),
+                resets: "never, terminate the session and start a new one".to_owned(),
+            });
+        }
+        let Some(max) = quotas.commands_per_minute else {
+            self.admitted_call = call;
+            return Ok(());
+        };
+        let now = Instant::now();
+        while self
+            .commands
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= RATE_WINDOW)
+        {
+            self.commands.pop_front();
+        }
+        if let Some(oldest) = self.commands.front()
+            && self.commands.len() >= max
+        {
+            let wait = RATE_WINDOW - now.duration_since(*oldest);
+            let at = chrono::Utc::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+            return Err(QuotaExceeded {
+                limit: "commands_per_minute",
+                value: max as u64,
+                used: format!("{} commands in the last minute", self.commands.len()),
+                resets: format!(
+                    "in {} seconds, at {}",
+                    wait.as_secs_f64().ceil(),
+                    at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                ),
+            });
+        }
+        self.commands.push_back(now);
+        self.admitted_call = call;
+        Ok(())
+    }
+}
+
+/// CPU time used by a process and all its descendants, including descendants that already exited.
+#[cfg(target_os = "linux")]
+pub fn cpu_time(pid: u32) -> Option<Duration> {
+    let ticks = process_stat(pid)?.1
+        + descendants(pid)
+            .into_iter()
+            .filter_map(|child| Some(process_stat(child)?.1))
+            .sum::<u64>();
+    let per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    (per_second > 0).then(|| Duration::from_secs_f64(ticks as f64 / per_second as f64))
+}
+
+/// Parent and CPU ticks of a process, its own plus those of its reaped children.
+#[cfg(target_os = "linux")]
+fn process_stat(pid: u32) -> Option<(u32, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The fields after the command name, which is in parentheses and may contain anything, start with the state.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let parent = fields.get(1)?.parse().ok()?;
+    let ticks = (11..=14)
+        .map(|index| fields.get(index)?.parse::<u64>().ok())
+        .sum::<Option<u64>>()?;
+    Some((parent, ticks))
+}
+
+/// Running descendants of a process. They are found through the `children` files of their parents, or by reading
+/// every process on kernels without them.
+#[cfg(target_os = "linux")]
+fn descendants(pid: u32) -> Vec<u32> {
+    use std::collections::HashSet;
+
+    let children_of: Box<dyn Fn(u32) -> Vec<u32>> =
+        if std::path::Path::new(&format!("/proc/{0}/task/{0}/children", pid)).exists() {
+            Box::new(children)
+        } else {
+            let processes: Vec<(u32, u32)> = std::fs::read_dir("/proc")
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .filter_map(|process| Some((process, process_stat(process)?.0)))
+                .collect();
+            Box::new(move |parent| {
+                processes
+                    .iter()
+                    .filter(|(_, of)| *of == parent)
+                    .map(|(process, _)| *process)
+                    .collect()
+            })
+        };
+    // A process may exit and its ID be reused while the tree is read.
+    let mut seen = HashSet::from([pid]);
+    let mut descendants = Vec::new();
+    let mut pending = children_of(pid);
+    while let Some(child) = pending.pop() {
+        if seen.insert(child) {
+            descendants.push(child);
+            pending.extend(children_of(child));
+        }
+    }
+    descendants
+}
+
+/// Running children of a process, of any of its threads.
+#[cfg(target_os = "linux")]
+fn children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|child| child.parse().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// CPU time is not measured on this platform.
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_time(_pid: u32) -> Option<Duration> {
+    None
+}
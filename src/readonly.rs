@@ -0,0 +1,650 @@
+//! Classification of debugger commands for read-only inspection mode.
+//!
+//! In read-only mode only commands that look at the debugged program are allowed: backtraces, printing,
+//! memory reads, disassembly, source listing and selecting threads or frames. Anything that resumes, restarts
+//! or signals the program, writes memory or registers, or evaluates an expression with side effects is rejected.
+//! Commands are matched exactly (including their common abbreviations), so unknown commands are rejected as well.
+use crate::{mi, policy, server::Backend};
+
+/// GDB console commands that only inspect state. Commands taking an expression are listed in [`GDB_EXPRESSION`].
+#[rustfmt::skip]
+const GDB_INSPECT: &[&str] = &[
+    "backtrace", "bt", "where", "info", "i", "list", "l", "disassemble", "frame", "f", "up", "down",
+    "ptype", "whatis", "help", "h", "show", "pwd", "echo", "file", "core-file", "core",
+    "symbol-file", "sharedlibrary", "attach", "detach", "directory", "dir", "search",
+    "forward-search", "reverse-search", "thread", "select-frame",
+];
+
+/// GDB console commands whose arguments are evaluated as expressions.
+#[rustfmt::skip]
+const GDB_EXPRESSION: &[&str] = &[
+    "print", "p", "inspect", "output", "x", "printf", "display", "print-object", "po", "explore",
+];
+
+/// GDB `set` subcommands that only change how things are displayed or where symbols are found.
+#[rustfmt::skip]
+const GDB_SETTINGS: &[&str] = &[
+    "args", "print", "p", "pagination", "width", "height", "confirm", "listsize", "disassembly-flavor",
+    "sysroot", "solib-search-path", "solib-absolute-prefix", "substitute-path",
+    "debug-file-directory", "filename-display", "max-value-size", "style",
+];
+
+/// GDB/MI commands that only inspect state, matched by prefix.
+#[rustfmt::skip]
+const GDB_MI_INSPECT: &[&str] = &[
+    "-stack-", "-data-evaluate-expression", "-data-read-memory", "-data-disassemble", "-data-list-", "-thread-info",
+    "-thread-list-ids", "-thread-select", "-var-create", "-var-delete", "-var-set-format",
+    "-var-show-", "-var-info-", "-var-list-children", "-var-evaluate-expression", "-var-update",
+    "-break-list", "-symbol-", "-file-", "-gdb-show", "-gdb-version", "-info-", "-list-",
+    "-environment-pwd", "-target-attach", "-target-detach", "-trace-list",
+];
+
+/// LLDB commands that only inspect state, as sequences of command words.
+#[rustfmt::skip]
+const LLDB_INSPECT: &[&str] = &[
+    "bt", "thread backtrace", "thread list", "thread info", "thread select", "frame info",
+    "frame select", "frame variable", "fr v", "v", "var", "up", "down", "f", "memory read",
+    "me read", "mem read", "x", "disassemble", "di", "dis", "source list", "source info", "list",
+    "l", "register read", "re read", "reg read", "image list", "image lookup", "image dump",
+    "target list", "target modules list", "target modules lookup", "target create", "target select",
+    "file", "process status", "process attach", "attach", "detach", "help", "version",
+    "settings show", "type lookup", "breakpoint list", "br list", "watchpoint list",
+    "platform select", "settings append target.exec-search-paths",
+];
+
+/// LLDB commands that read memory, which can also write it to a file.
+const LLDB_MEMORY_READ: &[&str] = &["memory read", "me read", "mem read", "x"];
+
+/// LLDB commands with options taking an address expression.
+#[rustfmt::skip]
+const LLDB_ADDRESS: &[&str] = &[
+    "disassemble", "di", "dis", "image lookup", "target modules lookup", "source list", "list", "l",
+];
+
+/// Options of [`LLDB_ADDRESS`] commands whose value is an address expression.
+#[rustfmt::skip]
+const LLDB_ADDRESS_OPTIONS: &[&str] = &["-s", "--start-address", "-e", "--end-address", "-a", "--address"];
+
+/// LLDB commands whose arguments are evaluated as expressions.
+const LLDB_EXPRESSION: &[&str] = &["p", "print", "expression", "expr", "e", "dwim-print"];
+
+/// pdb commands that only inspect state.
+#[rustfmt::skip]
+const PDB_INSPECT: &[&str] = &[
+    "w", "where", "bt", "u", "up", "d", "down", "l", "list", "ll", "longlist", "a", "args", "h",
+    "help", "undisplay",
+];
+
+/// pdb commands whose arguments are evaluated as Python expressions.
+const PDB_EXPRESSION: &[&str] = &["p", "pp", "whatis", "display", "source"];
+
+/// Functions that can be called in expressions without side effects.
+#[rustfmt::skip]
+const PURE_FUNCTIONS: &[&str] = &[
+    // C and C++ operators that look like calls
+    "sizeof", "alignof", "_alignof", "typeof", "__typeof__", "decltype",
+    // GDB convenience functions
+    "$_streq", "$_strlen", "$_memeq", "$_regex", "$_as_string", "$_isvoid",
+    // Python builtins
+    "len", "repr", "str", "type", "id", "isinstance", "issubclass", "hex", "oct", "bin", "abs",
+    "int", "float", "bool", "vars", "dir", "hasattr",
+];
+
+/// Check the words of a single command, as produced by the policy parser.
+/// Returns the reason for rejecting the command, if any.
+pub(crate) fn check(backend: Backend, words: &[String]) -> Result<(), String> {
+    match backend {
+        Backend::Gdb => check_gdb(words),
+        Backend::Lldb => check_lldb(words),
+        Backend::Pdb => check_pdb(words),
+    }
+}
+
+fn rejected(words: &[String]) -> Result<(), String> {
+    Err(format!(
+        "read-only mode does not allow '{}'",
+        words.first().map(String::as_str).unwrap_or_default()
+    ))
+}
+
+fn check_expression(expression: &str) -> Result<(), String> {
+    if has_side_effects(expression) {
+        Err("read-only mode does not allow expressions with side effects".to_owned())
+    } else {
+        Ok(())
+    }
+}
+
+fn check_gdb(words: &[String]) -> Result<(), String> {
+    // Output formats are attached to the verb, as in `x/16xb` or `print/x`.
+    let Some(verb) = words.first().and_then(|verb| verb.split('/').next()) else {
+        return Ok(());
+    };
+    let rest = words[1..].join(" ");
+    if verb == "-gdb-set" {
+        return match words.get(1) {
+            Some(setting) if GDB_SETTINGS.contains(&setting.as_str()) => Ok(()),
+            _ => Err("read-only mode only allows display settings with '-gdb-set'".to_owned()),
+        };
+    }
+    if verb.starts_with('-') {
+        if !GDB_MI_INSPECT.iter().any(|prefix| verb.starts_with(prefix)) {
+            return rejected(words);
+        }
+        return match verb {
+            "-data-evaluate-expression" | "-data-read-memory" | "-data-read-memory-bytes" => {
+                check_mi_expression(&words[1..], 0)
+            }
+            // -var-create NAME FRAME EXPRESSION
+            "-var-create" => check_mi_expression(&words[1..], 2),
+            "-data-disassemble" => check_mi_options(&words[1..], &["-s", "-e", "-a"]),
+            _ => Ok(()),
+        };
+    }
+    match verb {
+        "set" => match words.get(1) {
+            Some(setting) if GDB_SETTINGS.contains(&setting.as_str()) => Ok(()),
+            _ => Err("read-only mode only allows display settings with 'set'".to_owned()),
+        },
+        "thread" if words.get(1).is_some_and(|word| word == "apply") => {
+            // thread apply all|ID... [FLAGS] COMMAND
+            let command: Vec<String> = words[2..]
+                .iter()
+                .skip_while(|word| {
+                    *word == "all"
+                        || word.starts_with('-')
+                        || word
+                            .chars()
+                            .all(|c| c.is_ascii_digit() || c == '.' || c == '-')
+                })
+                .cloned()
+                .collect();
+            check_gdb(&command)
+        }
+        "frame" | "f" if words.get(1).is_some_and(|word| word == "apply") => rejected(words),
+        _ if GDB_INSPECT.contains(&verb) => match gdb_address_operand(words) {
+            Some(operand) => check_expression(&operand),
+            None => Ok(()),
+        },
+        _ if GDB_EXPRESSION.contains(&verb) => check_expression(&rest),
+        _ => rejected(words),
+    }
+}
+
+/// The operand of a GDB console command that is evaluated as an address expression: the arguments of `disassemble`
+/// and `info symbol`, the stack address of `frame address`, and `*ADDRESS` locations as in `list *f` or `info line *f`.
+fn gdb_address_operand(words: &[String]) -> Option<String> {
+    let verb = words.first()?.split('/').next()?;
+    let is = |index: usize, name: &str| {
+        words
+            .get(index)
+            .is_some_and(|word| word.len() >= 3 && name.starts_with(word.as_str()))
+    };
+    let operand = if verb == "disassemble" {
+        &words[1..]
+    } else if matches!(verb, "info" | "i") && is(1, "symbol") {
+        &words[2..]
+    } else if let Some(index) = words.iter().position(|word| word.starts_with('*')) {
+        return Some(words[index..].join(" ")[1..].to_owned());
+    } else if (matches!(verb, "frame" | "f") && is(1, "address"))
+        || (matches!(verb, "info" | "i") && is(1, "frame") && is(2, "address"))
+    {
+        &words[words
+            .iter()
+            .position(|word| "address".starts_with(word.as_str()))?
+            + 1..]
+    } else {
+        return None;
+    };
+    // Modifiers such as `/r` are not part of the expression.
+    let operand: Vec<&str> = operand
+        .iter()
+        .map(String::as_str)
+        .filter(|word| !word.starts_with('/'))
+        .collect();
+    Some(operand.join(" "))
+}
+
+/// Arguments of an MI command with c-strings unquoted, as GDB passes each to the command separately, without the
+/// global `--thread` and `--frame` options.
+fn mi_arguments(words: &[String]) -> Result<Vec<String>, String> {
+    let mut words = words;
+    while let [option, _, rest @ ..] = words
+        && matches!(option.as_str(), "--thread" | "--frame")
+    {
+        words = rest;
+    }
+    mi::arguments(&words.join(" "))
+        .map_err(|err| format!("read-only mode could not check the expression: {}", err))
+}
+
+/// Check the expression of an MI command, after `skip` arguments that are not part of it. Arguments are checked one
+/// by one.
+fn check_mi_expression(words: &[String], skip: usize) -> Result<(), String> {
+    mi_arguments(words)?
+        .iter()
+        .skip(skip)
+        .try_for_each(|argument| check_expression(argument))
+}
+
+/// Check the values of the given options of an MI command, which are evaluated as expressions.
+fn check_mi_options(words: &[String], options: &[&str]) -> Result<(), String> {
+    mi_arguments(words)?
+        .windows(2)
+        .filter(|pair| options.contains(&pair[0].as_str()))
+        .try_for_each(|pair| check_expression(&pair[1]))
+}
+
+fn check_lldb(words: &[String]) -> Result<(), String> {
+    let Some(verb) = words.first().map(String::as_str) else {
+        return Ok(());
+    };
+    // LLDB replaces text in backticks with the value of the expression, in any command.
+    let text = words.join(" ");
+    for (index, expression) in text.split('`').enumerate() {
+        if index % 2 == 1 {
+            check_expression(expression)?;
+        }
+    }
+    if LLDB_EXPRESSION.contains(&verb) {
+        // Options come before a `--` separator, the expression after it.
+        let rest = &words[1..];
+        let expression = match rest.iter().position(|word| word == "--") {
+            Some(separator) => &rest[separator + 1..],
+            None => rest,
+        };
+        return check_expression(&expression.join(" "));
+    }
+    let starts_with = |command: &&str| {
+        let command: Vec<&str> = command.split(' ').collect();
+        command.len() <= words.len() && command.iter().zip(words).all(|(a, b)| a == b)
+    };
+    if !LLDB_INSPECT.iter().any(starts_with) {
+        return rejected(words);
+    }
+    let arguments = policy::lldb_arguments(&text);
+    if LLDB_MEMORY_READ.iter().any(starts_with) {
+        if words.iter().any(|word| is_outfile_option(word)) {
+            return Err("read-only mode does not allow writing memory to a file".to_owned());
+        }
+        // The start and end addresses are expressions. Values of other options are plain, checking them is harmless.
+        let separator = arguments.iter().position(|argument| argument == "--");
+        return arguments
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(index, argument)| {
+                separator.is_some_and(|separator| *index > separator) || !argument.starts_with('-')
+            })
+            .try_for_each(|(_, argument)| check_expression(argument));
+    }
+    if LLDB_ADDRESS.iter().any(starts_with) {
+        for (index, argument) in arguments.iter().enumerate() {
+            let value = match argument.split_once('=') {
+                Some((option, value)) if LLDB_ADDRESS_OPTIONS.contains(&option) => Some(value),
+                _ if LLDB_ADDRESS_OPTIONS.contains(&argument.as_str()) => {
+                    arguments.get(index + 1).map(String::as_str)
+                }
+                // A short option with its value attached, as in `-af`.
+                _ => LLDB_ADDRESS_OPTIONS
+                    .iter()
+                    .filter(|option| option.len() == 2)
+                    .find_map(|option| argument.strip_prefix(option))
+                    .filter(|value| !value.is_empty()),
+            };
+            if let Some(value) = value {
+                check_expression(value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a word is the `-o`/`--outfile` option of `memory read`, or `--append-outfile`, including abbreviations of
+/// the long options and short options grouped as in `-bo`.
+fn is_outfile_option(word: &str) -> bool {
+    match word.strip_prefix("--") {
+        Some(option) => {
+            let name = option.split('=').next().unwrap_or_default();
+            !name.is_empty() && ("outfile".starts_with(name) || "append-outfile".starts_with(name))
+        }
+        None => word.starts_with('-') && word.contains('o'),
+    }
+}
+
+fn check_pdb(words: &[String]) -> Result<(), String> {
+    let Some(verb) = words.first().map(String::as_str) else {
+        return Ok(());
+    };
+    match verb {
+        _ if PDB_INSPECT.contains(&verb) => Ok(()),
+        _ if PDB_EXPRESSION.contains(&verb) => check_expression(&words[1..].join(" ")),
+        _ => rejected(words),
+    }
+}
+
+/// Whether an expression may change program state: assignments, increments, decrements or function calls.
+/// This errs on the side of rejecting, for example a cast applied to a parenthesized operand looks like a call.
+fn has_side_effects(expression: &str) -> bool {
+    let chars: Vec<char> = expression.chars().collect();
+    // Identifier being read, and the identifier right before the current character (ignoring whitespace).
+    let mut current = String::new();
+    let mut preceding: Option<String> = None;
+    let mut preceding_char: Option<char> = None;
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            current.push(c);
+            continue;
+        }
+        if !current.is_empty() {
+            preceding = Some(std::mem::take(&mut current));
+            preceding_char = None;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let next = chars.get(index).copied();
+        match c {
+            '"' | '\'' => {
+                // Skip string and character literals.
+                while index < chars.len() && chars[index] != c {
+                    index += if chars[index] == '\\' { 2 } else { 1 };
+                }
+                index += 1;
+            }
+            '+' | '-' if next == Some(c) => return true,
+            '=' => {
+                let comparison =
+                    matches!(preceding_char, Some('=' | '!' | '<' | '>')) || next == Some('=');
+                let shift_assign = matches!(preceding_char, Some('<' | '>'))
+                    && index >= 3
+                    && chars[index - 3] == chars[index - 2];
+                if !comparison || shift_assign {
+                    return true;
+                }
+                if next == Some('=') {
+                    index += 1;
+                }
+            }
+            '(' => {
+                let call = match &preceding {
+                    Some(callee) => {
+                        !callee.starts_with(|c: char| c.is_ascii_digit())
+                            && !PURE_FUNCTIONS.contains(&callee.as_str())
+                    }
+                    None => match preceding_char {
+                        Some(']') => true,
+                        Some(')') => !follows_cast(&chars[..index - 1]),
+                        _ => false,
+                    },
+                };
+                if call {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        preceding = None;
+        preceding_char = Some(c);
+    }
+    false
+}
+
+/// Words that start a C type name in a cast.
+#[rustfmt::skip]
+const TYPE_WORDS: &[&str] = &[
+    "struct", "union", "enum", "const", "volatile", "unsigned", "signed", "char", "short", "int",
+    "long", "float", "double", "void", "bool", "_bool",
+];
+
+/// Whether the parenthesized group ending `chars` is a cast, as in `(int *)(ptr)`, rather than a callee.
+fn follows_cast(chars: &[char]) -> bool {
+    let Some(close) = chars.iter().rposition(|c| *c == ')') else {
+        return false;
+    };
+    let mut depth = 0;
+    let Some(open) = (0..close).rev().find(|&i| {
+        match chars[i] {
+            ')' => depth += 1,
+            '(' if depth == 0 => return true,
+            '(' => depth -= 1,
+            _ => {}
+        }
+        false
+    }) else {
+        return false;
+    };
+    let content: String = chars[open + 1..close].iter().collect();
+    let content = content.trim();
+    content.ends_with('*')
+        || content.ends_with('&')
+        || content.ends_with("_t")
+        || content
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| TYPE_WORDS.contains(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &str) -> Vec<String> {
+        command.split_whitespace().map(str::to_owned).collect()
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn reads_have_no_side_effects() {
+        for expression in [
+            "x",
+            "a == b",
+            "a != b && c <= d || e >= f",
+            "arr[i] + p->next->value",
+            "sizeof(struct node)",
+            "(int *)(ptr)",
+            "(struct node *)(base + 8)",
+            "(uint32_t)(x)",
+            "strlen != 0",
+            "\"a = b()\"",
+            "'='",
+            "0x10(",
+            "a << 2",
+        ] {
+            assert!(!has_side_effects(expression), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn writes_and_calls_have_side_effects() {
+        for expression in [
+            "x = 1",
+            "x += 1",
+            "x <<= 2",
+            "x >>= 2",
+            "i++",
+            "--i",
+            "free(p)",
+            "$rip = 0",
+            "table[0](1)",
+            "(get())(1)",
+            "(*fn)(1)",
+            "\"str\" + f(1)",
+            "s == \"\\\"\" && f()",
+        ] {
+            assert!(has_side_effects(expression), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn casts_are_told_from_callees() {
+        assert!(follows_cast(&chars("(int *)")));
+        assert!(follows_cast(&chars("(const char *)")));
+        assert!(follows_cast(&chars("(size_t)")));
+        assert!(follows_cast(&chars("(unsigned long)")));
+        assert!(follows_cast(&chars("x + (struct node)")));
+        assert!(follows_cast(&chars("(Node &)")));
+        assert!(!follows_cast(&chars("(get_fn())")));
+        assert!(!follows_cast(&chars("(*fn)")));
+        assert!(!follows_cast(&chars("fn")));
+        assert!(!follows_cast(&chars("x)")));
+    }
+
+    #[test]
+    fn gdb_commands_are_checked() {
+        assert!(check(Backend::Gdb, &words("bt full")).is_ok());
+        assert!(check(Backend::Gdb, &words("x/16xb $sp")).is_ok());
+        assert!(check(Backend::Gdb, &words("print/x counter")).is_ok());
+        assert!(check(Backend::Gdb, &words("print counter = 0")).is_err());
+        assert!(check(Backend::Gdb, &words("set print pretty on")).is_ok());
+        assert!(check(Backend::Gdb, &words("set var counter = 0")).is_err());
+        assert!(check(Backend::Gdb, &words("set logging file /tmp/out")).is_err());
+        assert!(check(Backend::Gdb, &words("thread apply all -q bt")).is_ok());
+        assert!(check(Backend::Gdb, &words("thread apply 1 2 call abort()")).is_err());
+        assert!(check(Backend::Gdb, &words("frame apply all p x")).is_err());
+        assert!(check(Backend::Gdb, &words("run")).is_err());
+    }
+
+    #[test]
+    fn gdb_mi_expressions_are_unquoted() {
+        assert!(check(Backend::Gdb, &words("-stack-list-frames")).is_ok());
+        assert!(check(Backend::Gdb, &words("-data-evaluate-expression \"a == b\"")).is_ok());
+        assert!(check(Backend::Gdb, &words("-data-evaluate-expression \"x=1\"")).is_err());
+        assert!(
+            check(
+                Backend::Gdb,
+                &words("-data-evaluate-expression --thread 1 --frame 0 abort()")
+            )
+            .is_err()
+        );
+        assert!(check(Backend::Gdb, &words("-var-create - * \"p->next\"")).is_ok());
+        assert!(check(Backend::Gdb, &words("-var-create - * \"f()\"")).is_err());
+        assert!(check(Backend::Gdb, &words("-gdb-set print pretty on")).is_ok());
+        assert!(check(Backend::Gdb, &words("-gdb-set var x=1")).is_err());
+        assert!(check(Backend::Gdb, &words("-exec-run")).is_err());
+    }
+
+    #[test]
+    fn gdb_addresses_are_expressions() {
+        assert!(check(Backend::Gdb, &words("disassemble /r main")).is_ok());
+        assert!(check(Backend::Gdb, &words("disassemble 0x1000,+16")).is_ok());
+        assert!(check(Backend::Gdb, &words("disassemble f()")).is_err());
+        assert!(check(Backend::Gdb, &words("disassemble/s 0x1000,f()")).is_err());
+        assert!(check(Backend::Gdb, &words("info symbol 0x1000")).is_ok());
+        assert!(check(Backend::Gdb, &words("info symbol f()")).is_err());
+        assert!(check(Backend::Gdb, &words("info line *f()")).is_err());
+        assert!(check(Backend::Gdb, &words("info line main")).is_ok());
+        assert!(check(Backend::Gdb, &words("list *abort()")).is_err());
+        assert!(check(Backend::Gdb, &words("list *0x1000")).is_ok());
+        assert!(check(Backend::Gdb, &words("frame address f()")).is_err());
+        assert!(check(Backend::Gdb, &words("info frame address g()")).is_err());
+        assert!(check(Backend::Gdb, &words("info frame")).is_ok());
+    }
+
+    #[test]
+    fn gdb_mi_addresses_are_expressions() {
+        assert!(
+            check(
+                Backend::Gdb,
+                &words("-data-read-memory-bytes \"&buffer\" 8")
+            )
+            .is_ok()
+        );
+        assert!(
+            check(
+                Backend::Gdb,
+                &words("-data-read-memory-bytes \"abort()\" 8")
+            )
+            .is_err()
+        );
+        assert!(check(Backend::Gdb, &words("-data-read-memory-bytes -o f() 0 8")).is_err());
+        assert!(check(Backend::Gdb, &words("-data-read-memory f() x 4 1 1")).is_err());
+        assert!(check(Backend::Gdb, &words("-data-disassemble -a \"main\" -- 0")).is_ok());
+        assert!(
+            check(
+                Backend::Gdb,
+                &words("-data-disassemble -s $pc -e \"$pc + 16\" -- 2")
+            )
+            .is_ok()
+        );
+        assert!(check(Backend::Gdb, &words("-data-disassemble -a \"f()\" -- 0")).is_err());
+        assert!(check(Backend::Gdb, &words("-data-disassemble -s 0 -e g() -- 0")).is_err());
+    }
+
+    #[test]
+    fn lldb_addresses_are_expressions() {
+        let memory_read = "memory read --force --format x --size 1 --count 8 --";
+        assert!(
+            check(
+                Backend::Lldb,
+                &words(&format!("{} \"&buffer\"", memory_read))
+            )
+            .is_ok()
+        );
+        assert!(
+            check(
+                Backend::Lldb,
+                &words(&format!("{} \"abort()\"", memory_read))
+            )
+            .is_err()
+        );
+        assert!(check(Backend::Lldb, &words("x f()")).is_err());
+        assert!(check(Backend::Lldb, &words("memory read 0x1000 'end()'")).is_err());
+        assert!(
+            check(
+                Backend::Lldb,
+                &words("disassemble --bytes --address \"main\"")
+            )
+            .is_ok()
+        );
+        assert!(check(Backend::Lldb, &words("disassemble -s \"f()\" -e 0x10")).is_err());
+        assert!(check(Backend::Lldb, &words("di --end-address=g()")).is_err());
+        assert!(check(Backend::Lldb, &words("image lookup -a f()")).is_err());
+        assert!(check(Backend::Lldb, &words("image lookup -af()")).is_err());
+        assert!(check(Backend::Lldb, &words("image lookup -a 0x1000")).is_ok());
+        assert!(check(Backend::Lldb, &words("source list -a f()")).is_err());
+        assert!(check(Backend::Lldb, &words("thread select `abort()`")).is_err());
+        assert!(check(Backend::Lldb, &words("thread select `1 + 1`")).is_ok());
+    }
+
+    #[test]
+    fn lldb_commands_are_checked() {
+        assert!(check(Backend::Lldb, &words("frame variable")).is_ok());
+        assert!(check(Backend::Lldb, &words("expr -f x -- a == b")).is_ok());
+        assert!(check(Backend::Lldb, &words("expr -- x = 1")).is_err());
+        assert!(check(Backend::Lldb, &words("memory read 0x1000")).is_ok());
+        assert!(check(Backend::Lldb, &words("memory read -o /tmp/out 0x1000")).is_err());
+        assert!(check(Backend::Lldb, &words("x -bo /tmp/out 0x1000")).is_err());
+        assert!(check(Backend::Lldb, &words("memory read --outf=/tmp/out 0x1000")).is_err());
+        assert!(check(Backend::Lldb, &words("process launch")).is_err());
+    }
+
+    #[test]
+    fn outfile_options_are_recognized() {
+        assert!(is_outfile_option("-o"));
+        assert!(is_outfile_option("-bo"));
+        assert!(is_outfile_option("--outfile"));
+        assert!(is_outfile_option("--out=/tmp/x"));
+        assert!(is_outfile_option("--append-outfile"));
+        assert!(is_outfile_option("--app"));
+        assert!(!is_outfile_option("--count"));
+        assert!(!is_outfile_option("--"));
+        assert!(!is_outfile_option("-c"));
+        assert!(!is_outfile_option("outfile"));
+    }
+
+    #[test]
+    fn pdb_commands_are_checked() {
+        assert!(check(Backend::Pdb, &words("where")).is_ok());
+        assert!(check(Backend::Pdb, &words("p len(items)")).is_ok());
+        assert!(check(Backend::Pdb, &words("p items.pop()")).is_err());
+        assert!(check(Backend::Pdb, &words("source os.system")).is_ok());
+        assert!(check(Backend::Pdb, &words("continue")).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Registers read by the debuggers, filtered by group and compared between stops.
+//!
+//! Neither GDB/MI nor LLDB report the register group of a register in a form that is easy to use, so registers are
+//! grouped by name, which works for x86, ARM and AArch64. Sub-registers such as `eax` or `w0` are dropped when the
+//! register containing them is listed too.
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde_json::{Value, json};
+
+/// Register groups the client can ask for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterGroup {
+    General,
+    Float,
+    Vector,
+    All,
+}
+
+impl RegisterGroup {
+    pub const ALL: [RegisterGroup; 4] = [
+        RegisterGroup::General,
+        RegisterGroup::Float,
+        RegisterGroup::Vector,
+        RegisterGroup::All,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegisterGroup::General => "general",
+            RegisterGroup::Float => "float",
+            RegisterGroup::Vector => "vector",
+            RegisterGroup::All => "all",
+        }
+    }
+
+    /// Whether the register of the given name belongs to this group.
+    pub fn contains(&self, register: &str) -> bool {
+        *self == RegisterGroup::All || group_of(register) == *self
+    }
+}
+
+impl FromStr for RegisterGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RegisterGroup::ALL
+            .into_iter()
+            .find(|group| group.name() == s.trim().to_ascii_lowercase())
+            .ok_or(format!(
+                "Unknown register group '{}'. Expected one of general, float, vector, all",
+                s
+            ))
+    }
+}
+
+/// Group of a register, by its name.
+fn group_of(register: &str) -> RegisterGroup {
+    const FLOAT: [&str; 24] = [
+        "fctrl",
+        "fstat",
+        "ftag",
+        "fiseg",
+        "fioff",
+        "foseg",
+        "fooff",
+        "fop",
+        "fcw",
+        "fsw",
+        "ftw",
+        "fip",
+        "fcs",
+        "fdp",
+        "fds",
+        "mxcsrmask",
+        "fpsr",
+        "fpcr",
+        "fpscr",
+        "fpexc",
+        "fpsid",
+        "fcsr",
+        "fflags",
+        "frm",
+    ];
+    const FLOAT_BANKS: [&str; 5] = ["st", "stmm", "d", "s", "f"];
+    const VECTOR: [&str; 5] = ["mxcsr", "ffr", "vg", "vscr", "vrsave"];
+    const VECTOR_BANKS: [&str; 8] = ["xmm", "ymm", "zmm", "k", "v", "q", "z", "p"];
+
+    let register = register.to_ascii_lowercase();
+    // Register banks are a prefix and a number, with an optional suffix for the upper halves of x86 vectors.
+    let bank = register
+        .trim_end_matches('h')
+        .trim_end_matches(|c: char| c.is_ascii_digit());
+    let numbered = bank.len() < register.trim_end_matches('h').len();
+    if VECTOR.contains(&register.as_str()) || (numbered && VECTOR_BANKS.contains(&bank)) {
+        RegisterGroup::Vector
+    } else if FLOAT.contains(&register.as_str()) || (numbered && FLOAT_BANKS.contains(&bank)) {
+        RegisterGroup::Float
+    } else {
+        RegisterGroup::General
+    }
+}
+
+/// Registers that may contain the given register, such as `rax` for `eax` or `x0` for `w0`.
+fn containers(register: &str) -> Vec<String> {
+    const X86: [&str; 9] = ["ax", "bx", "cx", "dx", "si", "di", "bp", "sp", "ip"];
+    let mut containers = Vec::new();
+    if let Some(rest) = register.strip_prefix('e')
+        && X86.contains(&rest)
+    {
+        containers.push(format!("r{}", rest));
+    }
+    if X86.contains(&register) {
+        containers.push(format!("e{}", register));
+    }
+    match register {
+        "al" | "ah" => containers.push("ax".to_owned()),
+        "bl" | "bh" => containers.push("bx".to_owned()),
+        "cl" | "ch" => containers.push("cx".to_owned()),
+        "dl" | "dh" => containers.push("dx".to_owned()),
+        "sil" | "dil" | "bpl" | "spl" => containers.push(register[..2].to_owned()),
+        _ => {}
+    }
+    // r8d, r8w, r8l and r8b are parts of r8. w0 is part of x0.
+    if let Some(number) = register
+        .strip_prefix('r')
+        .and_then(|rest| rest.strip_suffix(['d', 'w', 'l', 'b']))
+        .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    {
+        containers.push(format!("r{}", number));
+    }
+    if let Some(number) = register
+        .strip_prefix('w')
+        .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    {
+        containers.push(format!("x{}", number));
+    }
+    containers
+}
+
+/// Names of the registers to report among all registers of the target, in order: those of the group that are not
+/// part of another listed register.
+pub fn select<'a>(names: &[&'a str], group: RegisterGroup) -> Vec<&'a str> {
+    names
+        .iter()
+        .copied()
+        .filter(|name| !name.is_empty() && group.contains(name))
+        .filter(|name| {
+            !containers(name)
+                .iter()
+                .any(|container| names.contains(&container.as_str()))
+        })
+        .collect()
+}
+
+/// Register values as JSON, in the order given.
+pub fn to_json(group: RegisterGroup, changed_only: bool, registers: &[(String, String)]) -> Value {
+    let registers: Vec<Value> = registers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+    json!({
+        "group": group.name(),
+        "changed_since_last_stop": changed_only,
+        "registers": registers,
+    })
+}
+
+/// Register values read at stops of a session, for debuggers that cannot tell which registers changed themselves.
+#[derive(Default)]
+pub struct RegisterHistory {
+    /// Number of the stop the latest values were read at, and the values.
+    latest: Option<(u64, HashMap<String, String>)>,
+    /// Values read at the stop before that.
+    previous: Option<HashMap<String, String>>,
+}
+
+impl RegisterHistory {
+    /// Keep the values of all registers read at the given stop.
+    pub fn record(&mut self, stop: u64, values: HashMap<String, String>) {
+        match self.latest.take() {
+            Some((latest_stop, latest)) if latest_stop != stop => {
+                self.previous = Some(latest);
+            }
+            _ => {}
+        }
+        self.latest = Some((stop, values));
+    }
+
+    /// Whether a register changed since the values read at an earlier stop.
+    /// `None` if no values were read at an earlier stop.
+    pub fn changed(&self, register: &str) -> Option<bool> {
+        let previous = self.previous.as_ref()?;
+        let (_, latest) = self.latest.as_ref()?;
+        Some(previous.get(register) != latest.get(register))
+    }
+}
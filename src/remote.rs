@@ -0,0 +1,246 @@
+//! Remote targets: programs debugged through a stub speaking the GDB remote protocol, such as `gdbserver` or
+//! `lldb-server`, running in another container, in another network namespace or under QEMU user mode.
+//!
+//! A stub can also be spawned locally for a session with [`spawn`]. It is killed when the session disconnects from
+//! it or ends, so that no stub outlives the session that uses it.
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+
+use crate::{mi, resources::Activity, sandbox::Sandbox, server::Session};
+
+/// How long a spawned stub may take to listen for the debugger.
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval at which a spawned stub is checked for listening.
+const LISTEN_POLL: Duration = Duration::from_millis(50);
+
+/// Output of a spawned stub kept to explain why it exited, in bytes.
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// Stubs that can be spawned for a session.
+#[derive(Clone, Copy, Debug)]
+pub enum Stub {
+    Gdbserver,
+    LldbServer,
+}
+
+impl Stub {
+    pub fn program(&self) -> &'static str {
+        match self {
+            Stub::Gdbserver => "gdbserver",
+            Stub::LldbServer => "lldb-server",
+        }
+    }
+
+    /// Command running `program` under the stub, listening on the loopback interface only.
+    fn command(&self, address: &str, program: &Path, arguments: &[String]) -> Command {
+        let mut command = Command::new(self.program());
+        match self {
+            // Stop listening once the debugger is connected, nobody else may take over the program.
+            Stub::Gdbserver => command.args(["--once", address]),
+            Stub::LldbServer => command.args(["gdbserver", address, "--"]),
+        };
+        command.arg(program).args(arguments);
+        command
+    }
+}
+
+/// Connection of a session to a remote target.
+pub struct Connection {
+    /// Address of the stub, such as `host:port` or a serial device.
+    pub address: String,
+    /// How the debugger is connected: `remote` or `extended-remote` for GDB, `gdb-remote` for LLDB.
+    pub protocol: &'static str,
+    /// Stub spawned for the session, killed when the connection is dropped.
+    stub: Option<(Stub, Child)>,
+}
+
+impl Connection {
+    pub fn new(address: &str, protocol: &'static str) -> Self {
+        Connection {
+            address: address.to_owned(),
+            protocol,
+            stub: None,
+        }
+    }
+
+    /// Connection to a stub spawned with [`spawn`].
+    pub fn spawned(address: &str, protocol: &'static str, stub: Stub, child: Child) -> Self {
+        Connection {
+            stub: Some((stub, child)),
+            ..Connection::new(address, protocol)
+        }
+    }
+
+    /// Process ID of the stub spawned for the session, if any and still running.
+    pub fn stub_pid(&self) -> Option<u32> {
+        self.stub.as_ref().and_then(|(_, child)| child.id())
+    }
+
+    /// The connection as JSON, for the `state` resource.
+    pub fn to_json(&self) -> Value {
+        mi::compact(json!({
+            "address": self.address,
+            "protocol": self.protocol,
+            "stub": self.stub.as_ref().map(|(stub, child)| mi::compact(json!({
+                "program": stub.program(),
+                "pid": child.id(),
+            }))),
+        }))
+    }
+}
+
+/// Check that `session` can reach remote targets. Sandboxed sessions only can if the sandbox keeps network access.
+pub fn check_network(session: &Session, sandbox: &Sandbox) -> Result<(), String> {
+    if session.sandbox().is_some() && !sandbox.network {
+        return Err(
+            "Sandboxed sessions have no network access to reach remote targets. Set `network` in the sandbox configuration"
+                .to_owned(),
+        );
+    }
+    Ok(())
+}
+
+/// Check an address given by the client for a remote target: `host:port`, a port, or a serial device under `/dev`.
+/// GDB runs the rest of an address starting with `|` as a shell command, which this rejects among others.
+pub fn check_address(address: &str) -> Result<(), String> {
+    let valid = if address.starts_with("/dev/") {
+        !address.contains(|c: char| c.is_whitespace() || c.is_control())
+    } else {
+        let address = ["tcp:", "tcp4:", "tcp6:", "udp:", "udp4:", "udp6:"]
+            .iter()
+            .find_map(|prefix| address.strip_prefix(prefix))
+            .unwrap_or(address);
+        let (host, port) = address.rsplit_once(':').unwrap_or(("", address));
+        port.parse::<u16>().is_ok_and(|port| port != 0)
+            && host.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '[' | ']')
+            })
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid remote address '{}'. Expected host:port, a port or a serial device such as /dev/ttyUSB0",
+            address
+        ))
+    }
+}
+
+/// A free TCP port on the loopback interface.
+fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Whether a socket listens on the given TCP port, as told by `/proc/net/tcp`.
+/// Connecting to find out is not an option: `gdbserver --once` would take it for the debugger.
+fn listening(port: u16) -> bool {
+    const LISTEN: &str = "0A";
+    let port = format!(":{:04X}", port);
+    ["/proc/net/tcp", "/proc/net/tcp6"].iter().any(|table| {
+        std::fs::read_to_string(table)
+            .unwrap_or_default()
+            .lines()
+            .skip(1)
+            .any(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                fields.len() > 3 && fields[1].ends_with(&port) && fields[3] == LISTEN
+            })
+    })
+}
+
+/// Spawn `stub` running `program` on a free port of the loopback interface, in `sandbox` if given, and wait until it
+/// listens. The output of the stub and the program goes to `activity`. Returns the stub process, killed when dropped, and the address to connect the debugger to.
+pub async fn spawn(
+    stub: Stub,
+    program: &Path,
+    arguments: &[String],
+    sandbox: Option<&Sandbox>,
+    activity: Arc<std::sync::Mutex<Activity>>,
+) -> Result<(Child, String), String> {
+    let port =
+        free_port().map_err(|err| format!("Failed to find a free port. [Error]: {}", err))?;
+    let address = format!("127.0.0.1:{}", port);
+    let mut command = stub.command(&address, program, arguments);
+    if let Some(sandbox) = sandbox {
+        command = sandbox
+            .apply(command)
+            .map_err(|err| format!("Failed to sandbox {}. [Error]: {}", stub.program(), err))?
+            .0;
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("Failed to start {}. [Error]: {}", stub.program(), err))?;
+
+    // The program writes to the output of the stub. Keep reading it for the `program-output` resource, so that the
+    // stub never blocks on a full pipe, and keep the start of it to explain why the stub exited.
+    let output = Arc::new(std::sync::Mutex::new(String::new()));
+    let stdout = child
+        .stdout
+        .take()
+        .map(|stdout| forward(stdout, &activity, &output));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|stderr| forward(stderr, &activity, &output));
+
+    let started = Instant::now();
+    while !listening(port) {
+        if let Ok(Some(status)) = child.try_wait() {
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = reader.await;
+            }
+            let output = output.lock().unwrap();
+            return Err(format!(
+                "{} exited with {} before listening. [Output]: {}",
+                stub.program(),
+                status,
+                output.trim_end()
+            ));
+        }
+        if started.elapsed() > LISTEN_TIMEOUT {
+            return Err(format!(
+                "{} did not listen on {} within {} seconds",
+                stub.program(),
+                address,
+                LISTEN_TIMEOUT.as_secs()
+            ));
+        }
+        tokio::time::sleep(LISTEN_POLL).await;
+    }
+    Ok((child, address))
+}
+
+/// Read lines of output of a spawned stub into `activity` until it is closed, and the first of them into `output`.
+fn forward<R: AsyncRead + Unpin + Send + 'static>(
+    reader: R,
+    activity: &Arc<std::sync::Mutex<Activity>>,
+    output: &Arc<std::sync::Mutex<String>>,
+) -> JoinHandle<()> {
+    let activity = activity.clone();
+    let output = output.clone();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let mut output = output.lock().unwrap();
+            if output.len() < MAX_OUTPUT {
+                output.push_str(&line);
+                output.push('\n');
+            }
+            activity.lock().unwrap().program_output(&line);
+        }
+    })
+}
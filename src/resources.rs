@@ -0,0 +1,330 @@
+//! Debugger sessions exposed as MCP resources: `session://{id}/transcript`, `session://{id}/program-output`,
+//! `session://{id}/breakpoints` and `session://{id}/state`.
+//!
+//! Clients subscribed to a resource get a `notifications/resources/updated` notification when it changes. Until
+//! they read it again, further changes are not notified, so a chatty program does not flood the client.
+//! Subscriptions end with the client's connection.
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use crate::client::Client;
+
+/// URI scheme of session resources.
+pub const SCHEME: &str = "session://";
+/// Program output kept for the `program-output` resource. Older output is dropped.
+const PROGRAM_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// The resources of a session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Transcript,
+    ProgramOutput,
+    Breakpoints,
+    State,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 4] = [
+        ResourceKind::Transcript,
+        ResourceKind::ProgramOutput,
+        ResourceKind::Breakpoints,
+        ResourceKind::State,
+    ];
+
+    /// Last segment of the resource URI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceKind::Transcript => "transcript",
+            ResourceKind::ProgramOutput => "program-output",
+            ResourceKind::Breakpoints => "breakpoints",
+            ResourceKind::State => "state",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ResourceKind::Transcript => {
+                "Commands sent to the debugger session and their output, oldest first"
+            }
+            ResourceKind::ProgramOutput => {
+                "Recent output of the debugged program. LLDB and PDB mix it with their own console output"
+            }
+            ResourceKind::Breakpoints => {
+                "Breakpoints of the debugger session, as listed by the debugger"
+            }
+            ResourceKind::State => {
+                "Execution state of the debugger session (idle, running, stopped or exited) and its last event, as JSON"
+            }
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ResourceKind::State => "application/json",
+            _ => "text/plain",
+        }
+    }
+
+    /// URI of this resource for the given session.
+    pub fn uri(&self, session_id: &str) -> String {
+        format!("{}{}/{}", SCHEME, session_id, self.name())
+    }
+
+    /// URI template of this resource.
+    pub fn uri_template(&self) -> String {
+        self.uri("{id}")
+    }
+}
+
+impl FromStr for ResourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ResourceKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or(format!("Unknown session resource '{}'", s))
+    }
+}
+
+/// Split a resource URI into the session ID and the resource.
+pub fn parse_uri(uri: &str) -> Result<(&str, ResourceKind), String> {
+    let (session_id, kind) = uri
+        .strip_prefix(SCHEME)
+        .and_then(|path| path.split_once('/'))
+        .ok_or(format!(
+            "Invalid resource URI '{}'. Expected {}{{id}}/{{resource}}",
+            uri, SCHEME
+        ))?;
+    Ok((session_id, kind.parse()?))
+}
+
+/// Execution state of a session, as far as its output tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionState {
+    /// No program is running yet.
+    Idle,
+    Running,
+    Stopped,
+    Exited,
+}
+
+impl ExecutionState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExecutionState::Idle => "idle",
+            ExecutionState::Running => "running",
+            ExecutionState::Stopped => "stopped",
+            ExecutionState::Exited => "exited",
+        }
+    }
+}
+
+struct Subscriber {
+    client: Client,
+    uri: String,
+    /// An update was notified and the client did not read the resource since.
+    notified: bool,
+}
+
+/// What a session's debugger has been doing, updated by its output readers as output arrives.
+/// Shared between the [`crate::server::Session`] and the observer of its output.
+pub struct Activity {
+    session_id: String,
+    program_output: VecDeque<String>,
+    program_output_bytes: usize,
+    state: ExecutionState,
+    /// Number of times the program stopped.
+    stops: u64,
+    last_event: Option<String>,
+    /// States of single threads, known when the debugger reports events per thread, as GDB in non-stop mode.
+    threads: BTreeMap<String, ExecutionState>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Activity {
+    pub fn shared(session_id: &str) -> Arc<Mutex<Activity>> {
+        Arc::new(Mutex::new(Activity {
+            session_id: session_id.to_owned(),
+            program_output: VecDeque::new(),
+            program_output_bytes: 0,
+            state: ExecutionState::Idle,
+            stops: 0,
+            last_event: None,
+            threads: BTreeMap::new(),
+            subscribers: Vec::new(),
+        }))
+    }
+
+    /// Keep a line of program output.
+    pub fn program_output(&mut self, line: &str) {
+        self.program_output_bytes += line.len() + 1;
+        self.program_output.push_back(line.to_owned());
+        while self.program_output_bytes > PROGRAM_OUTPUT_BYTES
+            && let Some(dropped) = self.program_output.pop_front()
+        {
+            self.program_output_bytes -= dropped.len() + 1;
+        }
+        self.changed(ResourceKind::ProgramOutput);
+    }
+
+    /// Note an asynchronous event of the given kind, as recognized by the backend's event parser, about the given
+    /// thread or `all` threads if the backend tells.
+    pub fn event(&mut self, kind: &str, line: &str, thread: Option<&str>) {
+        self.last_event = Some(line.to_owned());
+        let state = match kind {
+            "stopped" | "signal" | "uncaught-exception" | "exception" => {
+                Some(ExecutionState::Stopped)
+            }
+            "running" | "launched" | "process-started" => Some(ExecutionState::Running),
+            "exited" | "process-exited" | "crashed" => Some(ExecutionState::Exited),
+            _ => None,
+        };
+        match (thread, state) {
+            (Some("all"), Some(state)) => {
+                self.threads.values_mut().for_each(|thread| *thread = state)
+            }
+            (Some(thread), Some(state)) => {
+                self.threads.insert(thread.to_owned(), state);
+            }
+            (Some(thread), None) if kind == "thread-exited" => {
+                self.threads.remove(thread);
+            }
+            _ => {}
+        }
+        if state == Some(ExecutionState::Exited) {
+            self.threads.clear();
+        }
+        if let Some(state) = state {
+            // The program is stopped as long as one of its threads is, whatever the others do.
+            self.state = match state {
+                ExecutionState::Running | ExecutionState::Stopped if !self.threads.is_empty() => {
+                    if self
+                        .threads
+                        .values()
+                        .any(|thread| *thread == ExecutionState::Stopped)
+                    {
+                        ExecutionState::Stopped
+                    } else {
+                        ExecutionState::Running
+                    }
+                }
+                state => state,
+            };
+        }
+        if state == Some(ExecutionState::Stopped) {
+            self.stops += 1;
+        }
+        // Hit counts change when the program stops.
+        if kind.starts_with("breakpoint-") || state == Some(ExecutionState::Stopped) {
+            self.changed(ResourceKind::Breakpoints);
+        }
+        self.changed(ResourceKind::State);
+    }
+
+    /// Number of times the program stopped so far, to tell whether it ran between two points in time.
+    pub fn stops(&self) -> u64 {
+        self.stops
+    }
+
+    /// Text of the `program-output` resource.
+    pub fn program_output_text(&self) -> String {
+        self.program_output
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Fields of the `state` resource known from the output.
+    pub fn state_json(&self) -> serde_json::Value {
+        let mut state = json!({
+            "state": self.state.name(),
+            "last_event": self.last_event,
+            "program_output_bytes": self.program_output_bytes,
+        });
+        if !self.threads.is_empty() {
+            let threads: serde_json::Map<String, serde_json::Value> = self
+                .threads
+                .iter()
+                .map(|(thread, state)| (thread.clone(), state.name().into()))
+                .collect();
+            state["threads"] = threads.into();
+        }
+        state
+    }
+
+    /// Notify the subscribers of a resource of this session that it changed. Subscriptions of clients that are
+    /// gone are dropped.
+    pub fn changed(&mut self, kind: ResourceKind) {
+        let uri = kind.uri(&self.session_id);
+        self.subscribers
+            .retain(|subscriber| subscriber.client.is_connected());
+        for subscriber in self
+            .subscribers
+            .iter_mut()
+            .filter(|subscriber| subscriber.uri == uri && !subscriber.notified)
+        {
+            subscriber.notified = true;
+            subscriber
+                .client
+                .notify("notifications/resources/updated", json!({ "uri": uri }));
+        }
+    }
+
+    pub fn subscribe(&mut self, client: &Client, uri: &str) {
+        self.subscribers
+            .retain(|subscriber| subscriber.client.is_connected());
+        if !self
+            .subscribers
+            .iter()
+            .any(|subscriber| subscriber.client.is(client) && subscriber.uri == uri)
+        {
+            self.subscribers.push(Subscriber {
+                client: client.clone(),
+                uri: uri.to_owned(),
+                notified: false,
+            });
+        }
+    }
+
+    pub fn unsubscribe(&mut self, client: &Client, uri: &str) {
+        self.subscribers
+            .retain(|subscriber| !(subscriber.client.is(client) && subscriber.uri == uri));
+    }
+
+    /// The client read the resource, notify it of the next change again.
+    pub fn read_by(&mut self, client: &Client, uri: &str) {
+        for subscriber in self
+            .subscribers
+            .iter_mut()
+            .filter(|subscriber| subscriber.client.is(client) && subscriber.uri == uri)
+        {
+            subscriber.notified = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscriptions_of_disconnected_clients_are_dropped() {
+        let activity = Activity::shared("gdb-0");
+        let mut activity = activity.lock().unwrap();
+        let uri = ResourceKind::State.uri("gdb-0");
+        let (gone, messages) = Client::new("gone");
+        drop(messages);
+        let (connected, mut messages) = Client::new("connected");
+        activity.subscribe(&gone, &uri);
+        activity.subscribe(&connected, &uri);
+        activity.changed(ResourceKind::State);
+        assert_eq!(activity.subscribers.len(), 1);
+        assert!(activity.subscribers[0].client.is(&connected));
+        let notification = messages.try_next().unwrap().unwrap();
+        assert_eq!(notification["params"]["uri"], uri.as_str());
+    }
+}
@@ -0,0 +1,675 @@
+//! Optional sandbox for debugger processes.
+//!
+//! Debuggers and the programs they run are isolated with bubblewrap when it is installed, or otherwise with
+//! user, mount, PID and network namespaces plus Landlock filesystem rules. Either way the filesystem is read-only
+//! except for the writable directories (unless the kernel lacks Landlock), there is no network access, resource
+//! limits apply to the debugger and everything it starts, and a seccomp filter blocks system calls that reach
+//! beyond the sandbox, such as mounting, loading kernel code or attaching to other processes. The debugger and its
+//! debuggee share the sandbox, so ptrace between them still works.
+use std::io::Error;
+use std::os::fd::{AsRawFd, RawFd};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// How the sandbox isolates processes.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Isolation {
+    /// Bubblewrap if it is installed, namespaces otherwise.
+    #[default]
+    Auto,
+    /// Bubblewrap (`bwrap`). Fails if it is not installed.
+    Bwrap,
+    /// User, mount, PID and network namespaces with Landlock filesystem rules.
+    Namespaces,
+}
+
+/// Resource limits of sandboxed processes. Each one applies to the debugger and the debugged program separately.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Address space size in MiB.
+    pub memory_mb: Option<u64>,
+    /// CPU time in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Size of files written, in MiB.
+    pub file_size_mb: Option<u64>,
+    /// Number of processes of the user.
+    pub processes: Option<u64>,
+}
+
+/// Sandbox settings for debugger processes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sandbox {
+    /// Run debuggers inside the sandbox.
+    pub enabled: bool,
+    pub isolation: Isolation,
+    /// Keep network access.
+    pub network: bool,
+    /// Directories that stay writable. The rest of the filesystem is read-only.
+    pub writable: Vec<PathBuf>,
+    pub limits: ResourceLimits,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            isolation: Isolation::Auto,
+            network: false,
+            writable: vec![PathBuf::from("/tmp")],
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl Sandbox {
+    /// Prepare `command` to run inside the sandbox.
+    /// Returns the command to spawn instead and a description of the isolation applied.
+    pub fn apply(
+        &self,
+        command: tokio::process::Command,
+    ) -> Result<(tokio::process::Command, String), Error> {
+        let bwrap = match self.isolation {
+            Isolation::Auto => find_program("bwrap"),
+            Isolation::Bwrap => Some(find_program("bwrap").ok_or(Error::other(
+                "Sandbox requires bwrap, which is not installed",
+            ))?),
+            Isolation::Namespaces => None,
+        };
+        let writable: Vec<PathBuf> = self
+            .writable
+            .iter()
+            .filter(|path| path.exists())
+            .cloned()
+            .collect();
+        let mut description = Vec::new();
+
+        let (mut command, read_only) = match bwrap {
+            Some(bwrap) => {
+                description.push("bwrap".to_owned());
+                // bwrap mounts and changes namespaces itself, it installs the filter once it is done.
+                let filter = linux::seccomp_file()?;
+                let mut command = self.bwrap_command(
+                    bwrap,
+                    command,
+                    &writable,
+                    filter.as_ref().map(AsRawFd::as_raw_fd),
+                );
+                if let Some(filter) = filter {
+                    linux::inherit(&mut command, filter);
+                    description.push("seccomp filter".to_owned());
+                }
+                (command, true)
+            }
+            None => {
+                let mut command = command;
+                let (isolation, landlock) = linux::isolate(&mut command, self.network, &writable)?;
+                description.extend(isolation);
+                if linux::filter_syscalls(&mut command) {
+                    description.push("seccomp filter".to_owned());
+                }
+                (command, landlock)
+            }
+        };
+        description.push(if self.network {
+            "network allowed".to_owned()
+        } else {
+            "no network".to_owned()
+        });
+        if read_only {
+            description.push(format!(
+                "read-only filesystem except {}",
+                writable
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        description.extend(linux::limit(&mut command, &self.limits));
+        Ok((command, description.join(", ")))
+    }
+
+    /// Command running `command` in bwrap, with the seccomp filter read from the file descriptor `seccomp` if given.
+    fn bwrap_command(
+        &self,
+        bwrap: PathBuf,
+        command: tokio::process::Command,
+        writable: &[PathBuf],
+        seccomp: Option<RawFd>,
+    ) -> tokio::process::Command {
+        let inner = command.as_std();
+        let mut wrapped = tokio::process::Command::new(bwrap);
+        wrapped.args(["--die-with-parent", "--unshare-user-try", "--unshare-pid"]);
+        wrapped.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]);
+        if !self.network {
+            wrapped.arg("--unshare-net");
+        }
+        for path in writable {
+            wrapped.arg("--bind").arg(path).arg(path);
+        }
+        if let Some(seccomp) = seccomp {
+            wrapped.arg("--seccomp").arg(seccomp.to_string());
+        }
+        if let Some(dir) = inner.get_current_dir() {
+            wrapped.arg("--chdir").arg(dir);
+            wrapped.current_dir(dir);
+        }
+        for (key, value) in inner.get_envs() {
+            match value {
+                Some(value) => wrapped.env(key, value),
+                None => wrapped.env_remove(key),
+            };
+        }
+        wrapped
+            .arg("--")
+            .arg(inner.get_program())
+            .args(inner.get_args());
+        wrapped
+    }
+}
+
+/// Find an executable in `PATH`.
+fn find_program(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io::Error;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+
+    use super::ResourceLimits;
+
+    // Landlock ABI, see linux/landlock.h.
+    const CREATE_RULESET_VERSION: u32 = 1;
+    const RULE_PATH_BENEATH: u32 = 1;
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Move the command into new user, mount and PID namespaces (and a network namespace unless `network`) and
+    /// restrict writes to `writable` with Landlock when the kernel supports it.
+    /// Returns a description of the isolation, and whether writes are restricted.
+    pub(super) fn isolate(
+        command: &mut tokio::process::Command,
+        network: bool,
+        writable: &[PathBuf],
+    ) -> Result<(Vec<String>, bool), Error> {
+        let mut description = Vec::new();
+        let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+        let mut namespaces = vec!["user", "mount"];
+        // Without a /proc of its own, a PID namespace would hide the debugged processes from the debugger.
+        let pid_namespace = proc_mountable();
+        if pid_namespace {
+            flags |= libc::CLONE_NEWPID;
+            namespaces.push("PID");
+        }
+        if !network {
+            flags |= libc::CLONE_NEWNET;
+            namespaces.push("network");
+        }
+        let last = namespaces.pop().unwrap_or_default();
+        description.push(format!("{} and {} namespaces", namespaces.join(", "), last));
+        if !pid_namespace {
+            description.push("no PID namespace (/proc cannot be mounted)".to_owned());
+        }
+        // Map the current user to itself, so files keep their owners inside the namespace.
+        // Everything the child needs is prepared here, since it must not allocate after forking.
+        let uid_map = format!("{0} {0} 1", unsafe { libc::getuid() });
+        let gid_map = format!("{0} {0} 1", unsafe { libc::getgid() });
+        let ruleset = landlock_ruleset(writable)?;
+        match &ruleset {
+            Some((_, abi)) => description.push(format!("Landlock ABI {}", abi)),
+            None => description.push("Landlock unsupported by the kernel".to_owned()),
+        }
+        let landlock = ruleset.is_some();
+
+        let setup = move || -> Result<(), Error> {
+            unsafe {
+                enter_namespaces(flags, &uid_map, &gid_map)?;
+                if let Some((ruleset, _)) = &ruleset {
+                    if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                        return Err(Error::last_os_error());
+                    }
+                    if libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) != 0
+                    {
+                        return Err(Error::last_os_error());
+                    }
+                }
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(setup) };
+        Ok((description, landlock))
+    }
+
+    /// Enter new namespaces, mapping the user to itself. With a PID namespace, the calling process forks its init
+    /// and only returns in it, with /proc remounted for the namespace.
+    unsafe fn enter_namespaces(flags: i32, uid_map: &str, gid_map: &str) -> Result<(), Error> {
+        unsafe {
+            if libc::unshare(flags) != 0 {
+                return Err(Error::last_os_error());
+            }
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", uid_map.as_bytes())?;
+            write_file(c"/proc/self/gid_map", gid_map.as_bytes())?;
+            if flags & libc::CLONE_NEWPID == 0 {
+                return Ok(());
+            }
+            let pid = libc::fork();
+            if pid < 0 {
+                return Err(Error::last_os_error());
+            }
+            if pid > 0 {
+                wait_for_init(pid);
+            }
+            // Take the namespace down, and everything in it, if the waiting process is killed.
+            if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL, 0, 0, 0) != 0 {
+                return Err(Error::last_os_error());
+            }
+            let flags = libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC;
+            if libc::mount(
+                c"proc".as_ptr(),
+                c"/proc".as_ptr(),
+                c"proc".as_ptr(),
+                flags,
+                std::ptr::null(),
+            ) != 0
+            {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Stand in for the init of a PID namespace outside of it: wait for it and exit with its status, so the spawner
+    /// sees the process it started exit when the debugger does.
+    unsafe fn wait_for_init(pid: libc::pid_t) -> ! {
+        unsafe {
+            // Interrupts sent to the process group are meant for the debugger. The descriptors, among them the pipe
+            // reporting exec errors to the spawner, must not be held open by this process.
+            libc::signal(libc::SIGINT, libc::SIG_IGN);
+            if libc::syscall(libc::SYS_close_range, 0, u32::MAX, 0) != 0 {
+                for fd in 0..1024 {
+                    libc::close(fd);
+                }
+            }
+            let mut status = 0;
+            while libc::waitpid(pid, &mut status, 0) < 0
+                && Error::last_os_error().raw_os_error() == Some(libc::EINTR)
+            {}
+            libc::_exit(if libc::WIFEXITED(status) {
+                libc::WEXITSTATUS(status)
+            } else {
+                128 + libc::WTERMSIG(status)
+            })
+        }
+    }
+
+    /// Whether new PID namespaces can have their own /proc. Mounting it fails where parts of /proc are hidden, as
+    /// in many containers. Probed once, by running `true` in new namespaces.
+    fn proc_mountable() -> bool {
+        static MOUNTABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        *MOUNTABLE.get_or_init(|| {
+            let Some(program) = super::find_program("true") else {
+                return false;
+            };
+            let uid_map = format!("{0} {0} 1", unsafe { libc::getuid() });
+            let gid_map = format!("{0} {0} 1", unsafe { libc::getgid() });
+            let flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID;
+            let mut probe = std::process::Command::new(program);
+            probe
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null());
+            unsafe { probe.pre_exec(move || enter_namespaces(flags, &uid_map, &gid_map)) };
+            probe.status().is_ok_and(|status| status.success())
+        })
+    }
+
+    /// Install the seccomp filter of [`seccomp_filter`] in the command, after the namespaces are set up.
+    /// Returns whether the filter is supported on this architecture.
+    pub(super) fn filter_syscalls(command: &mut tokio::process::Command) -> bool {
+        let Some(mut filter) = seccomp_filter() else {
+            return false;
+        };
+        let setup = move || -> Result<(), Error> {
+            let program = libc::sock_fprog {
+                len: filter.len() as u16,
+                filter: filter.as_mut_ptr(),
+            };
+            unsafe {
+                // Required to install a filter without CAP_SYS_ADMIN, and keeps setuid programs from gaining it.
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                    || libc::prctl(
+                        libc::PR_SET_SECCOMP,
+                        libc::SECCOMP_MODE_FILTER,
+                        &program as *const libc::sock_fprog,
+                    ) != 0
+                {
+                    return Err(Error::last_os_error());
+                }
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(setup) };
+        true
+    }
+
+    /// A file holding the seccomp filter, for bwrap's `--seccomp`. `None` on architectures without a filter.
+    pub(super) fn seccomp_file() -> Result<Option<OwnedFd>, Error> {
+        let Some(filter) = seccomp_filter() else {
+            return Ok(None);
+        };
+        let fd = unsafe { libc::memfd_create(c"seccomp".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let file = unsafe { OwnedFd::from_raw_fd(fd) };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                filter.as_ptr().cast::<u8>(),
+                std::mem::size_of_val(filter.as_slice()),
+            )
+        };
+        let mut writer = std::fs::File::from(file.try_clone()?);
+        std::io::Write::write_all(&mut writer, bytes)?;
+        std::io::Seek::rewind(&mut writer)?;
+        Ok(Some(file))
+    }
+
+    /// Let the command inherit `fd`, which is kept open until the command is dropped.
+    pub(super) fn inherit(command: &mut tokio::process::Command, fd: OwnedFd) {
+        let setup = move || -> Result<(), Error> {
+            if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, 0) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(setup) };
+    }
+
+    /// `AUDIT_ARCH_*` value of the architecture the filter is written for, see linux/audit.h.
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    /// System calls failing with `EPERM` in the sandbox: mounting and changing namespaces, loading kernel code,
+    /// rebooting, swapping, and keys and file handles that reach outside of the sandbox.
+    const BLOCKED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_keyctl,
+        libc::SYS_acct,
+    ];
+
+    /// Classic BPF program of the seccomp filter. Besides [`BLOCKED_SYSCALLS`], `ptrace` can only be used on
+    /// children that asked to be traced, not to attach to other processes. System calls of other architectures,
+    /// such as 32-bit programs, kill the process, as the filter would not recognize them.
+    fn seccomp_filter() -> Option<Vec<libc::sock_filter>> {
+        // Offsets in `struct seccomp_data`.
+        const NR: u32 = 0;
+        const ARCH: u32 = 4;
+        const FIRST_ARGUMENT: u32 = 16;
+        /// Bit of the x32 ABI in system call numbers on x86-64.
+        const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+        // `ptrace` requests, whose type differs between the C libraries.
+        const PTRACE_ATTACH: u32 = 16;
+        const PTRACE_SEIZE: u32 = 0x4206;
+
+        let statement = |code: u32, k: u32| libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let jump_if_equal = |k: u32, jt: u8, jf: u8| libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+            jt,
+            jf,
+            k,
+        };
+        let load = |offset: u32| statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset);
+        let ret = |action: u32| statement(libc::BPF_RET | libc::BPF_K, action);
+        let deny = ret(libc::SECCOMP_RET_ERRNO | libc::EPERM as u32);
+        let allow = ret(libc::SECCOMP_RET_ALLOW);
+
+        let mut filter = vec![
+            load(ARCH),
+            jump_if_equal(AUDIT_ARCH?, 1, 0),
+            ret(libc::SECCOMP_RET_KILL_PROCESS),
+            load(NR),
+        ];
+        if cfg!(target_arch = "x86_64") {
+            filter.push(libc::sock_filter {
+                code: (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 1,
+                k: X32_SYSCALL_BIT,
+            });
+            filter.push(ret(libc::SECCOMP_RET_KILL_PROCESS));
+        }
+        for syscall in BLOCKED_SYSCALLS {
+            filter.push(jump_if_equal(*syscall as u32, 0, 1));
+            filter.push(deny);
+        }
+        filter.extend([
+            jump_if_equal(libc::SYS_ptrace as u32, 0, 4),
+            load(FIRST_ARGUMENT),
+            jump_if_equal(PTRACE_ATTACH, 1, 0),
+            jump_if_equal(PTRACE_SEIZE, 0, 1),
+            deny,
+            allow,
+        ]);
+        Some(filter)
+    }
+
+    /// Apply resource limits to the command. Returns a description of each limit.
+    pub(super) fn limit(
+        command: &mut tokio::process::Command,
+        limits: &ResourceLimits,
+    ) -> Vec<String> {
+        const MIB: u64 = 1024 * 1024;
+        let rlimits: Vec<_> = [
+            (libc::RLIMIT_AS, limits.memory_mb.map(|mb| mb * MIB)),
+            (libc::RLIMIT_CPU, limits.cpu_seconds),
+            (libc::RLIMIT_FSIZE, limits.file_size_mb.map(|mb| mb * MIB)),
+            (libc::RLIMIT_NPROC, limits.processes),
+        ]
+        .into_iter()
+        .filter_map(|(resource, value)| Some((resource, value? as libc::rlim_t)))
+        .collect();
+        if rlimits.is_empty() {
+            return Vec::new();
+        }
+        let setup = move || -> Result<(), Error> {
+            for (resource, value) in &rlimits {
+                let limit = libc::rlimit {
+                    rlim_cur: *value,
+                    rlim_max: *value,
+                };
+                if unsafe { libc::setrlimit(*resource, &limit) } != 0 {
+                    return Err(Error::last_os_error());
+                }
+            }
+            Ok(())
+        };
+        unsafe { command.pre_exec(setup) };
+
+        let mut description = Vec::new();
+        if let Some(mb) = limits.memory_mb {
+            description.push(format!("memory limit {} MiB", mb));
+        }
+        if let Some(seconds) = limits.cpu_seconds {
+            description.push(format!("CPU time limit {} s", seconds));
+        }
+        if let Some(mb) = limits.file_size_mb {
+            description.push(format!("file size limit {} MiB", mb));
+        }
+        if let Some(processes) = limits.processes {
+            description.push(format!("process limit {}", processes));
+        }
+        description
+    }
+
+    /// Write a whole buffer to a file without allocating, for use between fork and exec.
+    unsafe fn write_file(path: &std::ffi::CStr, content: &[u8]) -> Result<(), Error> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(Error::last_os_error());
+            }
+            let written = libc::write(fd, content.as_ptr().cast(), content.len());
+            libc::close(fd);
+            if written != content.len() as isize {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a Landlock ruleset allowing reads and execution everywhere and writes under `writable` and `/dev`.
+    /// Returns `None` if the kernel does not support Landlock.
+    fn landlock_ruleset(writable: &[PathBuf]) -> Result<Option<(OwnedFd, i64)>, Error> {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Ok(None);
+        }
+        let mut handled = ACCESS_FS_REFER - 1;
+        if abi >= 2 {
+            handled |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled |= ACCESS_FS_TRUNCATE;
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr,
+                size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        let read = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+        add_rule(&ruleset, Path::new("/"), read)?;
+        add_rule(&ruleset, Path::new("/dev"), handled)?;
+        for path in writable {
+            add_rule(&ruleset, path, handled)?;
+        }
+        Ok(Some((ruleset, abi)))
+    }
+
+    fn add_rule(ruleset: &OwnedFd, path: &Path, access: u64) -> Result<(), Error> {
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes()).map_err(Error::other)?;
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let parent = unsafe { OwnedFd::from_raw_fd(fd) };
+        let attr = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: parent.as_raw_fd(),
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr,
+                0,
+            )
+        };
+        if result != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod linux {
+    use std::io::Error;
+    use std::path::PathBuf;
+
+    use super::ResourceLimits;
+
+    pub(super) fn isolate(
+        _command: &mut tokio::process::Command,
+        _network: bool,
+        _writable: &[PathBuf],
+    ) -> Result<(Vec<String>, bool), Error> {
+        Err(Error::other("Sandboxing is only supported on Linux"))
+    }
+
+    pub(super) fn filter_syscalls(_command: &mut tokio::process::Command) -> bool {
+        false
+    }
+
+    pub(super) fn seccomp_file() -> Result<Option<std::os::fd::OwnedFd>, Error> {
+        Ok(None)
+    }
+
+    pub(super) fn inherit(_command: &mut tokio::process::Command, _fd: std::os::fd::OwnedFd) {}
+
+    pub(super) fn limit(
+        _command: &mut tokio::process::Command,
+        _limits: &ResourceLimits,
+    ) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,73 @@
+//! Filesystem scoping for the programs, core files and scripts loaded into debugger sessions.
+//!
+//! Paths are resolved against the client's workspace root (its first MCP root) and canonicalized, so symlinks
+//! and `..` cannot be used to step outside the allowed roots.
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Directories that sessions may load files from.
+/// Without configured roots and with `client_roots` off, any path is accepted.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesystemScope {
+    /// Loaded files must be inside one of these directories.
+    pub allowed_roots: Vec<PathBuf>,
+    /// Also allow the roots announced by the MCP client. If the client announces none, nothing is allowed
+    /// besides `allowed_roots`.
+    pub client_roots: bool,
+}
+
+impl FilesystemScope {
+    /// Whether loaded files are restricted to allowed roots.
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed_roots.is_empty() || self.client_roots
+    }
+
+    /// Resolve `path` and check it against the allowed roots.
+    /// Relative paths are taken relative to `workspace`, or the current directory if the client has no workspace.
+    /// `client_roots` are the roots announced by the client.
+    pub fn resolve(
+        &self,
+        path: &str,
+        workspace: Option<&Path>,
+        client_roots: &[PathBuf],
+    ) -> Result<PathBuf, String> {
+        let joined = match workspace {
+            Some(workspace) => workspace.join(path),
+            None => PathBuf::from(path),
+        };
+        let resolved = joined
+            .canonicalize()
+            .map_err(|err| format!("Failed to resolve path {}. [Error]: {}", path, err))?;
+        if !self.is_restricted() {
+            return Ok(resolved);
+        }
+
+        let client_roots = if self.client_roots { client_roots } else { &[] };
+        let roots: Vec<PathBuf> = self
+            .allowed_roots
+            .iter()
+            .chain(client_roots)
+            .filter_map(|root| root.canonicalize().ok())
+            .collect();
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else if roots.is_empty() {
+            Err(format!(
+                "Path {} is not allowed, no allowed roots are available",
+                resolved.display()
+            ))
+        } else {
+            Err(format!(
+                "Path {} is outside the allowed roots: {}",
+                resolved.display(),
+                roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+    }
+}
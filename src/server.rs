@@ -0,0 +1,1096 @@
+//! Multiplexed MCP server that serves any subset of the debugger backends from one process.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Write;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+use rmcp::{
+    Error as McpError, RoleServer, ServerHandler,
+    model::{
+        AnnotateAble, CallToolRequestParam, CallToolResult, GetPromptRequestParam, GetPromptResult,
+        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+        LoggingLevel, PaginatedRequestParam, Prompt, PromptArgument, PromptMessage,
+        PromptMessageRole, RawResource, RawResourceTemplate, ReadResourceRequestParam,
+        ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo, SetLevelRequestParam,
+        SubscribeRequestParam, UnsubscribeRequestParam,
+    },
+    service::RequestContext,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    CLIDebugSession, LineObserver,
+    audit::{self, AuditLog},
+    client::Client,
+    gdb::GdbServer,
+    lldb::LldbServer,
+    mi,
+    pdb::PdbServer,
+    policy::{self, Decision, Policy, PolicyViolation},
+    progress::{self, ToolCall},
+    prompts::Prompts,
+    quota::{QuotaExceeded, Quotas, Usage},
+    registers::RegisterHistory,
+    remote::Connection,
+    resources::{self, Activity, ResourceKind},
+    sandbox::Sandbox,
+    scope::FilesystemScope,
+    watchpoints::Watchpoints,
+};
+
+/// A debugger backend that can be served by [`DbgServer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Gdb,
+    Lldb,
+    Pdb,
+}
+
+impl Backend {
+    /// All supported backends, in the order their tools are listed.
+    pub const ALL: [Backend; 3] = [Backend::Gdb, Backend::Lldb, Backend::Pdb];
+
+    /// Name of the backend as used on the command line and as tool name prefix.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Gdb => "gdb",
+            Backend::Lldb => "lldb",
+            Backend::Pdb => "pdb",
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .into_iter()
+            .find(|backend| backend.name() == s.trim())
+            .ok_or(format!(
+                "Unknown backend '{}'. Expected one of gdb, lldb, pdb",
+                s
+            ))
+    }
+}
+
+impl Backend {
+    /// The program output in a line of debugger output that is not an event, if it is any.
+    /// GDB/MI tells program output apart from its own records, LLDB and PDB do not.
+    fn program_output(&self, line: &str) -> Option<String> {
+        match self {
+            Backend::Gdb => {
+                let record = line.trim_start_matches(|c: char| c.is_ascii_digit());
+                if let Some(stream) = record.strip_prefix('@') {
+                    let text = mi::c_string(stream).unwrap_or_else(|_| stream.to_owned());
+                    return Some(text.trim_end_matches('\n').to_owned());
+                }
+                let is_record = record.starts_with(['^', '*', '+', '=', '~', '&'])
+                    || record.starts_with("(gdb)");
+                (!is_record).then(|| line.to_owned())
+            }
+            Backend::Lldb => Some(line.trim_start_matches("(lldb) ").to_owned()),
+            Backend::Pdb => {
+                let line = line.trim_start_matches("(Pdb) ");
+                // Current location and source line printed whenever PDB stops.
+                let is_location = line.starts_with("> ") || line.starts_with("-> ");
+                (!is_location).then(|| line.to_owned())
+            }
+        }
+    }
+
+    /// Thread an event line is about: its thread ID, or `all` when it concerns all threads. Only GDB/MI tells, which
+    /// matters in non-stop mode, where threads stop and run independently of each other.
+    fn event_thread(&self, line: &str) -> Option<String> {
+        match self {
+            Backend::Gdb => {
+                let record = mi::async_record(line)?;
+                let field = match record.class.as_str() {
+                    "stopped"
+                        if record.get("stopped-threads").and_then(Value::as_str) == Some("all") =>
+                    {
+                        return Some("all".to_owned());
+                    }
+                    "stopped" | "running" => "thread-id",
+                    "thread-exited" => "id",
+                    _ => return None,
+                };
+                record.get(field)?.as_str().map(str::to_owned)
+            }
+            Backend::Lldb | Backend::Pdb => None,
+        }
+    }
+
+    /// Command listing the breakpoints of a session.
+    fn breakpoints_command(&self) -> &'static str {
+        match self {
+            Backend::Gdb => "-break-list",
+            Backend::Lldb => "breakpoint list",
+            Backend::Pdb => "break",
+        }
+    }
+}
+
+/// A debugger session together with the settings it was started with.
+/// Dereferences to the underlying [`CLIDebugSession`].
+pub struct Session {
+    debugger: CLIDebugSession,
+    /// Only inspection commands are allowed in this session.
+    pub read_only: bool,
+    transcript: Vec<String>,
+    usage: Usage,
+    activity: Arc<std::sync::Mutex<Activity>>,
+    /// Registers read at earlier stops, for backends that cannot tell which registers changed.
+    pub registers: RegisterHistory,
+    /// Remote target the debugger is connected to.
+    pub remote: Option<Connection>,
+    /// GDB runs in non-stop mode: threads stop and continue independently of each other.
+    pub non_stop: bool,
+    /// Watchpoints set through the tools and the hits collected for them.
+    pub watchpoints: Watchpoints,
+}
+
+impl Session {
+    /// `activity` must be the one kept up to date by the session's output observer, see [`observe_session`].
+    pub fn new(
+        debugger: CLIDebugSession,
+        read_only: bool,
+        activity: Arc<std::sync::Mutex<Activity>>,
+    ) -> Self {
+        Self {
+            debugger,
+            read_only,
+            transcript: Vec::new(),
+            usage: Usage::default(),
+            activity,
+            registers: RegisterHistory::default(),
+            remote: None,
+            non_stop: false,
+            watchpoints: Watchpoints::default(),
+        }
+    }
+
+    /// Add a timestamped entry to the transcript of this session.
+    pub fn record<S: Display>(&mut self, entry: S) {
+        self.transcript
+            .push(format!("[{}] {}", chrono::Utc::now().to_rfc3339(), entry));
+        self.activity
+            .lock()
+            .unwrap()
+            .changed(ResourceKind::Transcript);
+    }
+
+    /// Execute a command, see [`CLIDebugSession::execute_command`], and record it and its output in the transcript.
+    pub async fn execute_command<S: AsRef<str>>(
+        &mut self,
+        command: S,
+    ) -> Result<String, std::io::Error> {
+        self.record(format!("> {}", command.as_ref()));
+        let result = self.debugger.execute_command(command).await;
+        self.record_result(&result);
+        result
+    }
+
+    /// Read until `pattern`, see [`CLIDebugSession::read_response_until`], and record the output in the transcript.
+    pub async fn read_response_until<S: AsRef<str>>(
+        &mut self,
+        pattern: Option<S>,
+        timeout: Duration,
+    ) -> Result<String, std::io::Error> {
+        let result = self.debugger.read_response_until(pattern, timeout).await;
+        self.record_result(&result);
+        result
+    }
+
+    fn record_result(&mut self, result: &Result<String, std::io::Error>) {
+        match result {
+            Ok(output) => self.record(output.trim_end()),
+            Err(err) => self.record(format!("Error: {}", err)),
+        }
+    }
+
+    /// Execute a command for the server itself, such as listing breakpoints for a resource, without recording it.
+    pub async fn query(&mut self, command: &str) -> Result<String, std::io::Error> {
+        self.debugger.execute_command(command).await
+    }
+
+    /// What the session has been doing, as told by its output.
+    pub fn activity(&self) -> &Arc<std::sync::Mutex<Activity>> {
+        &self.activity
+    }
+
+    /// Check the quotas of this session before it runs another command, see [`Usage::admit`].
+    pub fn admit(&mut self, quotas: &Quotas) -> Result<(), QuotaExceeded> {
+        let stub = self.remote.as_ref().and_then(Connection::stub_pid);
+        self.usage.admit(quotas, &self.debugger, stub)
+    }
+
+    /// Entries recorded in this session, oldest first.
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+}
+
+impl Deref for Session {
+    type Target = CLIDebugSession;
+
+    fn deref(&self) -> &Self::Target {
+        &self.debugger
+    }
+}
+
+impl DerefMut for Session {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.debugger
+    }
+}
+
+/// Parser recognizing asynchronous debugger events in output lines, returning their log level and kind.
+pub type EventParser = fn(&str) -> Option<(LoggingLevel, &'static str)>;
+
+/// Observer keeping the [`Activity`] of a session up to date from its output, and forwarding the events `parse`
+/// recognizes to `client` as log notifications. The notifications are logged by the session ID and carry the event
+/// kind, the thread it is about if the backend tells, and the output line.
+pub fn observe_session(
+    client: Client,
+    backend: Backend,
+    session_id: &str,
+    activity: Arc<std::sync::Mutex<Activity>>,
+    parse: EventParser,
+) -> LineObserver {
+    let session_id = session_id.to_owned();
+    Arc::new(move |line| {
+        if let Some((level, event)) = parse(line) {
+            let thread = backend.event_thread(line);
+            activity
+                .lock()
+                .unwrap()
+                .event(event, line, thread.as_deref());
+            let mut data = json!({ "session_id": session_id, "event": event, "text": line });
+            if let Some(thread) = thread {
+                data["thread"] = thread.into();
+            }
+            client.log(level, &session_id, data);
+        } else if let Some(output) = backend.program_output(line) {
+            activity.lock().unwrap().program_output(&output);
+        }
+    })
+}
+
+/// Debugger sessions of one backend, keyed by session ID.
+pub type SessionMap = Arc<tokio::sync::Mutex<HashMap<String, Session>>>;
+
+/// Namespace used when clients are not authenticated.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Session maps of one backend, one per client namespace.
+/// Clients only see sessions of their own namespace, see [`DbgServer::in_namespace`].
+#[derive(Clone, Default)]
+pub struct SessionNamespaces {
+    maps: Arc<std::sync::Mutex<HashMap<String, SessionMap>>>,
+}
+
+impl SessionNamespaces {
+    /// Get the session map of a namespace, creating it on first use.
+    pub fn get(&self, namespace: &str) -> SessionMap {
+        self.maps
+            .lock()
+            .unwrap()
+            .entry(namespace.to_owned())
+            .or_default()
+            .clone()
+    }
+}
+
+/// Limits the number of debugger sessions that can be alive at the same time across all backends, in total and per
+/// client namespace.
+#[derive(Clone, Default)]
+pub struct SessionLimits {
+    active: Arc<AtomicUsize>,
+    max_sessions: Option<usize>,
+    per_client: Arc<std::sync::Mutex<HashMap<String, usize>>>,
+    max_per_client: Option<usize>,
+}
+
+/// A reserved place for a new session. The reservation is given back when dropped unless [`SessionSlot::commit`] is called.
+pub struct SessionSlot<'a> {
+    limits: &'a SessionLimits,
+    namespace: String,
+    committed: bool,
+}
+
+impl SessionLimits {
+    /// Creates a new [`SessionLimits`] instance. `None` means no limit.
+    pub fn new(max_sessions: Option<usize>, max_per_client: Option<usize>) -> Self {
+        Self {
+            max_sessions,
+            max_per_client,
+            ..Default::default()
+        }
+    }
+
+    /// Reserve a place for a new session of the given client namespace, failing if a limit is already reached.
+    pub fn acquire(&self, namespace: &str) -> Result<SessionSlot<'_>, String> {
+        let max = self.max_sessions.unwrap_or(usize::MAX);
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < max).then_some(active + 1)
+            })
+            .map_err(|active| QuotaExceeded {
+                limit: "max_sessions",
+                value: max as u64,
+                used: format!("{} active sessions", active),
+                resets: "when an existing session is terminated".to_owned(),
+            })?;
+
+        let mut per_client = self.per_client.lock().unwrap();
+        let active = per_client.entry(namespace.to_owned()).or_default();
+        if let Some(max) = self.max_per_client
+            && *active >= max
+        {
+            let exceeded = QuotaExceeded {
+                limit: "max_sessions_per_client",
+                value: max as u64,
+                used: format!("{} active sessions in namespace {}", active, namespace),
+                resets: "when one of these sessions is terminated".to_owned(),
+            };
+            drop(per_client);
+            self.release_global();
+            return Err(exceeded.into());
+        }
+        *active += 1;
+        Ok(SessionSlot {
+            limits: self,
+            namespace: namespace.to_owned(),
+            committed: false,
+        })
+    }
+
+    /// Give back the place held by a terminated session of the given client namespace.
+    pub fn release(&self, namespace: &str) {
+        if let Some(active) = self.per_client.lock().unwrap().get_mut(namespace) {
+            *active = active.saturating_sub(1);
+        }
+        self.release_global();
+    }
+
+    fn release_global(&self) {
+        let _ = self
+            .active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                active.checked_sub(1)
+            });
+    }
+}
+
+impl SessionSlot<'_> {
+    /// Keep the reservation. It must later be given back with [`SessionLimits::release`].
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for SessionSlot<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.limits.release(&self.namespace);
+        }
+    }
+}
+
+/// Line oriented diagnostic log shared by all backends.
+/// Writes to stderr by default, since stdout carries the MCP stdio transport.
+#[derive(Clone)]
+pub struct Logger {
+    file: Option<Arc<std::sync::Mutex<std::fs::File>>>,
+}
+
+impl Logger {
+    /// Creates a logger that writes to stderr.
+    pub fn stderr() -> Self {
+        Self { file: None }
+    }
+
+    /// Creates a logger that appends to the given file.
+    pub fn file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, std::io::Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: Some(Arc::new(std::sync::Mutex::new(file))),
+        })
+    }
+
+    /// Write a single log line. Failures to write are ignored.
+    pub fn log<S: Display>(&self, message: S) {
+        let line = format!("[{}] {}\n", chrono::Utc::now().to_rfc3339(), message);
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    let _ = file.write_all(line.as_bytes());
+                }
+            }
+            None => {
+                let _ = std::io::stderr().write_all(line.as_bytes());
+            }
+        }
+    }
+}
+
+/// State shared by every backend served from the same process.
+#[derive(Clone)]
+pub struct ServerContext {
+    pub limits: SessionLimits,
+    pub logger: Logger,
+    pub policy: Arc<Policy>,
+    pub filesystem: Arc<FilesystemScope>,
+    pub sandbox: Arc<Sandbox>,
+    pub quotas: Arc<Quotas>,
+    pub audit: AuditLog,
+    pub prompts: Arc<Prompts>,
+}
+
+impl ServerContext {
+    /// Check a command against the session's quotas and the policy before it is sent to the session.
+    /// Commands matching an approve rule are shown to the user through `client` and only pass once approved.
+    /// The session map is not locked while waiting for the answer, so other sessions stay usable.
+    pub async fn authorize(
+        &self,
+        client: &Client,
+        backend: Backend,
+        sessions: &SessionMap,
+        session_id: &str,
+        command: &str,
+    ) -> Result<(), String> {
+        let read_only = {
+            let mut sessions = sessions.lock().await;
+            let session = sessions.get_mut(session_id).ok_or(format!(
+                "Session with ID {} not found. Start a new session",
+                session_id
+            ))?;
+            session.admit(&self.quotas).inspect_err(|exceeded| {
+                audit::add_decision(Some(command), format!("limited: {}", exceeded.limit));
+            })?;
+            session.read_only
+        };
+        let decision = self
+            .policy
+            .check(backend, command, read_only)
+            .inspect_err(|violation| {
+                audit::add_decision(Some(command), format!("denied: {}", violation.rule));
+            })?;
+        if self.filesystem.is_restricted() {
+            // Paths are checked relative to the workspace, where the debugger has to stay.
+            if let Some(line) = policy::directory_change(backend, command) {
+                audit::add_decision(Some(command), "denied: working directory change");
+                return Err(format!(
+                    "Changing the working directory is not allowed when loaded files are restricted to allowed roots. [Command]: {}",
+                    line
+                ));
+            }
+            for path in policy::path_arguments(backend, command) {
+                self.resolve_path(client, &path).await?;
+            }
+        }
+        let Decision::NeedsApproval(rule) = decision else {
+            audit::add_decision(Some(command), "allowed");
+            return Ok(());
+        };
+
+        let message = format!(
+            "Allow {} session {} to run the command below? It matches the approve rule '{}'.\n\n{}",
+            backend.name(),
+            session_id,
+            rule,
+            command
+        );
+        let answer = client.confirm(&message).await;
+        let outcome = match &answer {
+            Ok(true) => "approved".to_owned(),
+            Ok(false) => "rejected by the user".to_owned(),
+            Err(err) => format!("not answered: {}", err),
+        };
+        if let Some(session) = sessions.lock().await.get_mut(session_id) {
+            session.record(format!(
+                "Approval of command '{}' (rule '{}'): {}",
+                command, rule, outcome
+            ));
+        }
+        self.logger.log(format!(
+            "{} session {}: command '{}' {}",
+            backend.name(),
+            session_id,
+            command,
+            outcome
+        ));
+        audit::add_decision(Some(command), format!("approve '{}': {}", rule, outcome));
+        match answer {
+            Ok(true) => Ok(()),
+            _ => Err(PolicyViolation {
+                backend,
+                command: command.trim().to_owned(),
+                rule: format!("approve '{}' {}", rule, outcome),
+            }
+            .into()),
+        }
+    }
+
+    /// Resolve a path given by the client against its workspace and check it against the filesystem scope.
+    pub async fn resolve_path(&self, client: &Client, path: &str) -> Result<PathBuf, String> {
+        let roots = self.client_roots(client).await;
+        self.filesystem
+            .resolve(path, roots.first().map(PathBuf::as_path), &roots)
+            .inspect_err(|err| audit::add_decision(None, format!("denied: {}", err)))
+    }
+
+    /// Directory new debugger processes start in: the client's workspace root, if it has one.
+    pub async fn workspace(&self, client: &Client) -> Option<PathBuf> {
+        self.client_roots(client).await.into_iter().next()
+    }
+
+    async fn client_roots(&self, client: &Client) -> Vec<PathBuf> {
+        client.roots().await.unwrap_or_else(|err| {
+            self.logger
+                .log(format!("Failed to list the client's roots: {}", err));
+            Vec::new()
+        })
+    }
+}
+
+/// MCP server serving the tools of one or more debugger backends.
+#[derive(Clone)]
+pub struct DbgServer {
+    gdb: Option<GdbServer>,
+    lldb: Option<LldbServer>,
+    pdb: Option<PdbServer>,
+    context: ServerContext,
+    namespace: String,
+    client: Client,
+}
+
+impl DbgServer {
+    /// Creates a new [`DbgServer`] instance serving the given backends.
+    pub fn new(backends: &[Backend], context: ServerContext) -> Self {
+        let enabled = |backend| backends.contains(&backend);
+        Self {
+            gdb: enabled(Backend::Gdb).then(|| GdbServer::new(context.clone())),
+            lldb: enabled(Backend::Lldb).then(|| LldbServer::new(context.clone())),
+            pdb: enabled(Backend::Pdb).then(|| PdbServer::new(context.clone())),
+            context,
+            namespace: DEFAULT_NAMESPACE.to_owned(),
+            client: Client::default(),
+        }
+    }
+
+    /// Returns a handle to this server that only sees the sessions of the given client namespace.
+    pub fn in_namespace(&self, namespace: &str) -> Self {
+        Self {
+            gdb: self
+                .gdb
+                .as_ref()
+                .map(|server| server.in_namespace(namespace)),
+            lldb: self
+                .lldb
+                .as_ref()
+                .map(|server| server.in_namespace(namespace)),
+            pdb: self
+                .pdb
+                .as_ref()
+                .map(|server| server.in_namespace(namespace)),
+            namespace: namespace.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle to this server that talks to the given client, for example to ask for approvals.
+    pub fn with_client(&self, client: Client) -> Self {
+        Self {
+            gdb: self
+                .gdb
+                .as_ref()
+                .map(|server| server.with_client(client.clone())),
+            lldb: self
+                .lldb
+                .as_ref()
+                .map(|server| server.with_client(client.clone())),
+            pdb: self
+                .pdb
+                .as_ref()
+                .map(|server| server.with_client(client.clone())),
+            client,
+            ..self.clone()
+        }
+    }
+
+    fn instructions(&self) -> Vec<String> {
+        [
+            self.gdb.as_ref().map(|server| server.get_info()),
+            self.lldb.as_ref().map(|server| server.get_info()),
+            self.pdb.as_ref().map(|server| server.get_info()),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|info| info.instructions)
+        .collect()
+    }
+}
+
+impl ServerHandler for DbgServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            instructions: Some(self.instructions().join(", ")),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_tools()
+                .build(),
+            ..Default::default()
+        }
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.client.set_log_level(request.level);
+        Ok(())
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let prompts = self
+            .context
+            .prompts
+            .templates(&self.backends())
+            .into_iter()
+            .map(|(name, template)| {
+                let arguments = template
+                    .arguments
+                    .into_iter()
+                    .map(|argument| PromptArgument {
+                        name: argument.name,
+                        description: argument.description,
+                        required: Some(argument.required),
+                    })
+                    .collect();
+                Prompt::new(name, template.description, Some(arguments))
+            })
+            .collect();
+        Ok(ListPromptsResult {
+            prompts,
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let served = self.backends();
+        let templates = self.context.prompts.templates(&served);
+        let template = templates.get(&request.name).ok_or_else(|| {
+            McpError::invalid_params(format!("Prompt {} not found", request.name), None)
+        })?;
+        let text = template
+            .render(
+                &request.name,
+                &request.arguments.unwrap_or_default(),
+                &served,
+            )
+            .map_err(|err| McpError::invalid_params(err, None))?;
+        Ok(GetPromptResult {
+            description: template.description.clone(),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+        })
+    }
+
+    async fn list_resources(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let mut resources = Vec::new();
+        for (_, sessions) in self.session_maps() {
+            let mut session_ids: Vec<String> = sessions.lock().await.keys().cloned().collect();
+            session_ids.sort();
+            for session_id in session_ids {
+                resources.extend(ResourceKind::ALL.into_iter().map(|kind| {
+                    RawResource {
+                        description: Some(kind.description().to_owned()),
+                        mime_type: Some(kind.mime_type().to_owned()),
+                        ..RawResource::new(
+                            kind.uri(&session_id),
+                            format!("{} {}", session_id, kind.name()),
+                        )
+                    }
+                    .no_annotation()
+                }));
+            }
+        }
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor: None,
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let resource_templates = ResourceKind::ALL
+            .into_iter()
+            .map(|kind| {
+                RawResourceTemplate {
+                    uri_template: kind.uri_template(),
+                    name: format!("Session {}", kind.name()),
+                    description: Some(kind.description().to_owned()),
+                    mime_type: Some(kind.mime_type().to_owned()),
+                }
+                .no_annotation()
+            })
+            .collect();
+        Ok(ListResourceTemplatesResult {
+            resource_templates,
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let (backend, sessions, session_id, kind) = self.resolve_resource(&request.uri)?;
+        let mut sessions = sessions.lock().await;
+        let session = sessions.get_mut(session_id).ok_or_else(|| {
+            McpError::resource_not_found(format!("Session with ID {} not found", session_id), None)
+        })?;
+        let text = match kind {
+            ResourceKind::Transcript => session
+                .transcript()
+                .iter()
+                .map(|entry| format!("{}\n", entry))
+                .collect(),
+            ResourceKind::ProgramOutput => session.activity().lock().unwrap().program_output_text(),
+            ResourceKind::Breakpoints => session
+                .query(backend.breakpoints_command())
+                .await
+                .map_err(|err| {
+                    McpError::internal_error(
+                        format!("Failed to list breakpoints. [Error]: {}", err),
+                        None,
+                    )
+                })?,
+            ResourceKind::State => {
+                let mut state = session.activity().lock().unwrap().state_json();
+                state["session_id"] = session_id.into();
+                state["backend"] = backend.name().into();
+                state["read_only"] = session.read_only.into();
+                state["sandbox"] = session.sandbox().into();
+                state["pid"] = session.pid().into();
+                state["output_bytes"] = session.output_bytes().into();
+                if let Some(remote) = &session.remote {
+                    state["remote"] = remote.to_json();
+                }
+                state.to_string()
+            }
+        };
+        session
+            .activity()
+            .lock()
+            .unwrap()
+            .read_by(&self.client, &request.uri);
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                // rmcp 0.1 serializes this field as `mime_type` instead of `mimeType`, the resource list has it.
+                mime_type: None,
+                text,
+            }],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let (_, sessions, session_id, _) = self.resolve_resource(&request.uri)?;
+        let sessions = sessions.lock().await;
+        let session = sessions.get(session_id).ok_or_else(|| {
+            McpError::resource_not_found(format!("Session with ID {} not found", session_id), None)
+        })?;
+        session
+            .activity()
+            .lock()
+            .unwrap()
+            .subscribe(&self.client, &request.uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let (_, sessions, session_id, _) = self.resolve_resource(&request.uri)?;
+        if let Some(session) = sessions.lock().await.get(session_id) {
+            session
+                .activity()
+                .lock()
+                .unwrap()
+                .unsubscribe(&self.client, &request.uri);
+        }
+        Ok(())
+    }
+
+    async fn list_tools(
+        &self,
+        request: PaginatedRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut tools = Vec::new();
+        if let Some(server) = &self.gdb {
+            tools.extend(
+                server
+                    .list_tools(request.clone(), context.clone())
+                    .await?
+                    .tools,
+            );
+        }
+        if let Some(server) = &self.lldb {
+            tools.extend(
+                server
+                    .list_tools(request.clone(), context.clone())
+                    .await?
+                    .tools,
+            );
+        }
+        if let Some(server) = &self.pdb {
+            tools.extend(
+                server
+                    .list_tools(request.clone(), context.clone())
+                    .await?
+                    .tools,
+            );
+        }
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let call = ToolCall::new(
+            self.client.clone(),
+            self.client.take_progress_token(&context.id),
+            context.ct.clone(),
+        );
+        let entry = self
+            .context
+            .audit
+            .is_enabled()
+            .then(|| self.audit_entry(&request));
+        let dispatch = progress::scope(call, self.dispatch(request, context));
+        let Some(entry) = entry else {
+            return dispatch.await;
+        };
+        let (result, decisions) = audit::track_decisions(dispatch).await;
+        self.audit(entry, decisions, &result);
+        result
+    }
+}
+
+impl DbgServer {
+    /// The served backends.
+    fn backends(&self) -> Vec<Backend> {
+        self.session_maps()
+            .into_iter()
+            .map(|(backend, _)| backend)
+            .collect()
+    }
+
+    /// Session maps of the served backends, in the client namespace of this handle.
+    fn session_maps(&self) -> Vec<(Backend, &SessionMap)> {
+        [
+            self.gdb
+                .as_ref()
+                .map(|server| (Backend::Gdb, server.sessions())),
+            self.lldb
+                .as_ref()
+                .map(|server| (Backend::Lldb, server.sessions())),
+            self.pdb
+                .as_ref()
+                .map(|server| (Backend::Pdb, server.sessions())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Find the backend and session map serving a resource URI, together with its session ID and resource.
+    fn resolve_resource<'a>(
+        &self,
+        uri: &'a str,
+    ) -> Result<(Backend, &SessionMap, &'a str, ResourceKind), McpError> {
+        let (session_id, kind) =
+            resources::parse_uri(uri).map_err(|err| McpError::invalid_params(err, None))?;
+        let (backend, sessions) = session_id
+            .split_once('-')
+            .and_then(|(prefix, _)| prefix.parse::<Backend>().ok())
+            .and_then(|backend| {
+                self.session_maps()
+                    .into_iter()
+                    .find(|(served, _)| *served == backend)
+            })
+            .ok_or_else(|| {
+                McpError::resource_not_found(
+                    format!("Session with ID {} not found", session_id),
+                    None,
+                )
+            })?;
+        Ok((backend, sessions, session_id, kind))
+    }
+
+    /// Route a tool call to the backend serving it.
+    async fn dispatch(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let backend = request
+            .name
+            .split_once('_')
+            .and_then(|(prefix, _)| prefix.parse::<Backend>().ok());
+        match backend {
+            Some(Backend::Gdb) => {
+                if let Some(server) = &self.gdb {
+                    return server.call_tool(request, context).await;
+                }
+            }
+            Some(Backend::Lldb) => {
+                if let Some(server) = &self.lldb {
+                    return server.call_tool(request, context).await;
+                }
+            }
+            Some(Backend::Pdb) => {
+                if let Some(server) = &self.pdb {
+                    return server.call_tool(request, context).await;
+                }
+            }
+            None => {}
+        }
+        Err(McpError::invalid_params(
+            format!("Tool {} is not served by this server", request.name),
+            None,
+        ))
+    }
+
+    /// Fields of the audit record of a tool call known before it runs.
+    fn audit_entry(&self, request: &CallToolRequestParam) -> Map<String, Value> {
+        let argument = |name: &str| {
+            request
+                .arguments
+                .as_ref()
+                .and_then(|arguments| arguments.get(name))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let mut entry = Map::new();
+        entry.insert("namespace".to_owned(), self.namespace.clone().into());
+        entry.insert("connection".to_owned(), self.client.connection().into());
+        entry.insert("client".to_owned(), self.client.info().unwrap_or_default());
+        entry.insert("tool".to_owned(), request.name.to_string().into());
+        entry.insert("session_id".to_owned(), argument("session_id"));
+        entry.insert("command".to_owned(), argument("command"));
+        entry.insert(
+            "arguments".to_owned(),
+            request
+                .arguments
+                .clone()
+                .map(Value::Object)
+                .unwrap_or_default(),
+        );
+        entry
+    }
+
+    /// Complete the audit record of a tool call with the commands it ran, their policy decisions and the outcome,
+    /// and write it.
+    fn audit(
+        &self,
+        mut entry: Map<String, Value>,
+        decisions: Vec<audit::Decision>,
+        result: &Result<CallToolResult, McpError>,
+    ) {
+        let (outcome, error) = match result {
+            Ok(result) if result.is_error != Some(true) => ("ok", None),
+            Ok(result) => (
+                "error",
+                result
+                    .content
+                    .iter()
+                    .find_map(|content| content.as_text())
+                    .map(|text| text.text.clone()),
+            ),
+            Err(err) => ("error", Some(err.message.to_string())),
+        };
+        // Tools stop at the first command that is not allowed, so the last decision is the one that counted.
+        let commands: Vec<&str> = decisions
+            .iter()
+            .filter_map(|decision| decision.command.as_deref())
+            .collect();
+        if !commands.is_empty() {
+            entry.insert("command".to_owned(), commands.join("\n").into());
+        }
+        entry.insert(
+            "decision".to_owned(),
+            decisions
+                .last()
+                .map(|decision| decision.decision.clone())
+                .into(),
+        );
+        if decisions.len() > 1 {
+            let decisions: Vec<Value> = decisions
+                .into_iter()
+                .map(|decision| {
+                    mi::compact(json!({
+                        "command": decision.command,
+                        "decision": decision.decision,
+                    }))
+                })
+                .collect();
+            entry.insert("decisions".to_owned(), decisions.into());
+        }
+        entry.insert("outcome".to_owned(), outcome.into());
+        if let Some(error) = error {
+            entry.insert("error".to_owned(), error.into());
+        }
+        if let Err(err) = self.context.audit.record(entry) {
+            self.context
+                .logger
+                .log(format!("Failed to write the audit log. [Error]: {}", err));
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Stdio transport: one client exchanging newline delimited JSON-RPC messages over stdin and stdout.
+use std::io::Error;
+
+use futures::StreamExt;
+use rmcp::ServerHandler;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{client::Client, server::Logger};
+
+/// Serve MCP over stdin and stdout until stdin is closed.
+/// The service is created by `make_service` with a handle to the connected client.
+pub async fn serve<S, F>(make_service: F, logger: Logger) -> Result<(), Error>
+where
+    S: ServerHandler,
+    F: FnOnce(Client) -> S,
+{
+    let (client, mut outgoing) = Client::new("stdio");
+    let service = make_service(client.clone());
+
+    tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = outgoing.next().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let lines = BufReader::new(tokio::io::stdin()).lines();
+    let incoming = futures::stream::unfold(lines, move |mut lines| {
+        let logger = logger.clone();
+        async move {
+            loop {
+                let line = lines.next_line().await.ok()??;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => return Some((message, lines)),
+                    Err(err) => logger.log(format!("Ignoring invalid JSON-RPC message: {}", err)),
+                }
+            }
+        }
+    });
+    crate::client::serve(service, client, Box::pin(incoming)).await
+}
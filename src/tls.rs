@@ -0,0 +1,169 @@
+//! TLS for the http transport, with optional client certificate authentication.
+//!
+//! Clients authenticated by a certificate are known by its name, see [`certificate_name`].
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        RootCertStore, ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::WebPkiClientVerifier,
+    },
+};
+
+/// Certificate and key the server presents, in PEM files.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Certificate chain of the server, leaf first.
+    pub certificate: PathBuf,
+    /// Private key of the certificate.
+    pub key: PathBuf,
+    /// CA certificates client certificates are verified against. When set, clients must present a certificate,
+    /// which authenticates them.
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Load the certificates and key into an acceptor for incoming connections.
+    pub fn acceptor(&self) -> Result<TlsAcceptor, String> {
+        let provider = Arc::new(ring::default_provider());
+        let certificates = load_certificates(&self.certificate)?;
+        let key = PrivateKeyDer::from_pem_file(&self.key).map_err(|err| {
+            format!(
+                "Failed to read the TLS key {}. [Error]: {}",
+                self.key.display(),
+                err
+            )
+        })?;
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|err| format!("Failed to configure TLS. [Error]: {}", err))?;
+        let builder = match &self.client_ca {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for certificate in load_certificates(path)? {
+                    roots.add(certificate).map_err(|err| {
+                        format!(
+                            "Invalid client CA certificate in {}. [Error]: {}",
+                            path.display(),
+                            err
+                        )
+                    })?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider)
+                    .build()
+                    .map_err(|err| {
+                        format!("Failed to configure client certificates. [Error]: {}", err)
+                    })?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(certificates, key)
+            .map_err(|err| format!("Invalid TLS certificate or key. [Error]: {}", err))?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Certificates of a PEM file. A file without any is an error.
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| {
+            format!(
+                "Failed to read certificates from {}. [Error]: {}",
+                path.display(),
+                err
+            )
+        })?;
+    if certificates.is_empty() {
+        return Err(format!("No certificates in {}", path.display()));
+    }
+    Ok(certificates)
+}
+
+/// Name of a client certificate: its first DNS name, or else the common name of its subject.
+pub fn certificate_name(certificate: &CertificateDer<'_>) -> Option<String> {
+    let certificate = webpki::EndEntityCert::try_from(certificate).ok()?;
+    if let Some(name) = certificate.valid_dns_names().next() {
+        return Some(name.to_owned());
+    }
+    common_name(certificate.subject())
+}
+
+/// DER encoded object identifier of the common name attribute, 2.5.4.3.
+const COMMON_NAME_OID: &[u8] = &[0x55, 0x04, 0x03];
+
+/// The common name in the contents of a DER encoded X.509 name, a sequence of sets of attributes.
+fn common_name(mut name: &[u8]) -> Option<String> {
+    while !name.is_empty() {
+        let (set, rest) = der_element(name, 0x31)?;
+        name = rest;
+        let mut set = set;
+        while !set.is_empty() {
+            let (attribute, rest) = der_element(set, 0x30)?;
+            set = rest;
+            let (oid, value) = der_element(attribute, 0x06)?;
+            if oid != COMMON_NAME_OID {
+                continue;
+            }
+            // UTF8String, PrintableString or IA5String
+            let (&tag, _) = value.split_first()?;
+            let (value, _) = der_element(value, tag)?;
+            return match tag {
+                0x0c | 0x13 | 0x16 => String::from_utf8(value.to_vec()).ok(),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+/// Split a DER element with the given tag off the front of `input`, returning its contents and the rest.
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&found, input) = input.split_first()?;
+    let (&length, mut input) = input.split_first()?;
+    if found != tag {
+        return None;
+    }
+    let length = if length < 0x80 {
+        length as usize
+    } else {
+        let bytes = (length & 0x7f) as usize;
+        if bytes == 0 || bytes > 4 || input.len() < bytes {
+            return None;
+        }
+        let (length, rest) = input.split_at(bytes);
+        input = rest;
+        length
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | *byte as usize)
+    };
+    (length <= input.len()).then(|| input.split_at(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_names_are_found() {
+        // C=XX, CN=alice, without the outer sequence
+        let name = [
+            0x31, 0x0b, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, b'X', b'X', 0x31,
+            0x0e, 0x30, 0x0c, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x05, b'a', b'l', b'i', b'c',
+            b'e',
+        ];
+        assert_eq!(common_name(&name).as_deref(), Some("alice"));
+        assert_eq!(common_name(&name[..13]), None);
+        assert_eq!(common_name(&name[..20]), None);
+        assert_eq!(common_name(&[]), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Watchpoints: their kinds, the hardware debug registers they take, and the hits recorded while collecting.
+//!
+//! Watchpoints are implemented with the debug registers of the CPU, of which there are few. The debuggers only find
+//! out that they ran out of them when the program resumes, with an error that does not tell which watchpoint is too
+//! many. Watchpoints set through the tools are counted against [`HARDWARE_SLOTS`] instead, so the client learns it
+//! when setting one.
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use serde_json::{Value, json};
+
+use crate::mi;
+
+/// Debug registers available for watchpoints, as on x86-64 and most AArch64 CPUs.
+pub const HARDWARE_SLOTS: usize = 4;
+
+/// Bytes a debug register can watch, at an address aligned to that size.
+pub const SLOT_BYTES: u64 = 8;
+
+/// Hits kept per session, the oldest are dropped first.
+const MAX_HITS: usize = 1000;
+
+/// Hits collected by default before collecting stops.
+pub const DEFAULT_COLLECT_HITS: u64 = 100;
+
+/// Frames of the backtrace recorded with every hit.
+pub const HIT_BACKTRACE_DEPTH: u64 = 8;
+
+/// Accesses a watchpoint stops on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+impl WatchKind {
+    pub const ALL: [WatchKind; 3] = [WatchKind::Write, WatchKind::Read, WatchKind::Access];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchKind::Write => "write",
+            WatchKind::Read => "read",
+            WatchKind::Access => "access",
+        }
+    }
+}
+
+impl FromStr for WatchKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WatchKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s.trim().to_ascii_lowercase())
+            .ok_or(format!(
+                "Unknown watchpoint kind '{}'. Expected one of write, read, access",
+                s
+            ))
+    }
+}
+
+/// What to watch, as asked by the client.
+pub enum Target {
+    /// An lvalue expression, such as a variable or a field.
+    Expression(String),
+    /// A range of the given length at an address expression.
+    Range(String, u64),
+}
+
+impl Target {
+    /// Pick the target from the tool arguments.
+    pub fn new(
+        expression: Option<String>,
+        address: Option<String>,
+        length: Option<u64>,
+    ) -> Result<Self, String> {
+        match (expression, address, length) {
+            (Some(_), Some(_), _) | (Some(_), None, Some(_)) => {
+                Err("Give either an expression or an address and length, not both".to_owned())
+            }
+            (Some(expression), None, None) => Ok(Target::Expression(expression)),
+            (None, Some(_), Some(0)) => Err("The length must be at least 1".to_owned()),
+            (None, Some(address), length) => Ok(Target::Range(address, length.unwrap_or(1))),
+            (None, None, _) => Err("Give an expression or an address to watch".to_owned()),
+        }
+    }
+}
+
+/// Debug registers needed to watch `length` bytes at `address`: one per aligned block of [`SLOT_BYTES`] touched.
+pub fn slots(address: u64, length: u64) -> usize {
+    let first = address / SLOT_BYTES;
+    let last = address.saturating_add(length.max(1) - 1) / SLOT_BYTES;
+    (last - first + 1) as usize
+}
+
+/// A watchpoint trigger recorded while collecting.
+pub struct Hit {
+    pub watchpoint: u64,
+    pub thread: Option<String>,
+    pub old_value: Option<String>,
+    /// New value on writes, the value read on reads.
+    pub new_value: Option<String>,
+    pub frames: Vec<Value>,
+}
+
+impl Hit {
+    fn to_json(&self, index: usize) -> Value {
+        mi::compact(json!({
+            "hit": index,
+            "watchpoint": self.watchpoint,
+            "thread": self.thread,
+            "old_value": self.old_value,
+            "new_value": self.new_value,
+            "backtrace": self.frames,
+        }))
+    }
+}
+
+/// Watchpoints set through the tools in a session, and the hits collected for them.
+#[derive(Default)]
+pub struct Watchpoints {
+    /// Debug registers taken by each watchpoint, by number.
+    slots: HashMap<u64, usize>,
+    hits: VecDeque<Hit>,
+    /// Hits recorded so far, including dropped ones, to number them.
+    recorded: usize,
+}
+
+impl Watchpoints {
+    /// Check that `needed` more debug registers are free. Watchpoints that are not in `existing` any more, because
+    /// they were deleted with a debugger command, are forgotten first.
+    pub fn check_slots(&mut self, existing: &[u64], needed: usize) -> Result<(), String> {
+        self.slots.retain(|number, _| existing.contains(number));
+        let used: usize = self.slots.values().sum();
+        if used + needed > HARDWARE_SLOTS {
+            let mut numbers: Vec<u64> = self.slots.keys().copied().collect();
+            numbers.sort_unstable();
+            let numbers: Vec<String> = numbers.iter().map(u64::to_string).collect();
+            let in_use = match numbers.as_slice() {
+                [] => String::new(),
+                [number] => format!(", watchpoint {} uses the others", number),
+                numbers => format!(", watchpoints {} use the others", numbers.join(", ")),
+            };
+            return Err(format!(
+                "The watchpoint needs {} of the {} hardware debug registers, which watch up to {} aligned bytes each, \
+                 but only {} free{}. Watch a smaller range or delete a watchpoint",
+                needed,
+                HARDWARE_SLOTS,
+                SLOT_BYTES,
+                match HARDWARE_SLOTS.saturating_sub(used) {
+                    1 => "1 is".to_owned(),
+                    free => format!("{} are", free),
+                },
+                in_use
+            ));
+        }
+        Ok(())
+    }
+
+    /// Keep the debug registers taken by a new watchpoint.
+    pub fn add(&mut self, number: u64, slots: usize) {
+        self.slots.insert(number, slots);
+    }
+
+    pub fn record(&mut self, hit: Hit) {
+        self.recorded += 1;
+        self.hits.push_back(hit);
+        if self.hits.len() > MAX_HITS {
+            self.hits.pop_front();
+        }
+    }
+
+    /// Number of hits recorded so far, to tell the hits of one collection apart from earlier ones.
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// Hits recorded after the first `since`, of the given watchpoint or of all, as JSON.
+    pub fn history_json(&self, watchpoint: Option<u64>, since: usize) -> Value {
+        let dropped = self.recorded - self.hits.len();
+        let hits: Vec<Value> = self
+            .hits
+            .iter()
+            .enumerate()
+            .map(|(index, hit)| (dropped + index + 1, hit))
+            .filter(|(index, hit)| {
+                *index > since && watchpoint.is_none_or(|watchpoint| hit.watchpoint == watchpoint)
+            })
+            .map(|(index, hit)| hit.to_json(index))
+            .collect();
+        mi::compact(json!({
+            "hits": hits,
+            // Hits were dropped to keep the history short.
+            "truncated": (dropped > since).then_some(true),
+        }))
+    }
+}